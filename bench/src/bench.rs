@@ -1,7 +1,7 @@
 use block_mesh::ndshape::{ConstShape, ConstShape3u32};
 use block_mesh::{
-    greedy_quads, visible_block_faces, GreedyQuadsBuffer, MergeVoxel, UnitQuadBuffer, Voxel,
-    VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG,
+    greedy_quads, visible_block_faces, DefaultVoxelContext, GreedyQuadsBuffer, MergeVoxel,
+    UnitQuadBuffer, Voxel, VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG,
 };
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
@@ -21,6 +21,7 @@ fn bench_empty_space_greedy(c: &mut Criterion) {
         [17; 3],
         &RIGHT_HANDED_Y_UP_CONFIG.faces,
         &mut buffer,
+        &DefaultVoxelContext,
     );
 
     group.bench_with_input(
@@ -35,6 +36,7 @@ fn bench_empty_space_greedy(c: &mut Criterion) {
                     [17; 3],
                     &RIGHT_HANDED_Y_UP_CONFIG.faces,
                     &mut buffer,
+                    &DefaultVoxelContext,
                 )
             });
         },
@@ -59,6 +61,7 @@ fn bench_sphere_greedy(c: &mut Criterion) {
         [17; 3],
         &RIGHT_HANDED_Y_UP_CONFIG.faces,
         &mut buffer,
+        &DefaultVoxelContext,
     );
 
     group.bench_with_input(
@@ -73,6 +76,52 @@ fn bench_sphere_greedy(c: &mut Criterion) {
                     [17; 3],
                     &RIGHT_HANDED_Y_UP_CONFIG.faces,
                     &mut buffer,
+                    &DefaultVoxelContext,
+                )
+            });
+        },
+    );
+    group.finish();
+}
+
+type SlabShape = ConstShape3u32<66, 66, 66>;
+
+fn bench_slab_greedy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bench_slab_greedy");
+    // A 64x64x64 chunk with a layer of solid ground at the bottom, so every face merges into a few large quads.
+    let mut samples = vec![EMPTY; SlabShape::USIZE];
+    for i in 0u32..(SlabShape::SIZE) {
+        let [_, y, _] = SlabShape::delinearize(i);
+        if y <= 8 {
+            samples[i as usize] = FULL;
+        }
+    }
+
+    // Do a single run first to allocate the buffer to the right size.
+    let mut buffer = GreedyQuadsBuffer::new(samples.len());
+    greedy_quads(
+        &samples,
+        &SlabShape {},
+        [0; 3],
+        [65; 3],
+        &RIGHT_HANDED_Y_UP_CONFIG.faces,
+        &mut buffer,
+        &DefaultVoxelContext,
+    );
+
+    group.bench_with_input(
+        BenchmarkId::from_parameter(format!("quads={}", buffer.quads.num_quads())),
+        &(),
+        |b, _| {
+            b.iter(|| {
+                greedy_quads(
+                    &samples,
+                    &SlabShape {},
+                    [0; 3],
+                    [65; 3],
+                    &RIGHT_HANDED_Y_UP_CONFIG.faces,
+                    &mut buffer,
+                    &DefaultVoxelContext,
                 )
             });
         },
@@ -92,6 +141,7 @@ fn bench_empty_space_simple(c: &mut Criterion) {
         [17; 3],
         &RIGHT_HANDED_Y_UP_CONFIG.faces,
         &mut buffer,
+        &DefaultVoxelContext,
     );
 
     group.bench_with_input(
@@ -106,6 +156,7 @@ fn bench_empty_space_simple(c: &mut Criterion) {
                     [17; 3],
                     &RIGHT_HANDED_Y_UP_CONFIG.faces,
                     &mut buffer,
+                    &DefaultVoxelContext,
                 )
             });
         },
@@ -129,6 +180,7 @@ fn bench_sphere_simple(c: &mut Criterion) {
         [17; 3],
         &RIGHT_HANDED_Y_UP_CONFIG.faces,
         &mut buffer,
+        &DefaultVoxelContext,
     );
 
     group.bench_with_input(
@@ -143,6 +195,7 @@ fn bench_sphere_simple(c: &mut Criterion) {
                     [17; 3],
                     &RIGHT_HANDED_Y_UP_CONFIG.faces,
                     &mut buffer,
+                    &DefaultVoxelContext,
                 )
             });
         },
@@ -154,6 +207,7 @@ criterion_group!(
     benches,
     bench_sphere_simple,
     bench_sphere_greedy,
+    bench_slab_greedy,
     bench_empty_space_simple,
     bench_empty_space_greedy
 );
@@ -177,10 +231,15 @@ impl Voxel for BoolVoxel {
 
 impl MergeVoxel for BoolVoxel {
     type MergeValue = Self;
+    type MergeValueFacingNeighbour = bool;
 
     fn merge_value(&self) -> Self::MergeValue {
         *self
     }
+
+    fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+        true
+    }
 }
 
 fn sphere_voxel([x, y, z]: [f32; 3]) -> BoolVoxel {
@@ -111,7 +111,7 @@ fn generate_simple_mesh(
     let mut normals = Vec::with_capacity(num_vertices);
     for (group, face) in buffer.groups.into_iter().zip(faces.into_iter()) {
         for quad in group.into_iter() {
-            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
+            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32, false));
             positions.extend_from_slice(&face.quad_mesh_positions(&quad.into(), 1.0));
             normals.extend_from_slice(&face.quad_mesh_normals());
         }
@@ -168,7 +168,7 @@ fn generate_greedy_mesh(
     let mut normals = Vec::with_capacity(num_vertices);
     for (group, face) in buffer.quads.groups.into_iter().zip(faces.into_iter()) {
         for quad in group.into_iter() {
-            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
+            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32, false));
             positions.extend_from_slice(&face.quad_mesh_positions(&quad, 1.0));
             normals.extend_from_slice(&face.quad_mesh_normals());
         }
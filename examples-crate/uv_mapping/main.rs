@@ -127,7 +127,7 @@ fn setup(
     let mut tex_coords = Vec::with_capacity(num_vertices);
     for (group, face) in buffer.quads.groups.into_iter().zip(faces.into_iter()) {
         for quad in group.into_iter() {
-            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
+            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32, false));
             positions.extend_from_slice(&face.quad_mesh_positions(&quad, 1.0));
             normals.extend_from_slice(&face.quad_mesh_normals());
             tex_coords.extend_from_slice(&face.tex_coords(
@@ -0,0 +1,405 @@
+use crate::{
+    bounds::assert_in_bounds,
+    simple::{face_is_visible_for_face, VisibleFaceMask},
+    Axis, MeshData, QuadCoordinateConfig, SignedAxis, UnorientedQuad, VoxelContext, VoxelSource,
+};
+
+use ilattice::glam::{IVec3, Vec3};
+use ndshape::Shape;
+
+/// Same as [`visible_block_faces`](crate::visible_block_faces), but every convex edge, where two perpendicular visible
+/// faces of the same voxel meet, is cut off by a 45° chamfer that is `bevel` voxels wide on each face. The result is
+/// written directly to `output` in units of `voxel_size`.
+///
+/// Faces are shrunk by `bevel` on every side with a chamfer. Where three visible faces meet at a corner, the chamfers
+/// are joined by a triangle, and where a chamfer ends because the edge stops being convex, the notch is closed by a
+/// triangle in the plane between the two voxels. Concave edges are not changed. Every triangle is emitted as a quad
+/// with a duplicated edge midpoint, so `output` keeps the layout of 4 vertices and 6 indices per quad.
+///
+/// Chamfers use the [`VoxelContext::color`] of the first of their two faces, and their UVs span their size in voxels
+/// like the UVs of regular faces. The UVs of shrunk faces are not trimmed.
+///
+/// `bevel` must be in `(0.0, 0.5]`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<3, 3, 3>;
+///
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+///
+/// let mut mesh = MeshData::new();
+/// visible_block_faces_beveled(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [2; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG,
+///     1.0,
+///     0.1,
+///     &mut mesh,
+///     &DefaultVoxelContext,
+/// );
+/// // 6 faces, 12 edge chamfers, and 8 corner triangles.
+/// assert_eq!(mesh.positions.len(), 4 * (6 + 12 + 8));
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn visible_block_faces_beveled<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    config: &QuadCoordinateConfig,
+    voxel_size: f32,
+    bevel: f32,
+    output: &mut MeshData,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+    assert!(
+        bevel > 0.0 && bevel <= 0.5,
+        "bevel={bevel} must be in (0.0, 0.5]"
+    );
+
+    let visible_faces = VisibleFaceMask::new(voxels, voxels_shape, min, max, &config.faces, ctx);
+
+    let face_indices = DIRECTIONS.map(|direction| config.face_index(direction));
+    let opposite_face_indices = DIRECTIONS.map(|direction| config.face_index(opposite(direction)));
+    let strides = DIRECTIONS
+        .map(|direction| voxels_shape.linearize(direction.get_unit_vector().as_uvec3().to_array()));
+
+    // The visibility of the face of the voxel at `index` with the normal `direction`. Unlike `visible_faces`, this also
+    // works for the voxels on the boundary of `[min, max]`, where a chamfer can continue into the next chunk.
+    let is_visible = |index: u32, direction: SignedAxis| {
+        let d = direction as usize;
+        let voxel = unsafe { voxels.get_unchecked(index as usize) };
+        let neighbor = unsafe { voxels.get_unchecked(index.wrapping_add(strides[d]) as usize) };
+        face_is_visible_for_face(
+            voxel,
+            neighbor,
+            face_indices[d],
+            opposite_face_indices[d],
+            ctx,
+        )
+    };
+
+    for p in visible_faces.interior().iter3() {
+        let visible = DIRECTIONS
+            .map(|direction| visible_faces.is_visible(p, face_indices[direction as usize]));
+        if !visible.contains(&true) {
+            continue;
+        }
+        let p_index = voxels_shape.linearize(p.to_array());
+        let p_voxel = unsafe { voxels.get_unchecked(p_index as usize) };
+        let center = p.as_vec3() + 0.5;
+
+        // Faces, shrunk on the sides with a chamfer.
+        for direction in DIRECTIONS {
+            if !visible[direction as usize] {
+                continue;
+            }
+            let mut min_inset = Vec3::ZERO;
+            let mut max_inset = Vec3::ZERO;
+            for side in perpendicular(direction) {
+                if visible[side as usize] {
+                    let i = side.unsigned_axis().index();
+                    if side.signum() > 0 {
+                        max_inset[i] = bevel;
+                    } else {
+                        min_inset[i] = bevel;
+                    }
+                }
+            }
+            let face_index = face_indices[direction as usize];
+            let quad = UnorientedQuad {
+                minimum: p.to_array(),
+                width: 1,
+                height: 1,
+            };
+            output.push_inset_quad(
+                &config.faces[face_index],
+                &quad,
+                config.u_flip_face,
+                voxel_size,
+                min_inset,
+                max_inset,
+            );
+            output.set_last_quad_color(ctx.color(p_voxel, face_index));
+        }
+
+        // Edge chamfers and the caps where they end.
+        for a in DIRECTIONS {
+            for b in perpendicular(a) {
+                if (b as u8) < (a as u8) || !visible[a as usize] || !visible[b as usize] {
+                    continue;
+                }
+                let (n_a, n_b) = (unit(a), unit(b));
+                let color = ctx.color(p_voxel, face_indices[a as usize]);
+
+                let ends = [-1, 1].map(|sign| {
+                    let end = SignedAxis::new(sign, third_axis(a, b));
+                    let on_edge = center + 0.5 * (n_a + n_b + unit(end));
+                    // Shorten the chamfer where it meets a corner triangle.
+                    let t = if visible[end as usize] { bevel } else { 0.0 };
+                    let on_edge = on_edge - t * unit(end);
+                    (end, on_edge, on_edge - bevel * n_b, on_edge - bevel * n_a)
+                });
+                let [(_, _, a0, b0), (_, _, a1, b1)] = ends;
+                push_polygon(
+                    output,
+                    [a0, a1, b1, b0],
+                    (n_a + n_b).normalize(),
+                    voxel_size,
+                    color,
+                );
+
+                for (end, on_edge, on_a, on_b) in ends {
+                    if visible[end as usize] {
+                        continue;
+                    }
+                    let neighbor_index = p_index.wrapping_add(strides[end as usize]);
+                    if is_visible(neighbor_index, a) && is_visible(neighbor_index, b) {
+                        // The chamfer continues into the neighbor.
+                        continue;
+                    }
+                    push_triangle(output, [on_a, on_b, on_edge], -unit(end), voxel_size, color);
+                }
+            }
+        }
+
+        // Corner triangles.
+        for [x, y, z] in CORNERS {
+            let corner = [
+                SignedAxis::new(x, Axis::X),
+                SignedAxis::new(y, Axis::Y),
+                SignedAxis::new(z, Axis::Z),
+            ];
+            if !corner.iter().all(|&direction| visible[direction as usize]) {
+                continue;
+            }
+            let [n_x, n_y, n_z] = corner.map(unit);
+            let point = center + 0.5 * (n_x + n_y + n_z);
+            push_triangle(
+                output,
+                [
+                    point - bevel * (n_y + n_z),
+                    point - bevel * (n_x + n_z),
+                    point - bevel * (n_x + n_y),
+                ],
+                (n_x + n_y + n_z).normalize(),
+                voxel_size,
+                ctx.color(p_voxel, face_indices[corner[0] as usize]),
+            );
+        }
+    }
+}
+
+const DIRECTIONS: [SignedAxis; 6] = [
+    SignedAxis::NegX,
+    SignedAxis::PosX,
+    SignedAxis::NegY,
+    SignedAxis::PosY,
+    SignedAxis::NegZ,
+    SignedAxis::PosZ,
+];
+
+const CORNERS: [[i32; 3]; 8] = [
+    [-1, -1, -1],
+    [1, -1, -1],
+    [-1, 1, -1],
+    [1, 1, -1],
+    [-1, -1, 1],
+    [1, -1, 1],
+    [-1, 1, 1],
+    [1, 1, 1],
+];
+
+fn unit(direction: SignedAxis) -> Vec3 {
+    direction.get_unit_vector().as_vec3()
+}
+
+fn opposite(direction: SignedAxis) -> SignedAxis {
+    SignedAxis::new(-direction.signum(), direction.unsigned_axis())
+}
+
+/// The 4 directions that are perpendicular to `direction`.
+fn perpendicular(direction: SignedAxis) -> impl Iterator<Item = SignedAxis> {
+    DIRECTIONS
+        .into_iter()
+        .filter(move |d| d.unsigned_axis() != direction.unsigned_axis())
+}
+
+/// The axis that is perpendicular to both `a` and `b`.
+fn third_axis(a: SignedAxis, b: SignedAxis) -> Axis {
+    let axis = IVec3::ONE - a.get_unit_vector().abs() - b.get_unit_vector().abs();
+    SignedAxis::from_vector(axis).unwrap().unsigned_axis()
+}
+
+/// Appends a planar polygon with 4 corners in cyclic order, winding it counterclockwise around `normal`.
+fn push_polygon(
+    mesh: &mut MeshData,
+    corners: [Vec3; 4],
+    normal: Vec3,
+    voxel_size: f32,
+    color: [u8; 4],
+) {
+    let [c0, c1, c2, _] = corners;
+    let start = mesh.positions.len() as u32;
+    if (c1 - c0).cross(c2 - c0).dot(normal) > 0.0 {
+        mesh.indices
+            .extend_from_slice(&[0, 1, 2, 0, 2, 3].map(|i| start + i));
+    } else {
+        mesh.indices
+            .extend_from_slice(&[0, 2, 1, 0, 3, 2].map(|i| start + i));
+    }
+
+    // UVs measure the polygon in voxels, starting at the first corner.
+    let u = (c1 - c0).normalize();
+    let v = normal.cross(u);
+    let uvs = corners.map(|c| [(c - c0).dot(u), (c - c0).dot(v)]);
+    let (mut uv_min, mut uv_max) = ([f32::MAX; 2], [f32::MIN; 2]);
+    for uv in uvs {
+        for i in 0..2 {
+            uv_min[i] = uv_min[i].min(uv[i]);
+            uv_max[i] = uv_max[i].max(uv[i]);
+        }
+    }
+
+    mesh.positions
+        .extend(corners.map(|c| (voxel_size * c).to_array()));
+    mesh.normals.extend_from_slice(&[normal.to_array(); 4]);
+    mesh.tex_coords.extend_from_slice(&uvs);
+    let extent = [
+        voxel_size * (uv_max[0] - uv_min[0]),
+        voxel_size * (uv_max[1] - uv_min[1]),
+    ];
+    mesh.quad_extents.extend_from_slice(&[extent; 4]);
+    mesh.colors.extend_from_slice(&[color; 4]);
+}
+
+/// Appends a triangle as a polygon with a 4th corner in the middle of the edge from `c1` to `c2`.
+fn push_triangle(
+    mesh: &mut MeshData,
+    [c0, c1, c2]: [Vec3; 3],
+    normal: Vec3,
+    voxel_size: f32,
+    color: [u8; 4],
+) {
+    push_polygon(
+        mesh,
+        [c0, c1, (c1 + c2) / 2.0, c2],
+        normal,
+        voxel_size,
+        color,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{EMPTY, FULL};
+    use crate::{testing::validate_outward_normals, DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    #[test]
+    fn beveled_cube_is_closed() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1; 3]) as usize] = FULL;
+
+        let mut mesh = MeshData::new();
+        visible_block_faces_beveled(
+            &samples,
+            &shape,
+            [0; 3],
+            [5; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG,
+            1.0,
+            0.25,
+            &mut mesh,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(validate_outward_normals(&mesh), Ok(()));
+        assert_eq!(mesh.positions.len(), 4 * (6 + 12 + 8));
+
+        assert_closed(&mesh);
+        // A cube without its edges and corners.
+        let volume = enclosed_volume(&mesh);
+        assert!(volume > 1.0 - 12.0 * 0.25 * 0.25 && volume < 1.0 - 12.0 * 0.25 * 0.25 * 0.5 * 0.5);
+    }
+
+    #[test]
+    fn steps_are_wound_outward() {
+        // A staircase, so chamfers end against concave edges.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for [x, y, z] in [
+            [1, 1, 1],
+            [2, 1, 1],
+            [2, 2, 1],
+            [3, 1, 1],
+            [3, 2, 1],
+            [3, 3, 1],
+        ] {
+            samples[shape.linearize([x, y, z]) as usize] = FULL;
+        }
+
+        let mut mesh = MeshData::new();
+        visible_block_faces_beveled(
+            &samples,
+            &shape,
+            [0; 3],
+            [5; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG,
+            1.0,
+            0.25,
+            &mut mesh,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(validate_outward_normals(&mesh), Ok(()));
+        assert_closed(&mesh);
+        assert!(mesh
+            .positions
+            .iter()
+            .all(|p| p.iter().all(|&c| (1.0..=4.0).contains(&c))));
+    }
+
+    /// The area vectors of the triangles of a closed surface add up to zero, even with T-junctions.
+    fn assert_closed(mesh: &MeshData) {
+        let sum = mesh
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.positions[triangle[i] as usize]));
+                (b - a).cross(c - a)
+            })
+            .fold(Vec3::ZERO, |sum, area| sum + area);
+        assert!(sum.length() < 1e-4, "surface is not closed: {sum:?}");
+    }
+
+    fn enclosed_volume(mesh: &MeshData) -> f32 {
+        mesh.indices
+            .chunks(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.positions[triangle[i] as usize]));
+                a.dot(b.cross(c)) / 6.0
+            })
+            .sum()
+    }
+
+    type SampleShape = ConstShape3u32<6, 6, 6>;
+}
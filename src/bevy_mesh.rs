@@ -0,0 +1,41 @@
+use crate::{MeshData, QuadBuffer, QuadCoordinateConfig};
+
+use bevy_render::mesh::{Indices, Mesh};
+use bevy_render::render_asset::RenderAssetUsages;
+use bevy_render::render_resource::PrimitiveTopology;
+
+impl From<MeshData> for Mesh {
+    fn from(data: MeshData) -> Self {
+        let MeshData {
+            positions,
+            normals,
+            tex_coords,
+            // Not a standard Bevy attribute; insert it with a custom `MeshVertexAttribute` if your shader needs it.
+            quad_extents: _,
+            colors,
+            indices,
+        } = data;
+
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, tex_coords);
+        let colors: Vec<[f32; 4]> = colors
+            .into_iter()
+            .map(|color| color.map(|c| c as f32 / 255.0))
+            .collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.insert_indices(Indices::U32(indices));
+        mesh
+    }
+}
+
+/// Converts the quads into a Bevy [`Mesh`] with positions, normals, UVs, white vertex colors, and a triangle list.
+///
+/// This is equivalent to `Mesh::from(quads.to_mesh_data(config, voxel_size))`.
+pub fn to_bevy_mesh(quads: &QuadBuffer, config: &QuadCoordinateConfig, voxel_size: f32) -> Mesh {
+    quads.to_mesh_data(config, voxel_size).into()
+}
@@ -0,0 +1,267 @@
+use crate::{
+    bounds::{assert_extent_in_shape, assert_in_bounds},
+    for_each_visible_face, greedy_quads, ContextAdapter, ForwardingContext, GreedyQuadsBuffer,
+    MergeContextAdapter, MergeVoxelContext, OrientedBlockFace, UnitQuadBuffer, UnorientedUnitQuad,
+    VoxelContext, VoxelSource, VoxelVisibility,
+};
+
+use ndshape::Shape;
+
+/// Returns `true` iff the voxel at linear index `index` is set in the packed `occupancy` bits.
+///
+/// Voxel `i` is bit `i % 64` of `occupancy[i / 64]`, where bit 0 is the least significant bit.
+#[inline]
+pub fn is_occupied(occupancy: &[u64], index: u32) -> bool {
+    occupancy[(index / 64) as usize] & (1 << (index % 64)) != 0
+}
+
+/// Same as [`visible_block_faces`](crate::visible_block_faces), but the voxels are given as packed occupancy bits (see
+/// [`is_occupied`]). Occupied voxels are opaque and all other voxels are empty.
+///
+/// This avoids touching your full voxel data when you already maintain a separate occupancy bitset.
+pub fn visible_block_faces_from_bitmask<S>(
+    occupancy: &[u64],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut UnitQuadBuffer,
+) where
+    S: Shape<3, Coord = u32>,
+{
+    assert_occupancy_in_bounds(occupancy, voxels_shape, min, max);
+
+    let voxels = OccupancyBits {
+        occupancy,
+        len: voxels_shape.usize(),
+    };
+    for_each_visible_face(
+        &voxels,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        &OccupancyContext,
+        |p, _, face_index| {
+            output.groups[face_index].push(UnorientedUnitQuad { minimum: p });
+        },
+    );
+}
+
+/// Same as [`greedy_quads`], but visibility is read from packed occupancy bits (see [`is_occupied`]) instead of `ctx`.
+/// Occupied voxels are opaque and all other voxels are empty. `voxels` is only read for merge values and
+/// [`VoxelContext::face_inset`].
+///
+/// This allocates an array of voxel indices on every call.
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_from_bitmask<T, S, C>(
+    occupancy: &[u64],
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+    assert_occupancy_in_bounds(occupancy, voxels_shape, min, max);
+
+    // The index array has the same shape as the voxels, so every stride and quad is the same.
+    let indices: Vec<u32> = (0..voxels_shape.size()).collect();
+    let bitmask_ctx = ForwardingContext(BitmaskContext {
+        occupancy,
+        voxels,
+        ctx,
+    });
+    greedy_quads(
+        &indices,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        output,
+        &bitmask_ctx,
+    );
+}
+
+fn assert_occupancy_in_bounds<S>(occupancy: &[u64], voxels_shape: &S, min: [u32; 3], max: [u32; 3])
+where
+    S: Shape<3, Coord = u32>,
+{
+    let num_words = voxels_shape.usize().div_ceil(64);
+    assert!(
+        num_words <= occupancy.len(),
+        "occupancy has {} words but the shape requires {num_words}; would cause access out of bounds",
+        occupancy.len()
+    );
+    assert_extent_in_shape(voxels_shape, min, max);
+}
+
+/// The packed occupancy bits as a source of `bool` voxels.
+struct OccupancyBits<'a> {
+    occupancy: &'a [u64],
+    len: usize,
+}
+
+impl VoxelSource for OccupancyBits<'_> {
+    type Voxel = bool;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    unsafe fn get_unchecked(&self, index: usize) -> &bool {
+        if is_occupied(self.occupancy, index as u32) {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+/// Occupied voxels are opaque and all other voxels are empty.
+struct OccupancyContext;
+
+impl VoxelContext<bool> for OccupancyContext {
+    #[inline]
+    fn get_visibility(&self, occupied: &bool) -> VoxelVisibility {
+        if *occupied {
+            VoxelVisibility::Opaque
+        } else {
+            VoxelVisibility::Empty
+        }
+    }
+}
+
+/// Reads visibility from `occupancy` and everything else from the voxel at each index.
+struct BitmaskContext<'a, T, C> {
+    occupancy: &'a [u64],
+    voxels: &'a [T],
+    ctx: &'a C,
+}
+
+impl<'a, T, C> ContextAdapter<u32> for BitmaskContext<'a, T, C>
+where
+    C: VoxelContext<T>,
+{
+    type Voxel = T;
+    type Inner = C;
+
+    #[inline]
+    fn inner(&self) -> &C {
+        self.ctx
+    }
+
+    #[inline]
+    fn voxel<'b>(&'b self, index: &'b u32) -> Option<&'b T> {
+        Some(&self.voxels[*index as usize])
+    }
+
+    #[inline]
+    fn get_visibility(&self, index: &u32) -> VoxelVisibility {
+        if is_occupied(self.occupancy, *index) {
+            VoxelVisibility::Opaque
+        } else {
+            VoxelVisibility::Empty
+        }
+    }
+
+    #[inline]
+    fn get_visibility_for_face(&self, index: &u32, _face_index: usize) -> VoxelVisibility {
+        // The occupancy bits are the same through every face.
+        self.get_visibility(index)
+    }
+}
+
+impl<'a, T, C> MergeContextAdapter<u32> for BitmaskContext<'a, T, C>
+where
+    C: MergeVoxelContext<T>,
+{
+    type MergeValue = C::MergeValue;
+    type MergeValueFacingNeighbour = C::MergeValueFacingNeighbour;
+
+    #[inline]
+    fn merge_value(&self, index: &u32) -> Self::MergeValue {
+        self.ctx.merge_value(&self.voxels[*index as usize])
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, index: &u32) -> Self::MergeValueFacingNeighbour {
+        self.ctx
+            .merge_value_facing_neighbour(&self.voxels[*index as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, SampleShape, EMPTY};
+    use crate::{visible_block_faces, DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ilattice::glam::UVec3;
+    use ndshape::ConstShape;
+
+    #[test]
+    fn bitmask_matches_voxel_visibility() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        let mut occupancy = vec![0u64; SampleShape::USIZE.div_ceil(64)];
+        for i in 0..SampleShape::SIZE {
+            let p = UVec3::from(shape.delinearize(i)).as_vec3() - 16.0;
+            if p.length() < 15.0 {
+                // Vary the merge value so the voxel data still matters.
+                samples[i as usize] = BoolVoxel(true, p.y > 0.0);
+                occupancy[(i / 64) as usize] |= 1 << (i % 64);
+            }
+        }
+
+        let mut expected_unit = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut expected_unit,
+            &DefaultVoxelContext,
+        );
+        let mut unit = UnitQuadBuffer::new();
+        visible_block_faces_from_bitmask(
+            &occupancy,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut unit,
+        );
+        assert_eq!(unit.groups, expected_unit.groups);
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_from_bitmask(
+            &occupancy,
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(buffer.quads.groups, expected.quads.groups);
+    }
+}
@@ -1,17 +1,41 @@
+use crate::VoxelSource;
+
 use ilattice::glam::UVec3;
 use ilattice::prelude::Extent;
 use ndshape::Shape;
 
-pub fn assert_in_bounds<T, S>(voxels: &[T], voxels_shape: &S, min: [u32; 3], max: [u32; 3])
+pub fn assert_in_bounds<V, S>(voxels: &V, voxels_shape: &S, min: [u32; 3], max: [u32; 3])
 where
+    V: VoxelSource + ?Sized,
     S: Shape<3, Coord = u32>,
 {
+    assert_voxels_fit_shape(voxels, voxels_shape);
+    assert_extent_in_shape(voxels_shape, min, max);
+}
+
+pub fn assert_voxels_fit_shape<V, S>(voxels: &V, voxels_shape: &S)
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+{
+    let shape = voxels_shape.as_array();
+    let size = shape.iter().map(|&d| d as u64).product::<u64>();
+    assert!(
+        size <= u32::MAX as u64,
+        "shape={shape:?} has {size} voxels, which can't be indexed with u32 strides"
+    );
     assert!(
         voxels_shape.size() as usize <= voxels.len(),
         "voxel buffer size {:?} is less than the shape size {:?}; would cause access out of bounds",
         voxels.len(),
         voxels_shape.size()
     );
+}
+
+pub fn assert_extent_in_shape<S>(voxels_shape: &S, min: [u32; 3], max: [u32; 3])
+where
+    S: Shape<3, Coord = u32>,
+{
     let shape = voxels_shape.as_array();
     let local_extent = Extent::from_min_and_shape(UVec3::ZERO, UVec3::from(shape));
     local_extent
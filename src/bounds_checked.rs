@@ -0,0 +1,495 @@
+use crate::{
+    bounds::assert_voxels_fit_shape, greedy_quads, ContextAdapter, ForwardingContext,
+    GreedyQuadsBuffer, MergeContextAdapter, MergeVoxelContext, OrientedBlockFace, PaddedChunkShape,
+    VoxelContext,
+};
+
+use ilattice::glam::{IVec3, UVec3};
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// Marks a voxel outside of the unpadded shape.
+const OUT_OF_BOUNDS: u32 = u32::MAX;
+
+/// Same as [`greedy_quads`], but meshes every voxel in `voxels_shape`, so `voxels` doesn't need any padding. Neighbors
+/// outside of the shape are treated as [`VoxelVisibility::Empty`], so faces on the boundary are always meshed.
+///
+/// The quads are in the coordinates of `voxels_shape`.
+///
+/// This is slower than [`greedy_quads`] because it builds a padded array of voxel indices on every call, where the
+/// padding refers to no voxel, and meshes that instead. Prefer padding your chunks if meshing speed matters.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<16, 16, 16>;
+///
+/// let voxels = [BoolVoxel(true); ChunkShape::SIZE as usize];
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_bounds_checked(
+///     &voxels,
+///     &ChunkShape {},
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// // The boundary faces of the chunk are meshed.
+/// assert!(buffer.quads.groups.iter().flatten().all(|quad| quad.width == 16 && quad.height == 16));
+/// ```
+///
+/// [`VoxelVisibility::Empty`]: crate::VoxelVisibility::Empty
+pub fn greedy_quads_bounds_checked<T, S, C>(
+    voxels: &[T],
+    voxels_shape: &S,
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    greedy_quads_bounds_checked_with_wrap_mode(
+        voxels,
+        voxels_shape,
+        faces,
+        WrapMode::TreatAsEmpty,
+        output,
+        ctx,
+    )
+}
+
+/// Which voxel is the neighbor of a voxel on the boundary of the shape, in the direction that leaves the shape.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum WrapMode {
+    /// The boundary voxel is its own neighbor, as if the boundary were repeated forever. Faces on the boundary are never
+    /// meshed, since a voxel never has a visible face towards itself.
+    Clamp,
+    /// The neighbor is the voxel on the opposite boundary, as in a toroidal world. A world that fits in a single chunk
+    /// then meshes without faces at the seams.
+    Wrap,
+    /// The neighbor is [`VoxelVisibility::Empty`], so faces on the boundary are always meshed.
+    ///
+    /// [`VoxelVisibility::Empty`]: crate::VoxelVisibility::Empty
+    #[default]
+    TreatAsEmpty,
+}
+
+impl WrapMode {
+    /// The voxel of a shape with `shape` voxels on each axis that is read for the coordinates `p`, which are at most 1
+    /// voxel outside of the shape, or `None` if the neighbor is empty.
+    fn resolve(self, p: IVec3, shape: UVec3) -> Option<UVec3> {
+        let shape = shape.as_ivec3();
+        if p.cmpge(IVec3::ZERO).all() && p.cmplt(shape).all() {
+            return Some(p.as_uvec3());
+        }
+        match self {
+            Self::Clamp => Some(p.clamp(IVec3::ZERO, shape - IVec3::ONE).as_uvec3()),
+            Self::Wrap => Some(
+                IVec3::new(
+                    p.x.rem_euclid(shape.x),
+                    p.y.rem_euclid(shape.y),
+                    p.z.rem_euclid(shape.z),
+                )
+                .as_uvec3(),
+            ),
+            Self::TreatAsEmpty => None,
+        }
+    }
+}
+
+/// Same as [`greedy_quads_bounds_checked`], but the neighbors of the voxels on the boundary of the shape are chosen by
+/// `wrap_mode`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type WorldShape = ConstShape3u32<16, 16, 16>;
+///
+/// // A floor that spans the whole world along X and Z.
+/// let mut voxels = [BoolVoxel(false); WorldShape::SIZE as usize];
+/// for i in 0..WorldShape::SIZE {
+///     let [_, y, _] = WorldShape::delinearize(i);
+///     voxels[i as usize] = BoolVoxel(y == 4);
+/// }
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_bounds_checked_with_wrap_mode(
+///     &voxels,
+///     &WorldShape {},
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     WrapMode::Wrap,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// // Only the top and bottom of the floor are meshed, without any faces at the seams.
+/// assert_eq!(buffer.quads.num_quads(), 2);
+/// ```
+pub fn greedy_quads_bounds_checked_with_wrap_mode<T, S, C>(
+    voxels: &[T],
+    voxels_shape: &S,
+    faces: &[OrientedBlockFace; 6],
+    wrap_mode: WrapMode,
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    greedy_quads_with_padding(
+        voxels,
+        voxels_shape,
+        [false; 6],
+        faces,
+        wrap_mode,
+        output,
+        ctx,
+    )
+}
+
+/// Same as [`greedy_quads`], but `voxels` only has padding on some sides, e.g. for a world with a bedrock floor that
+/// has no chunk below it. `padding[i]` is `true` iff `voxels` has a layer of padding on the side that `faces[i]` points
+/// towards. Every voxel of `voxels_shape` that isn't padding is meshed, and the neighbors beyond the sides without
+/// padding are chosen by `wrap_mode` as in [`greedy_quads_bounds_checked_with_wrap_mode`]. With [`WrapMode::Wrap`], the
+/// opposite boundary is the last layer of `voxels_shape`, even if it is padding.
+///
+/// The quads are in the coordinates of `voxels_shape`. This builds a padded array of voxel indices like
+/// [`greedy_quads_bounds_checked`], so it is slower than [`greedy_quads`].
+///
+/// Panics if `voxels_shape` has no voxels left after removing the padding.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// // A 16^3 chunk at the bottom of the world, padded on every side but the bottom.
+/// type ChunkShape = ConstShape3u32<18, 17, 18>;
+///
+/// // A floor of bedrock, which continues into the neighboring chunks.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     voxels[i as usize] = BoolVoxel(ChunkShape::delinearize(i)[1] == 0);
+/// }
+///
+/// let config = &RIGHT_HANDED_Y_UP_CONFIG;
+/// let mut padding = [true; 6];
+/// padding[config.face_index(SignedAxis::NegY)] = false;
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_with_padding(
+///     &voxels,
+///     &ChunkShape {},
+///     padding,
+///     &config.faces,
+///     WrapMode::Clamp,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// // Only the top of the floor is meshed; nothing can see the bottom of the world.
+/// assert_eq!(buffer.quads.num_quads(), 1);
+/// let top = &buffer.quads.groups[config.face_index(SignedAxis::PosY)][0];
+/// assert_eq!((top.minimum, top.width, top.height), ([1, 0, 1], 16, 16));
+/// ```
+pub fn greedy_quads_with_padding<T, S, C>(
+    voxels: &[T],
+    voxels_shape: &S,
+    padding: [bool; 6],
+    faces: &[OrientedBlockFace; 6],
+    wrap_mode: WrapMode,
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    assert_voxels_fit_shape(voxels, voxels_shape);
+
+    // The number of layers of padding that `voxels` has on the minimum and maximum side of each axis.
+    let mut padding_min = UVec3::ZERO;
+    let mut padding_max = UVec3::ZERO;
+    for (face, &padded) in faces.iter().zip(padding.iter()) {
+        let side = if face.is_positive() {
+            &mut padding_max
+        } else {
+            &mut padding_min
+        };
+        side[face.axis().index()] = u32::from(padded);
+    }
+
+    let shape = UVec3::from(voxels_shape.as_array());
+    let dims = (shape.as_ivec3() - (padding_min + padding_max).as_ivec3()).max(IVec3::ZERO);
+    let padded_shape = PaddedChunkShape::new(dims.as_uvec3().to_array());
+    let mut indices = vec![OUT_OF_BOUNDS; padded_shape.usize()];
+    let padded_extent =
+        Extent::from_min_and_shape(UVec3::ZERO, UVec3::from(padded_shape.as_array()));
+    // Point `p` of the padded array is the voxel at `p - offset`.
+    let offset = IVec3::ONE - padding_min.as_ivec3();
+    for p in padded_extent.iter3() {
+        if let Some(source) = wrap_mode.resolve(p.as_ivec3() - offset, shape) {
+            indices[padded_shape.linearize(p.to_array()) as usize] =
+                voxels_shape.linearize(source.to_array());
+        }
+    }
+
+    let bounds_checked_ctx = ForwardingContext(BoundsCheckedContext {
+        voxels,
+        voxels_shape,
+        ctx,
+    });
+    greedy_quads(
+        &indices,
+        &padded_shape,
+        padded_shape.min(),
+        padded_shape.max(),
+        faces,
+        output,
+        &bounds_checked_ctx,
+    );
+
+    // Remove the padding offset.
+    for quad in output.quads.groups.iter_mut().flatten() {
+        quad.minimum = (UVec3::from(quad.minimum) + padding_min - UVec3::ONE).to_array();
+    }
+}
+
+/// Reads the voxel at each index from `voxels`, treating [`OUT_OF_BOUNDS`] as an empty voxel.
+struct BoundsCheckedContext<'a, T, S, C> {
+    voxels: &'a [T],
+    voxels_shape: &'a S,
+    ctx: &'a C,
+}
+
+impl<'a, T, S, C> ContextAdapter<u32> for BoundsCheckedContext<'a, T, S, C>
+where
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<T>,
+{
+    type Voxel = T;
+    type Inner = C;
+
+    #[inline]
+    fn inner(&self) -> &C {
+        self.ctx
+    }
+
+    #[inline]
+    fn voxel<'b>(&'b self, index: &'b u32) -> Option<&'b T> {
+        (*index != OUT_OF_BOUNDS).then(|| &self.voxels[*index as usize])
+    }
+
+    #[inline]
+    fn position(&self, index: &u32, _: [u32; 3]) -> [u32; 3] {
+        // The position passed in is in the padded index array, so find the voxel's position in `voxels` instead.
+        self.voxels_shape.delinearize(*index)
+    }
+}
+
+impl<'a, T, S, C> MergeContextAdapter<u32> for BoundsCheckedContext<'a, T, S, C>
+where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    // Out-of-bounds neighbors still need a merge value facing the voxels on the boundary.
+    type MergeValue = Option<C::MergeValue>;
+    type MergeValueFacingNeighbour = Option<C::MergeValueFacingNeighbour>;
+
+    #[inline]
+    fn merge_value(&self, index: &u32) -> Self::MergeValue {
+        self.voxel(index).map(|voxel| self.ctx.merge_value(voxel))
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, index: &u32) -> Self::MergeValueFacingNeighbour {
+        self.voxel(index)
+            .map(|voxel| self.ctx.merge_value_facing_neighbour(voxel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, EMPTY};
+    use crate::{DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    #[test]
+    fn matches_meshing_a_padded_copy() {
+        // A sphere that is cut off by the boundary of the chunk.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let p = UVec3::from(shape.delinearize(i)).as_vec3() - 4.0;
+            if p.length() < 12.0 {
+                samples[i as usize] = BoolVoxel(true, p.y > 0.0);
+            }
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_bounds_checked(
+            &samples,
+            &shape,
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        let padded_shape = PaddedShape {};
+        let mut padded = [EMPTY; PaddedShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let p = UVec3::from(shape.delinearize(i)) + UVec3::ONE;
+            padded[padded_shape.linearize(p.to_array()) as usize] = samples[i as usize];
+        }
+        let mut expected = GreedyQuadsBuffer::new(padded.len());
+        greedy_quads(
+            &padded,
+            &padded_shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+
+        assert!(buffer.quads.num_quads() > 0);
+        for (group, expected_group) in buffer.quads.groups.iter().zip(expected.quads.groups.iter())
+        {
+            assert_eq!(group.len(), expected_group.len());
+            for (quad, expected_quad) in group.iter().zip(expected_group.iter()) {
+                assert_eq!(quad.minimum, expected_quad.minimum.map(|c| c - 1));
+                assert_eq!(
+                    (quad.width, quad.height),
+                    (expected_quad.width, expected_quad.height)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn full_padding_matches_greedy_quads() {
+        let shape = PaddedShape {};
+        let mut samples = [EMPTY; PaddedShape::SIZE as usize];
+        for i in 0..PaddedShape::SIZE {
+            let p = UVec3::from(shape.delinearize(i)).as_vec3() - 16.0;
+            samples[i as usize] = BoolVoxel(p.length() < 18.0, p.x > 0.0);
+        }
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_padding(
+            &samples,
+            &shape,
+            [true; 6],
+            faces,
+            WrapMode::TreatAsEmpty,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        assert!(expected.quads.num_quads() > 0);
+        assert_eq!(buffer.quads.groups, expected.quads.groups);
+    }
+
+    #[test]
+    fn wrapped_faces_match_an_interior_copy() {
+        // A torus-like world: every voxel whose coordinates sum to a multiple of 3 is solid, which tiles seamlessly.
+        let shape = SmallShape {};
+        let mut samples = [EMPTY; SmallShape::SIZE as usize];
+        for i in 0..SmallShape::SIZE {
+            let [x, y, z] = shape.delinearize(i);
+            samples[i as usize] = BoolVoxel((x + y + z) % 3 == 0, false);
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_bounds_checked_with_wrap_mode(
+            &samples,
+            &shape,
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            WrapMode::Wrap,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        // Every solid voxel is surrounded by empty voxels, so each one has all 6 faces.
+        let num_solid = samples.iter().filter(|voxel| voxel.0).count();
+        assert_eq!(buffer.quads.num_quads(), 6 * num_solid);
+
+        // Clamping hides the faces on the boundary.
+        greedy_quads_bounds_checked_with_wrap_mode(
+            &samples,
+            &shape,
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            WrapMode::Clamp,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let boundary_faces = RIGHT_HANDED_Y_UP_CONFIG
+            .faces
+            .iter()
+            .map(|face| {
+                let i_n = face.axis().index();
+                let boundary = if face.is_positive() { 5 } else { 0 };
+                (0..SmallShape::SIZE)
+                    .filter(|&i| samples[i as usize].0 && shape.delinearize(i)[i_n] == boundary)
+                    .count()
+            })
+            .sum::<usize>();
+        assert_eq!(buffer.quads.num_quads(), 6 * num_solid - boundary_faces);
+    }
+
+    type SmallShape = ConstShape3u32<6, 6, 6>;
+    type SampleShape = ConstShape3u32<32, 32, 32>;
+    type PaddedShape = ConstShape3u32<34, 34, 34>;
+}
@@ -0,0 +1,338 @@
+use crate::{
+    bounds::assert_in_bounds, shell::interior_extent, OrientedBlockFace, QuadBuffer,
+    UnorientedQuad, VoxelContext, VoxelSource, VoxelVisibility,
+};
+
+use ilattice::glam::UVec3;
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// Covers every non-empty voxel on the interior of `[min, max]` with a small set of disjoint boxes, e.g. for physics
+/// colliders.
+///
+/// This is the 3D analogue of [`greedy_quads`](crate::greedy_quads): starting from the first voxel that isn't covered
+/// yet, a box is grown as far as possible along X, then Y, then Z, as long as every voxel it covers is non-empty. Voxels
+/// are considered non-empty unless [`VoxelContext::get_visibility`] returns [`VoxelVisibility::Empty`], so translucent
+/// voxels are also covered. The interior is used for consistency with the meshing algorithms, even though boxes don't
+/// need the padding.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::ilattice::glam::UVec3;
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // The bottom half of the chunk is solid.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     let [_, y, _] = ChunkShape::delinearize(i);
+///     voxels[i as usize] = BoolVoxel(y <= 8);
+/// }
+///
+/// let boxes = greedy_boxes(&voxels, &ChunkShape {}, [0; 3], [17; 3], &DefaultVoxelContext);
+/// assert_eq!(boxes.len(), 1);
+/// assert_eq!(boxes[0].minimum, UVec3::ONE);
+/// assert_eq!(boxes[0].shape, UVec3::new(16, 8, 16));
+/// ```
+pub fn greedy_boxes<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    ctx: &C,
+) -> Vec<Extent<UVec3>>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    let interior = interior_extent(min, max); // Stay consistent with the meshing algorithms.
+    let interior_ub = interior.least_upper_bound();
+
+    let mut visited = vec![false; voxels.len()];
+    let mut boxes = Vec::new();
+
+    let is_free = |p: UVec3, visited: &[bool]| {
+        let index = voxels_shape.linearize(p.to_array()) as usize;
+        !visited[index] && ctx.get_visibility(voxels.voxel(index)) != VoxelVisibility::Empty
+    };
+    let all_free =
+        |region: Extent<UVec3>, visited: &[bool]| region.iter3().all(|p| is_free(p, visited));
+
+    for p in interior.iter3() {
+        if !is_free(p, &visited) {
+            continue;
+        }
+
+        // Grow along X, then Y, then Z, one layer at a time.
+        let mut shape = UVec3::ONE;
+        while p.x + shape.x < interior_ub.x
+            && is_free(UVec3::new(p.x + shape.x, p.y, p.z), &visited)
+        {
+            shape.x += 1;
+        }
+        while p.y + shape.y < interior_ub.y
+            && all_free(
+                Extent::from_min_and_shape(
+                    UVec3::new(p.x, p.y + shape.y, p.z),
+                    UVec3::new(shape.x, 1, 1),
+                ),
+                &visited,
+            )
+        {
+            shape.y += 1;
+        }
+        while p.z + shape.z < interior_ub.z
+            && all_free(
+                Extent::from_min_and_shape(
+                    UVec3::new(p.x, p.y, p.z + shape.z),
+                    UVec3::new(shape.x, shape.y, 1),
+                ),
+                &visited,
+            )
+        {
+            shape.z += 1;
+        }
+
+        let found = Extent::from_min_and_shape(p, shape);
+        for q in found.iter3() {
+            visited[voxels_shape.linearize(q.to_array()) as usize] = true;
+        }
+        boxes.push(found);
+    }
+
+    boxes
+}
+
+/// Returns the smallest axis-aligned box that contains every non-empty voxel on the interior of `[min, max]`, or `None`
+/// if they are all empty, e.g. for a physics broad phase or to skip meshing chunks with no content.
+///
+/// Voxels are non-empty as in [`greedy_boxes`]. This is a single scan over the voxels, so it is much cheaper than
+/// meshing. The box is not oriented to fit diagonal content more tightly; use [`greedy_boxes`] for a tighter fit.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::ilattice::glam::UVec3;
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// assert_eq!(solid_aabb(&voxels, &ChunkShape {}, [0; 3], [17; 3], &DefaultVoxelContext), None);
+///
+/// voxels[ChunkShape::linearize([2, 3, 4]) as usize] = BoolVoxel(true);
+/// voxels[ChunkShape::linearize([5, 1, 9]) as usize] = BoolVoxel(true);
+/// let aabb = solid_aabb(&voxels, &ChunkShape {}, [0; 3], [17; 3], &DefaultVoxelContext).unwrap();
+/// assert_eq!(aabb.minimum, UVec3::new(2, 1, 4));
+/// assert_eq!(aabb.max(), UVec3::new(5, 3, 9));
+/// ```
+pub fn solid_aabb<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    ctx: &C,
+) -> Option<Extent<UVec3>>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    let mut bounds: Option<(UVec3, UVec3)> = None;
+    for p in interior_extent(min, max).iter3() {
+        let voxel = voxels.voxel(voxels_shape.linearize(p.to_array()) as usize);
+        if ctx.get_visibility(voxel) == VoxelVisibility::Empty {
+            continue;
+        }
+        bounds = Some(match bounds {
+            Some((lo, hi)) => (lo.min(p), hi.max(p)),
+            None => (p, p),
+        });
+    }
+    bounds.map(|(lo, hi)| Extent::from_min_and_max(lo, hi))
+}
+
+/// Covers the non-empty voxels with [`greedy_boxes`] and writes all six faces of every box to `output`, interpreting
+/// `output.groups[i]` with `faces[i]`.
+///
+/// Unlike [`greedy_quads`](crate::greedy_quads), faces are merged across depth as well, and the faces between adjacent
+/// boxes are kept, so this is meant for volumetric content like clouds and fog rather than opaque surfaces. Chunky
+/// volumes need far fewer quads than meshing every voxel with
+/// [`visible_block_faces`](crate::visible_block_faces).
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Translucent } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // A cloud made of two staggered slabs.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     let [x, y, z] = ChunkShape::delinearize(i);
+///     voxels[i as usize] = BoolVoxel((y == 4 && z <= 8) || (y == 5 && z > 8));
+/// }
+///
+/// let mut quads = QuadBuffer::new();
+/// greedy_volume_quads(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [17; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut quads,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(quads.num_quads(), 2 * 6);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_volume_quads<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut QuadBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    output.reset();
+    for found in greedy_boxes(voxels, voxels_shape, min, max, ctx) {
+        let shape = found.shape.to_array();
+        for (group, face) in output.groups.iter_mut().zip(faces.iter()) {
+            let [n_axis, u_axis, v_axis] = face.permutation.axes();
+            let mut minimum = found.minimum.to_array();
+            // The quad of a positive face belongs to the last layer of voxels in the box.
+            if face.n_sign > 0 {
+                minimum[n_axis.index()] += shape[n_axis.index()] - 1;
+            }
+            group.push(UnorientedQuad {
+                minimum,
+                width: shape[u_axis.index()],
+                height: shape[v_axis.index()],
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sphere, BoolVoxel, SampleShape, EMPTY};
+    use crate::{greedy_quads, DefaultVoxelContext, GreedyQuadsBuffer, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    #[test]
+    fn boxes_cover_sphere_exactly() {
+        let shape = SampleShape {};
+        let samples = sphere();
+
+        let boxes = greedy_boxes(&samples, &shape, [0; 3], [33; 3], &DefaultVoxelContext);
+
+        let mut covered = [false; SampleShape::SIZE as usize];
+        for b in boxes.iter() {
+            for p in b.iter3() {
+                let index = shape.linearize(p.to_array()) as usize;
+                assert!(samples[index].0, "box {b:?} covers an empty voxel");
+                assert!(!covered[index], "box {b:?} overlaps another box");
+                covered[index] = true;
+            }
+        }
+        let solid = samples.iter().filter(|v| v.0).count();
+        assert_eq!(covered.iter().filter(|&&c| c).count(), solid);
+        // Far fewer boxes than voxels.
+        assert!(boxes.len() * 10 < solid);
+    }
+
+    #[test]
+    fn solid_aabb_bounds_sphere_tightly() {
+        let shape = SampleShape {};
+        let samples = sphere();
+
+        let aabb = solid_aabb(&samples, &shape, [0; 3], [33; 3], &DefaultVoxelContext).unwrap();
+        assert_eq!(
+            aabb,
+            Extent::from_min_and_max(UVec3::splat(2), UVec3::splat(30))
+        );
+
+        // Voxels outside of the interior are ignored.
+        let aabb = solid_aabb(&samples, &shape, [0; 3], [10; 3], &DefaultVoxelContext).unwrap();
+        assert_eq!(aabb.max(), UVec3::splat(9));
+        assert_eq!(
+            solid_aabb(
+                &[EMPTY; 27],
+                &ConstShape3u32::<3, 3, 3> {},
+                [0; 3],
+                [2; 3],
+                &DefaultVoxelContext
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn volume_quads_of_a_single_box_match_greedy_quads() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let [x, y, z] = shape.delinearize(i);
+            let solid = (3..=10).contains(&x) && (5..=6).contains(&y) && (2..=30).contains(&z);
+            samples[i as usize] = BoolVoxel(solid, false);
+        }
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut volume = QuadBuffer::new();
+        greedy_volume_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut volume,
+            &DefaultVoxelContext,
+        );
+        let mut surface = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut surface,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(volume.groups, surface.quads.groups);
+    }
+}
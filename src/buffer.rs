@@ -1,4 +1,8 @@
-use crate::{UnorientedQuad, UnorientedUnitQuad};
+use crate::{OrientedBlockFace, UnorientedQuad, UnorientedUnitQuad};
+
+use ilattice::glam::{UVec3, Vec3};
+use ndshape::Shape;
+use std::collections::HashMap;
 
 #[derive(Default)]
 pub struct QuadBuffer {
@@ -13,6 +17,13 @@ impl QuadBuffer {
         Self { groups: [EMPTY; 6] }
     }
 
+    /// Creates a buffer where `groups[i]` can hold `group_capacity[i]` quads without reallocating.
+    pub fn with_group_capacity(group_capacity: [usize; 6]) -> Self {
+        Self {
+            groups: group_capacity.map(Vec::with_capacity),
+        }
+    }
+
     pub fn reset(&mut self) {
         for group in self.groups.iter_mut() {
             group.clear();
@@ -27,6 +38,272 @@ impl QuadBuffer {
         }
         sum
     }
+
+    /// Appends every quad in `other` to the end of the corresponding group in `self`.
+    ///
+    /// Meshes are generated group by group, so the vertices of the appended quads are not contiguous: in each group, they
+    /// come after the quads that were already in `self`, but before the next group. To keep the vertices of each buffer
+    /// in a separate range, combine the meshes with [`MeshData::append`](crate::MeshData::append) instead.
+    pub fn append(&mut self, other: &QuadBuffer) {
+        for (group, other_group) in self.groups.iter_mut().zip(other.groups.iter()) {
+            group.extend_from_slice(other_group);
+        }
+    }
+
+    /// Returns the number of vertices needed to mesh every quad, with 4 vertices per quad.
+    pub fn vertex_count(&self) -> usize {
+        4 * self.num_quads()
+    }
+
+    /// Returns the number of triangle indices needed to mesh every quad, with 2 triangles per quad.
+    pub fn index_count(&self) -> usize {
+        6 * self.num_quads()
+    }
+
+    /// Returns the quads that were added and removed in `other`, a newer meshing of the same chunk, compared to `self`,
+    /// e.g. to only upload the changed quads to the GPU or send them over the network.
+    ///
+    /// Quads are compared by equality within each group, and the order of the quads doesn't matter. If a group has
+    /// duplicate quads, only the difference in their counts is reported. Added quads are listed in the order of `other`,
+    /// and removed quads in the order of `self`.
+    ///
+    /// Each group is compared by counting its quads in a hash map, which takes O(n) expected time for n quads, but
+    /// allocates the map. Sorting both groups and merging them would avoid hashing, but take O(n log n) time, which is
+    /// slower for the thousands of quads in a typical chunk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let quad = |x| UnorientedQuad { minimum: [x, 1, 1], width: 1, height: 1 };
+    /// let mut old = QuadBuffer::new();
+    /// old.groups[0].extend([quad(1), quad(2)]);
+    /// let mut new = QuadBuffer::new();
+    /// new.groups[0].extend([quad(2), quad(3)]);
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.added, [(0, quad(3))]);
+    /// assert_eq!(diff.removed, [(0, quad(1))]);
+    /// assert!(old.diff(&old).is_empty());
+    /// ```
+    pub fn diff(&self, other: &QuadBuffer) -> QuadDiff {
+        let mut diff = QuadDiff::default();
+        let mut counts = HashMap::new();
+        for (group_index, (group, other_group)) in
+            self.groups.iter().zip(other.groups.iter()).enumerate()
+        {
+            // Positive counts are quads that only `self` has, and negative counts are quads that only `other` has.
+            counts.clear();
+            for quad in group.iter() {
+                *counts.entry(*quad).or_insert(0isize) += 1;
+            }
+            for quad in other_group.iter() {
+                *counts.entry(*quad).or_insert(0) -= 1;
+            }
+            for quad in group.iter() {
+                if let Some(count) = counts.get_mut(quad).filter(|count| **count > 0) {
+                    *count -= 1;
+                    diff.removed.push((group_index, *quad));
+                }
+            }
+            for quad in other_group.iter() {
+                if let Some(count) = counts.get_mut(quad).filter(|count| **count < 0) {
+                    *count += 1;
+                    diff.added.push((group_index, *quad));
+                }
+            }
+        }
+        diff
+    }
+
+    /// Sorts the quads in each group by the distance from their centers (see [`OrientedBlockFace::quad_center`]) to
+    /// `camera`, interpreting `groups[i]` with `faces[i]`.
+    ///
+    /// Quads are sorted nearest first, which helps early depth testing reject hidden fragments of opaque geometry, or
+    /// farthest first if `back_to_front` is set, which is needed to blend translucent geometry correctly. `camera` is in
+    /// the same coordinates as the quads, i.e. voxels relative to the minimum of the voxel array. Quads are only sorted
+    /// within their group, so draw the groups in a suitable order as well if that matters.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// # use block_mesh::ilattice::glam::Vec3;
+    /// let mut quads = QuadBuffer::new();
+    /// for x in 0..4 {
+    ///     quads.groups[0].push(UnorientedQuad { minimum: [x, 1, 1], width: 1, height: 1 });
+    /// }
+    ///
+    /// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+    /// quads.sort_by_distance(Vec3::new(10.0, 1.0, 1.0), faces, false);
+    /// assert_eq!(quads.groups[0][0].minimum, [3, 1, 1]);
+    /// quads.sort_by_distance(Vec3::new(10.0, 1.0, 1.0), faces, true);
+    /// assert_eq!(quads.groups[0][0].minimum, [0, 1, 1]);
+    /// ```
+    pub fn sort_by_distance(
+        &mut self,
+        camera: Vec3,
+        faces: &[OrientedBlockFace; 6],
+        back_to_front: bool,
+    ) {
+        for (group, face) in self.groups.iter_mut().zip(faces.iter()) {
+            group.sort_by(|a, b| {
+                let distance_a = face.quad_center(a).distance_squared(camera);
+                let distance_b = face.quad_center(b).distance_squared(camera);
+                if back_to_front {
+                    distance_b.total_cmp(&distance_a)
+                } else {
+                    distance_a.total_cmp(&distance_b)
+                }
+            });
+        }
+    }
+
+    /// Returns a copy of this buffer where every quad is split into a grid of up to `n` by `n` smaller quads, interpreting
+    /// `groups[i]` with `faces[i]`.
+    ///
+    /// This gives shaders that displace vertices, e.g. for terrain detail, more vertices to work with, while keeping most
+    /// of the savings of greedy meshing. Quads can't be split below one voxel, so a side that is shorter than `n` voxels
+    /// is split into one part per voxel. When a side isn't divisible by `n`, its parts differ in size by at most one
+    /// voxel, with the larger parts first. Attributes like [`OrientedBlockFace::tex_coords`] are derived from the voxel
+    /// coordinates of each quad, so they stay continuous across the new quads. Panics if `n` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let mut quads = QuadBuffer::new();
+    /// quads.groups[0].push(UnorientedQuad { minimum: [0; 3], width: 5, height: 1 });
+    ///
+    /// let subdivided = quads.subdivided(&RIGHT_HANDED_Y_UP_CONFIG.faces, 2);
+    /// let widths: Vec<u32> = subdivided.groups[0].iter().map(|quad| quad.width).collect();
+    /// assert_eq!(widths, [3, 2]);
+    /// ```
+    pub fn subdivided(&self, faces: &[OrientedBlockFace; 6], n: u32) -> QuadBuffer {
+        assert!(n > 0, "Quads can't be split into 0 parts");
+        let mut output = QuadBuffer::new();
+        for ((dst, src), face) in output
+            .groups
+            .iter_mut()
+            .zip(self.groups.iter())
+            .zip(faces.iter())
+        {
+            for quad in src.iter() {
+                let minimum = UVec3::from(quad.minimum);
+                for (v_offset, height) in split_evenly(quad.height, n) {
+                    for (u_offset, width) in split_evenly(quad.width, n) {
+                        dst.push(UnorientedQuad {
+                            minimum: (minimum + face.u * u_offset + face.v * v_offset).to_array(),
+                            width,
+                            height,
+                        });
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    /// Returns the center and radius of a sphere that contains every quad, interpreting `groups[i]` with `faces[i]`, or
+    /// `None` if there are no quads. Positions are scaled by `voxel_size` as in
+    /// [`OrientedBlockFace::quad_mesh_positions`].
+    ///
+    /// The sphere isn't minimal, but it's close: this returns the smaller of the sphere around the center of the
+    /// bounding box and the sphere found by Ritter's algorithm, which takes a few passes over the corners of the quads.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let mut quads = QuadBuffer::new();
+    /// // The bottom and top faces of a 2x2x2 cube at the origin.
+    /// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+    /// let neg_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::NegY);
+    /// let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+    /// quads.groups[neg_y].push(UnorientedQuad { minimum: [0; 3], width: 2, height: 2 });
+    /// quads.groups[pos_y].push(UnorientedQuad { minimum: [0, 1, 0], width: 2, height: 2 });
+    ///
+    /// let (center, radius) = quads.bounding_sphere(faces, 1.0).unwrap();
+    /// assert_eq!(center.to_array(), [1.0; 3]);
+    /// assert!((radius - 3.0f32.sqrt()).abs() < 1e-6);
+    /// ```
+    pub fn bounding_sphere(
+        &self,
+        faces: &[OrientedBlockFace; 6],
+        voxel_size: f32,
+    ) -> Option<(Vec3, f32)> {
+        let corners = || {
+            self.groups
+                .iter()
+                .zip(faces.iter())
+                .flat_map(move |(group, face)| {
+                    group
+                        .iter()
+                        .flat_map(move |quad| face.quad_mesh_positions(quad, voxel_size))
+                })
+                .map(Vec3::from)
+        };
+        let first = corners().next()?;
+        let farthest_from = |p: Vec3| {
+            corners()
+                .max_by(|a, b| a.distance_squared(p).total_cmp(&b.distance_squared(p)))
+                .unwrap()
+        };
+        let radius_around =
+            |center: Vec3| corners().map(|p| p.distance(center)).fold(0.0, f32::max);
+
+        // The sphere around the bounding box.
+        let (min, max) = corners().fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+        let box_center = (min + max) / 2.0;
+        let box_radius = radius_around(box_center);
+
+        // Ritter's algorithm: start with the sphere between two far apart corners, and grow it to contain every corner.
+        let a = farthest_from(first);
+        let b = farthest_from(a);
+        let mut center = (a + b) / 2.0;
+        let mut radius = a.distance(b) / 2.0;
+        for p in corners() {
+            let distance = p.distance(center);
+            if distance > radius {
+                let new_radius = (radius + distance) / 2.0;
+                center += (p - center) * ((new_radius - radius) / distance);
+                radius = new_radius;
+            }
+        }
+        // Rounding may leave a corner just outside of the grown sphere.
+        let radius = radius_around(center);
+
+        Some(if radius < box_radius {
+            (center, radius)
+        } else {
+            (box_center, box_radius)
+        })
+    }
+}
+
+/// The quads that differ between two meshings of the same chunk, computed by [`QuadBuffer::diff`]. Each quad is paired
+/// with the index of its group.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct QuadDiff {
+    /// The quads that are only in the newer buffer.
+    pub added: Vec<(usize, UnorientedQuad)>,
+    /// The quads that are only in the older buffer.
+    pub removed: Vec<(usize, UnorientedQuad)>,
+}
+
+impl QuadDiff {
+    /// Returns `true` iff both buffers have the same quads.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Splits `length` into `min(n, length)` parts whose sizes differ by at most one, yielding the offset and size of each.
+fn split_evenly(length: u32, n: u32) -> impl Iterator<Item = (u32, u32)> {
+    let num_parts = n.min(length);
+    let base = length / num_parts.max(1);
+    let remainder = length - base * num_parts;
+    (0..num_parts).map(move |i| (i * base + i.min(remainder), base + u32::from(i < remainder)))
 }
 
 #[derive(Default)]
@@ -61,4 +338,266 @@ impl UnitQuadBuffer {
         }
         sum
     }
+
+    /// Returns the number of vertices needed to mesh every quad, with 4 vertices per quad.
+    pub fn vertex_count(&self) -> usize {
+        4 * self.num_quads()
+    }
+
+    /// Returns the number of triangle indices needed to mesh every quad, with 2 triangles per quad.
+    pub fn index_count(&self) -> usize {
+        6 * self.num_quads()
+    }
+}
+
+/// A [`UnitQuadBuffer`] that stores the minimum of each quad as its linear index in the voxel array, e.g. 4 bytes per
+/// quad with `u32` indices or 2 bytes with `u16` indices instead of 12 bytes.
+///
+/// `u16` indices fit any shape with at most 65536 voxels, like a padded chunk of 40^3 voxels. The quads are unpacked
+/// with the shape of the voxel array they were generated from.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// let mut quads = UnitQuadBuffer::new();
+/// quads.groups[0].push(UnorientedUnitQuad { minimum: [1, 2, 3] });
+/// quads.groups[5].push(UnorientedUnitQuad { minimum: [16, 16, 16] });
+///
+/// let packed = PackedUnitQuadBuffer::<u16>::from_unit_quads(&quads, &ChunkShape {});
+/// assert_eq!(packed.num_quads(), 2);
+/// assert_eq!(packed.minimum(5, 0, &ChunkShape {}), [16, 16, 16]);
+/// assert_eq!(packed.to_unit_quads(&ChunkShape {}).groups, quads.groups);
+/// ```
+pub struct PackedUnitQuadBuffer<I = u32> {
+    /// The linear index of the minimum of each quad, in a group for each block face.
+    pub groups: [Vec<I>; 6],
+}
+
+impl<I> Default for PackedUnitQuadBuffer<I> {
+    fn default() -> Self {
+        Self {
+            groups: Default::default(),
+        }
+    }
+}
+
+impl<I> PackedUnitQuadBuffer<I>
+where
+    I: Copy + Into<u32> + TryFrom<u32>,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Packs every quad in `quads`, which were generated from a voxel array with the shape `voxels_shape`.
+    ///
+    /// Panics if a linear index doesn't fit in `I`.
+    pub fn from_unit_quads<S>(quads: &UnitQuadBuffer, voxels_shape: &S) -> Self
+    where
+        S: Shape<3, Coord = u32>,
+    {
+        let mut packed = Self::new();
+        for (dst, src) in packed.groups.iter_mut().zip(quads.groups.iter()) {
+            dst.extend(src.iter().map(|quad| {
+                let index = voxels_shape.linearize(quad.minimum);
+                I::try_from(index).unwrap_or_else(|_| {
+                    panic!("The linear index {index} of {:?} doesn't fit", quad.minimum)
+                })
+            }));
+        }
+        packed
+    }
+
+    /// Unpacks every quad, where `voxels_shape` is the shape that was used to pack them.
+    pub fn to_unit_quads<S>(&self, voxels_shape: &S) -> UnitQuadBuffer
+    where
+        S: Shape<3, Coord = u32>,
+    {
+        let mut quads = UnitQuadBuffer::new();
+        for (dst, src) in quads.groups.iter_mut().zip(self.groups.iter()) {
+            dst.extend(src.iter().map(|&index| UnorientedUnitQuad {
+                minimum: voxels_shape.delinearize(index.into()),
+            }));
+        }
+        quads
+    }
+
+    /// Returns the minimum of quad `i` in the group `face_index`, where `voxels_shape` is the shape that was used to
+    /// pack it.
+    #[inline]
+    pub fn minimum<S>(&self, face_index: usize, i: usize, voxels_shape: &S) -> [u32; 3]
+    where
+        S: Shape<3, Coord = u32>,
+    {
+        voxels_shape.delinearize(self.groups[face_index][i].into())
+    }
+
+    /// Clears the buffer.
+    pub fn reset(&mut self) {
+        for group in self.groups.iter_mut() {
+            group.clear();
+        }
+    }
+
+    /// Returns the total count of quads across all groups.
+    pub fn num_quads(&self) -> usize {
+        self.groups.iter().map(Vec::len).sum()
+    }
+}
+
+/// The voxel at the minimum of each quad in a [`QuadBuffer`], stored in parallel with its groups. Filled by
+/// [`greedy_quads_with_representatives`](crate::greedy_quads_with_representatives).
+///
+/// Greedy meshing only guarantees that the voxels in a quad share a merge value, so this is useful for reading
+/// attributes that were intentionally left out of the merge value without indexing the voxel array yourself. It's a
+/// separate buffer so there is no overhead when it isn't needed.
+pub struct RepresentativeVoxelBuffer<T> {
+    /// `groups[i][j]` is the voxel at `quads.groups[i][j].minimum`.
+    pub groups: [Vec<T>; 6],
+}
+
+impl<T> Default for RepresentativeVoxelBuffer<T> {
+    fn default() -> Self {
+        Self {
+            groups: Default::default(),
+        }
+    }
+}
+
+impl<T> RepresentativeVoxelBuffer<T> {
+    /// Creates an empty buffer. The groups allocate as voxels are added, and keep their allocations across
+    /// [`RepresentativeVoxelBuffer::reset`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the buffer.
+    pub fn reset(&mut self) {
+        for group in self.groups.iter_mut() {
+            group.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RIGHT_HANDED_Y_UP_CONFIG;
+
+    #[test]
+    fn applying_diff_gives_new_quads() {
+        let quad = |x, width| UnorientedQuad {
+            minimum: [x, 0, 0],
+            width,
+            height: 1,
+        };
+        let mut old = QuadBuffer::new();
+        old.groups[1].extend([quad(0, 1), quad(0, 1), quad(1, 2), quad(4, 1)]);
+        old.groups[3].push(quad(2, 2));
+        let mut new = QuadBuffer::new();
+        new.groups[1].extend([quad(4, 1), quad(0, 1), quad(1, 3)]);
+        new.groups[2].push(quad(2, 2));
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, [(1, quad(1, 3)), (2, quad(2, 2))]);
+        assert_eq!(
+            diff.removed,
+            [(1, quad(0, 1)), (1, quad(1, 2)), (3, quad(2, 2))]
+        );
+
+        let mut patched = old.groups.clone();
+        for (group, quad) in diff.removed {
+            let i = patched[group].iter().position(|q| *q == quad).unwrap();
+            patched[group].remove(i);
+        }
+        for (group, quad) in diff.added {
+            patched[group].push(quad);
+        }
+        for (patched_group, new_group) in patched.iter_mut().zip(new.groups.iter()) {
+            let key = |q: &UnorientedQuad| (q.minimum, q.width, q.height);
+            patched_group.sort_by_key(key);
+            let mut new_group = new_group.clone();
+            new_group.sort_by_key(key);
+            assert_eq!(*patched_group, new_group);
+        }
+    }
+
+    #[test]
+    fn bounding_sphere_contains_every_corner() {
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let mut quads = QuadBuffer::new();
+        assert_eq!(quads.bounding_sphere(faces, 1.0), None);
+
+        for (i, group) in quads.groups.iter_mut().enumerate() {
+            let i = i as u32;
+            group.push(UnorientedQuad {
+                minimum: [3 * i, 7 - i, i * i],
+                width: 1 + 2 * i,
+                height: 5 - i,
+            });
+        }
+        let (center, radius) = quads.bounding_sphere(faces, 0.5).unwrap();
+        let mut max_distance = 0.0f32;
+        for (group, face) in quads.groups.iter().zip(faces.iter()) {
+            for quad in group.iter() {
+                for p in face.quad_mesh_positions(quad, 0.5) {
+                    max_distance = max_distance.max(Vec3::from(p).distance(center));
+                }
+            }
+        }
+        // Some corner is on the sphere.
+        assert_eq!(radius, max_distance);
+    }
+
+    #[test]
+    fn subdivided_quads_cover_the_original_quads() {
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let mut quads = QuadBuffer::new();
+        for (i, group) in quads.groups.iter_mut().enumerate() {
+            let i = i as u32;
+            group.push(UnorientedQuad {
+                minimum: [i, 2 * i, 3],
+                width: 7 + i,
+                height: 2,
+            });
+        }
+
+        for n in 1..=4 {
+            let subdivided = quads.subdivided(faces, n);
+            for ((face, group), pieces) in faces
+                .iter()
+                .zip(quads.groups.iter())
+                .zip(subdivided.groups.iter())
+            {
+                let quad = &group[0];
+                assert_eq!(pieces.len() as u32, n.min(quad.width) * n.min(quad.height));
+
+                // Every voxel on the original quad is covered by exactly one piece.
+                let mut covered = Vec::new();
+                for piece in pieces.iter() {
+                    assert!(piece.width.abs_diff(quad.width / n.min(quad.width)) <= 1);
+                    let minimum = UVec3::from(piece.minimum);
+                    for v in 0..piece.height {
+                        for u in 0..piece.width {
+                            covered.push((minimum + face.u * u + face.v * v).to_array());
+                        }
+                    }
+                }
+                let minimum = UVec3::from(quad.minimum);
+                let mut expected = Vec::new();
+                for v in 0..quad.height {
+                    for u in 0..quad.width {
+                        expected.push((minimum + face.u * u + face.v * v).to_array());
+                    }
+                }
+                covered.sort();
+                expected.sort();
+                assert_eq!(covered, expected);
+            }
+        }
+    }
 }
@@ -0,0 +1,228 @@
+use crate::{
+    bounds::assert_in_bounds, greedy_quads, ContextAdapter, ForwardingContext, GreedyQuadsBuffer,
+    MergeContextAdapter, MergeVoxelContext, OrientedBlockFace, VoxelContext, VoxelVisibility,
+};
+
+use ilattice::glam::{IVec3, UVec3};
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// Returns the visibility of every voxel in `voxels`, except that pockets of non-opaque voxels that are completely
+/// enclosed by opaque voxels are made [`VoxelVisibility::Opaque`], so the faces around them won't be meshed.
+///
+/// A pocket is enclosed if it can't be reached from the boundary of `[min, max]` by moving between face-adjacent,
+/// non-opaque voxels. Since the boundary is the padding of the chunk, a cavity that extends into a neighboring chunk is
+/// always considered open, even if it is enclosed in the whole world. Voxels outside of `[min, max]` are left as they
+/// are.
+///
+/// Pass the result to [`greedy_quads_with_visibility`].
+pub fn cull_enclosed_cavities<T, S, C>(
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    ctx: &C,
+) -> Vec<VoxelVisibility>
+where
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<T>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    let mut visibility: Vec<_> = voxels.iter().map(|v| ctx.get_visibility(v)).collect();
+
+    let min = UVec3::from(min).as_ivec3();
+    let max = UVec3::from(max).as_ivec3();
+    let extent = Extent::from_min_and_max(min, max);
+    let interior = extent.padded(-1);
+    let extent = Extent::from_min_and_shape(extent.minimum.as_uvec3(), extent.shape.as_uvec3());
+    let interior =
+        Extent::from_min_and_shape(interior.minimum.as_uvec3(), interior.shape.as_uvec3());
+    let is_open = |visibility: &[VoxelVisibility], p: UVec3| {
+        visibility[voxels_shape.linearize(p.to_array()) as usize] != VoxelVisibility::Opaque
+    };
+
+    // Flood fill from every non-opaque voxel on the boundary.
+    let mut reached = vec![false; voxels.len()];
+    let mut stack: Vec<UVec3> = extent
+        .iter3()
+        .filter(|&p| !interior.contains(p) && is_open(&visibility, p))
+        .collect();
+    for p in stack.iter() {
+        reached[voxels_shape.linearize(p.to_array()) as usize] = true;
+    }
+    while let Some(p) = stack.pop() {
+        for offset in [
+            IVec3::X,
+            IVec3::Y,
+            IVec3::Z,
+            -IVec3::X,
+            -IVec3::Y,
+            -IVec3::Z,
+        ] {
+            let q = (p.as_ivec3() + offset).as_uvec3();
+            if !interior.contains(q) {
+                continue;
+            }
+            let q_index = voxels_shape.linearize(q.to_array()) as usize;
+            if !reached[q_index] && is_open(&visibility, q) {
+                reached[q_index] = true;
+                stack.push(q);
+            }
+        }
+    }
+
+    for p in interior.iter3() {
+        let index = voxels_shape.linearize(p.to_array()) as usize;
+        if !reached[index] {
+            visibility[index] = VoxelVisibility::Opaque;
+        }
+    }
+
+    visibility
+}
+
+/// Same as [`greedy_quads`], but the visibility of each voxel is read from `visibility`, e.g. the output of
+/// [`cull_enclosed_cavities`], instead of [`VoxelContext::get_visibility`] and [`VoxelContext::get_visibility_for_face`].
+/// `voxels` is only read for merge values and [`VoxelContext::face_inset`].
+///
+/// This allocates an array of voxel indices on every call.
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_visibility<T, S, C>(
+    visibility: &[VoxelVisibility],
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+    assert_in_bounds(visibility, voxels_shape, min, max);
+
+    // The index array has the same shape as the voxels, so every stride and quad is the same.
+    let indices: Vec<u32> = (0..voxels_shape.size()).collect();
+    let visibility_ctx = ForwardingContext(VisibilityContext {
+        visibility,
+        voxels,
+        ctx,
+    });
+    greedy_quads(
+        &indices,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        output,
+        &visibility_ctx,
+    );
+}
+
+/// Reads visibility from `visibility` and everything else from the voxel at each index.
+struct VisibilityContext<'a, T, C> {
+    visibility: &'a [VoxelVisibility],
+    voxels: &'a [T],
+    ctx: &'a C,
+}
+
+impl<'a, T, C> ContextAdapter<u32> for VisibilityContext<'a, T, C>
+where
+    C: VoxelContext<T>,
+{
+    type Voxel = T;
+    type Inner = C;
+
+    #[inline]
+    fn inner(&self) -> &C {
+        self.ctx
+    }
+
+    #[inline]
+    fn voxel<'b>(&'b self, index: &'b u32) -> Option<&'b T> {
+        Some(&self.voxels[*index as usize])
+    }
+
+    #[inline]
+    fn get_visibility(&self, index: &u32) -> VoxelVisibility {
+        self.visibility[*index as usize]
+    }
+
+    #[inline]
+    fn get_visibility_for_face(&self, index: &u32, _face_index: usize) -> VoxelVisibility {
+        // The given visibility is the same through every face.
+        self.get_visibility(index)
+    }
+}
+
+impl<'a, T, C> MergeContextAdapter<u32> for VisibilityContext<'a, T, C>
+where
+    C: MergeVoxelContext<T>,
+{
+    type MergeValue = C::MergeValue;
+    type MergeValueFacingNeighbour = C::MergeValueFacingNeighbour;
+
+    #[inline]
+    fn merge_value(&self, index: &u32) -> Self::MergeValue {
+        self.ctx.merge_value(&self.voxels[*index as usize])
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, index: &u32) -> Self::MergeValueFacingNeighbour {
+        self.ctx
+            .merge_value_facing_neighbour(&self.voxels[*index as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, EMPTY};
+    use crate::{DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    #[test]
+    fn enclosed_cavity_faces_are_culled() {
+        // A hollow cube.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let p = UVec3::from(shape.delinearize(i));
+            let in_cube =
+                |lo, hi| p.cmpge(UVec3::splat(lo)).all() && p.cmple(UVec3::splat(hi)).all();
+            samples[i as usize] = BoolVoxel(in_cube(4, 13) && !in_cube(6, 11), false);
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [17; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        // The inside of the shell is meshed too.
+        assert_eq!(buffer.quads.num_quads(), 12);
+
+        let visibility =
+            cull_enclosed_cavities(&samples, &shape, [0; 3], [17; 3], &DefaultVoxelContext);
+        greedy_quads_with_visibility(
+            &visibility,
+            &samples,
+            &shape,
+            [0; 3],
+            [17; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(buffer.quads.num_quads(), 6);
+    }
+
+    type SampleShape = ConstShape3u32<18, 18, 18>;
+}
@@ -0,0 +1,185 @@
+use crate::{
+    greedy_quads, visible_block_faces, GreedyQuadsBuffer, MergeVoxelContext, OrientedBlockFace,
+    QuadBuffer, UnitQuadBuffer, UnorientedQuad, UnorientedUnitQuad, VoxelSource,
+};
+
+use ndshape::Shape;
+
+/// A destination for the quads of a mesh, so the same code can consume the output of [`greedy_quads`] and
+/// [`visible_block_faces`], or so quads can be written straight into a custom structure. See [`mesh_quads`].
+///
+/// Unit quads are passed as [`UnorientedQuad`]s with a width and height of 1.
+pub trait QuadCollector {
+    /// Adds `quad`, which belongs to `faces[face_index]` of the meshing call.
+    fn push_quad(&mut self, face_index: usize, quad: UnorientedQuad);
+}
+
+impl QuadCollector for QuadBuffer {
+    #[inline]
+    fn push_quad(&mut self, face_index: usize, quad: UnorientedQuad) {
+        self.groups[face_index].push(quad);
+    }
+}
+
+impl QuadCollector for GreedyQuadsBuffer {
+    #[inline]
+    fn push_quad(&mut self, face_index: usize, quad: UnorientedQuad) {
+        self.quads.push_quad(face_index, quad);
+    }
+}
+
+impl QuadCollector for UnitQuadBuffer {
+    /// Panics if `quad` covers more than one voxel face, since it can't be represented by an [`UnorientedUnitQuad`].
+    #[inline]
+    fn push_quad(&mut self, face_index: usize, quad: UnorientedQuad) {
+        assert!(
+            quad.width == 1 && quad.height == 1,
+            "A {}x{} quad can't be stored in a UnitQuadBuffer",
+            quad.width,
+            quad.height
+        );
+        self.groups[face_index].push(UnorientedUnitQuad {
+            minimum: quad.minimum,
+        });
+    }
+}
+
+/// Which algorithm [`mesh_quads`] uses.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MeshAlgorithm {
+    /// [`greedy_quads`].
+    Greedy,
+    /// [`visible_block_faces`].
+    VisibleFaces,
+}
+
+/// Meshes the voxels with `algorithm` and appends every quad to `collector`, along with the index of its face in
+/// `faces`.
+///
+/// The quads are passed in the order of the groups, and within each group in the order that the algorithm generates
+/// them. Existing quads in `collector` are kept. The working memory of the algorithm is allocated on every call, so call
+/// the algorithm directly with a reused buffer when meshing many chunks.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// /// Counts the voxel faces covered by the quads, whichever algorithm made them.
+/// #[derive(Default)]
+/// struct FaceArea(u32);
+///
+/// impl QuadCollector for FaceArea {
+///     fn push_quad(&mut self, _face_index: usize, quad: UnorientedQuad) {
+///         self.0 += quad.width * quad.height;
+///     }
+/// }
+///
+/// type ChunkShape = ConstShape3u32<6, 6, 6>;
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     voxels[i as usize] = BoolVoxel(ChunkShape::delinearize(i).iter().all(|c| (1..=4).contains(c)));
+/// }
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// for algorithm in [MeshAlgorithm::Greedy, MeshAlgorithm::VisibleFaces] {
+///     let mut area = FaceArea::default();
+///     mesh_quads(&voxels, &ChunkShape {}, [0; 3], [5; 3], faces, algorithm, &mut area, &DefaultVoxelContext);
+///     assert_eq!(area.0, 6 * 16);
+/// }
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn mesh_quads<V, S, C, Q>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    algorithm: MeshAlgorithm,
+    collector: &mut Q,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+    Q: QuadCollector + ?Sized,
+{
+    match algorithm {
+        MeshAlgorithm::Greedy => {
+            let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+            greedy_quads(voxels, voxels_shape, min, max, faces, &mut buffer, ctx);
+            for (face_index, group) in buffer.quads.groups.iter().enumerate() {
+                for &quad in group.iter() {
+                    collector.push_quad(face_index, quad);
+                }
+            }
+        }
+        MeshAlgorithm::VisibleFaces => {
+            let mut buffer = UnitQuadBuffer::new();
+            visible_block_faces(voxels, voxels_shape, min, max, faces, &mut buffer, ctx);
+            for (face_index, group) in buffer.groups.iter().enumerate() {
+                for &quad in group.iter() {
+                    collector.push_quad(face_index, quad.into());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sphere, SampleShape};
+    use crate::{DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+
+    #[test]
+    fn collected_quads_match_buffers() {
+        let shape = SampleShape {};
+        let samples = sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let ctx = &DefaultVoxelContext;
+
+        let mut greedy = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(&samples, &shape, [0; 3], [33; 3], faces, &mut greedy, ctx);
+        let mut collected = GreedyQuadsBuffer::new(0);
+        mesh_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            MeshAlgorithm::Greedy,
+            &mut collected,
+            ctx,
+        );
+        assert_eq!(collected.quads.groups, greedy.quads.groups);
+
+        let mut unit = UnitQuadBuffer::new();
+        visible_block_faces(&samples, &shape, [0; 3], [33; 3], faces, &mut unit, ctx);
+        let mut collected = UnitQuadBuffer::new();
+        mesh_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            MeshAlgorithm::VisibleFaces,
+            &mut collected,
+            ctx,
+        );
+        assert_eq!(collected.groups, unit.groups);
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{
+    bounds::assert_in_bounds, shell::interior_extent, GreedyMeshPlan, GreedyQuadsBuffer,
+    GreedyQuadsConfig, MergeVoxelContext, MeshData, QuadCoordinateConfig, VoxelMerger, VoxelSource,
+};
+
+use ilattice::glam::{UVec3, Vec3};
+use ndshape::Shape;
+
+/// Arbitrary triangles drawn in place of a voxel's cube, returned by [`VoxelContext::custom_geometry`].
+///
+/// Positions are in units of voxels relative to the minimum corner of the voxel's cell, so `[0.0; 3]` to `[1.0; 3]`
+/// spans the cell, although the geometry may extend past it. Indices refer to the vertices of this mesh.
+///
+/// [`VoxelContext::custom_geometry`]: crate::VoxelContext::custom_geometry
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CustomMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// UV coordinates, in the same convention as [`MeshData::tex_coords`].
+    pub tex_coords: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    /// Appends the vertices and triangles of `mesh`, moved to the cell of the voxel at `position` and scaled by
+    /// `voxel_size`.
+    ///
+    /// The appended vertices have opaque white colors and the extents of a single voxel face. They don't follow the
+    /// layout of 4 vertices and 6 indices per quad, so they should come after every quad in the mesh.
+    pub fn push_custom_mesh(&mut self, mesh: &CustomMesh, position: [u32; 3], voxel_size: f32) {
+        let start = self.positions.len() as u32;
+        let cell = UVec3::from(position).as_vec3();
+        self.positions.extend(
+            mesh.positions
+                .iter()
+                .map(|&p| ((Vec3::from(p) + cell) * voxel_size).to_array()),
+        );
+        self.normals.extend_from_slice(&mesh.normals);
+        self.tex_coords.extend_from_slice(&mesh.tex_coords);
+        self.quad_extents
+            .resize(self.positions.len(), [voxel_size; 2]);
+        self.colors.resize(self.positions.len(), [u8::MAX; 4]);
+        self.indices.extend(mesh.indices.iter().map(|&i| start + i));
+    }
+}
+
+/// Same as [`greedy_quads`](crate::greedy_quads), but voxels with [`VoxelContext::custom_geometry`] are drawn with
+/// their [`CustomMesh`] instead of cube faces, and the result is appended to `output` in units of `voxel_size`.
+///
+/// The quads are left in `buffer` and appended first, as in [`QuadBuffer::to_mesh_data`], so the first
+/// `buffer.quads.vertex_count()` new vertices keep the layout of 4 vertices per quad. The custom meshes follow, in the
+/// order of their voxels' linear indices.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// #[derive(Clone, Copy, Eq, PartialEq)]
+/// enum Block {
+///     Air,
+///     Stone,
+///     Torch,
+/// }
+///
+/// /// Draws torches as a single upright quad.
+/// struct Context {
+///     torch: CustomMesh,
+/// }
+///
+/// impl VoxelContext<Block> for Context {
+///     fn get_visibility(&self, voxel: &Block) -> VoxelVisibility {
+///         match voxel {
+///             Block::Air => VoxelVisibility::Empty,
+///             Block::Stone => VoxelVisibility::Opaque,
+///             Block::Torch => VoxelVisibility::Translucent,
+///         }
+///     }
+///
+///     fn custom_geometry<'a>(&'a self, voxel: &'a Block, _: [u32; 3]) -> Option<&'a CustomMesh> {
+///         (*voxel == Block::Torch).then(|| &self.torch)
+///     }
+/// }
+///
+/// impl MergeVoxelContext<Block> for Context {
+///     type MergeValue = bool;
+///     type MergeValueFacingNeighbour = bool;
+///     fn merge_value(&self, voxel: &Block) -> bool { *voxel == Block::Stone }
+///     fn merge_value_facing_neighbour(&self, voxel: &Block) -> bool { *voxel == Block::Stone }
+/// }
+///
+/// let ctx = Context {
+///     torch: CustomMesh {
+///         positions: vec![[0.5, 0.0, 0.4], [0.5, 0.0, 0.6], [0.5, 0.8, 0.4], [0.5, 0.8, 0.6]],
+///         normals: vec![[1.0, 0.0, 0.0]; 4],
+///         tex_coords: vec![[0.0, 1.0], [1.0, 1.0], [0.0, 0.0], [1.0, 0.0]],
+///         indices: vec![0, 2, 1, 1, 2, 3],
+///     },
+/// };
+///
+/// // A torch on top of a stone block.
+/// type ChunkShape = ConstShape3u32<3, 4, 3>;
+/// let mut voxels = [Block::Air; ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = Block::Stone;
+/// voxels[ChunkShape::linearize([1, 2, 1]) as usize] = Block::Torch;
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// let mut mesh = MeshData::new();
+/// greedy_mesh_with_custom_geometry(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [2, 3, 2],
+///     &RIGHT_HANDED_Y_UP_CONFIG,
+///     1.0,
+///     &mut buffer,
+///     &mut mesh,
+///     &ctx,
+/// );
+/// // The stone is still a full cube, since the torch is translucent, and the torch is a single quad.
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// assert_eq!(mesh.positions.len(), 4 * 6 + 4);
+/// assert_eq!(mesh.positions[4 * 6], [1.5, 2.0, 1.4]);
+/// ```
+///
+/// [`VoxelContext::custom_geometry`]: crate::VoxelContext::custom_geometry
+/// [`QuadBuffer::to_mesh_data`]: crate::QuadBuffer::to_mesh_data
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_mesh_with_custom_geometry<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    config: &QuadCoordinateConfig,
+    voxel_size: f32,
+    buffer: &mut GreedyQuadsBuffer,
+    output: &mut MeshData,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    // Voxels with custom geometry are skipped as if their faces were already meshed.
+    let mut skip = vec![false; voxels.len()];
+    let mut custom = Vec::new();
+    for p in interior_extent(min, max).iter3() {
+        let position = p.to_array();
+        let index = voxels_shape.linearize(position) as usize;
+        if let Some(mesh) = ctx.custom_geometry(voxels.voxel(index), position) {
+            skip[index] = true;
+            custom.push((index, position, mesh));
+        }
+    }
+    custom.sort_unstable_by_key(|&(index, _, _)| index);
+
+    GreedyMeshPlan::new(voxels_shape, &config.faces, min, max)
+        .mesh_skipping::<_, VoxelMerger<V::Voxel>, _>(
+            voxels,
+            &GreedyQuadsConfig::default(),
+            [Some(skip.as_slice()); 6],
+            buffer,
+            None,
+            ctx,
+        );
+
+    for (group, face) in buffer.quads.groups.iter().zip(config.faces.iter()) {
+        for quad in group.iter() {
+            output.push_quad(face, quad, config.u_flip_face, voxel_size);
+        }
+    }
+    for (_, position, mesh) in custom {
+        output.push_custom_mesh(mesh, position, voxel_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InvertedVoxelContext, VoxelContext, VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    #[test]
+    fn custom_voxels_replace_their_cube_faces() {
+        let shape = SampleShape {};
+        let mut samples = [Block::Air; SampleShape::SIZE as usize];
+        // A solid 4^3 cube with one custom voxel replacing a block on its top layer.
+        for i in 0..SampleShape::SIZE {
+            if shape.delinearize(i).iter().all(|c| (1..=4).contains(c)) {
+                samples[i as usize] = Block::Solid;
+            }
+        }
+        let custom_position = [2, 4, 2];
+        samples[shape.linearize(custom_position) as usize] = Block::Custom;
+
+        let ctx = Context {
+            mesh: CustomMesh {
+                positions: vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+                normals: vec![[0.0, 0.0, 1.0]; 3],
+                tex_coords: vec![[0.0; 2]; 3],
+                indices: vec![0, 1, 2],
+            },
+        };
+        let config = &RIGHT_HANDED_Y_UP_CONFIG;
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        let mut mesh = MeshData::new();
+        greedy_mesh_with_custom_geometry(
+            &samples,
+            &shape,
+            [0; 3],
+            [7; 3],
+            config,
+            2.0,
+            &mut buffer,
+            &mut mesh,
+            &ctx,
+        );
+
+        // No quad covers the custom voxel, and the faces of its neighbors around it are visible.
+        for (group, face) in buffer.quads.groups.iter().zip(config.faces.iter()) {
+            for quad in group.iter() {
+                for u in 0..quad.width {
+                    for v in 0..quad.height {
+                        let p = UVec3::from(quad.minimum) + u * face.u + v * face.v;
+                        assert_ne!(p.to_array(), custom_position);
+                    }
+                }
+            }
+        }
+        let area: u32 = buffer
+            .quads
+            .groups
+            .iter()
+            .flatten()
+            .map(|quad| quad.width * quad.height)
+            .sum();
+        assert_eq!(area, 6 * 16 - 1 + 5);
+
+        let num_quad_vertices = buffer.quads.vertex_count();
+        assert_eq!(mesh.positions.len(), num_quad_vertices + 3);
+        assert_eq!(mesh.indices.len(), buffer.quads.index_count() + 3);
+        assert_eq!(mesh.quad_extents.len(), mesh.positions.len());
+        assert_eq!(mesh.colors.len(), mesh.positions.len());
+        assert_eq!(
+            mesh.positions[num_quad_vertices..],
+            [[4.0, 8.0, 4.0], [6.0, 8.0, 4.0], [4.0, 10.0, 4.0]]
+        );
+        let start = num_quad_vertices as u32;
+        assert_eq!(
+            mesh.indices[buffer.quads.index_count()..],
+            [start, start + 1, start + 2]
+        );
+    }
+
+    #[test]
+    fn custom_geometry_is_read_through_wrapper_contexts() {
+        let shape = SampleShape {};
+        let mut samples = [Block::Air; SampleShape::SIZE as usize];
+        samples[shape.linearize([3, 2, 2]) as usize] = Block::Custom;
+
+        let ctx = Context {
+            mesh: CustomMesh {
+                positions: vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+                normals: vec![[0.0, 0.0, 1.0]; 3],
+                tex_coords: vec![[0.0; 2]; 3],
+                indices: vec![0, 1, 2],
+            },
+        };
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        let mut mesh = MeshData::new();
+        greedy_mesh_with_custom_geometry(
+            &samples,
+            &shape,
+            [0; 3],
+            [7; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG,
+            1.0,
+            &mut buffer,
+            &mut mesh,
+            &InvertedVoxelContext::new(&ctx),
+        );
+
+        // The inverted air is meshed around the custom voxel, which still gets its own geometry.
+        assert_eq!(buffer.quads.num_quads(), 6);
+        let num_quad_vertices = buffer.quads.vertex_count();
+        assert_eq!(
+            mesh.positions[num_quad_vertices..],
+            [[3.0, 2.0, 2.0], [4.0, 2.0, 2.0], [3.0, 3.0, 2.0]]
+        );
+    }
+
+    type SampleShape = ConstShape3u32<8, 8, 8>;
+
+    #[derive(Clone, Copy, Eq, PartialEq)]
+    enum Block {
+        Air,
+        Solid,
+        Custom,
+    }
+
+    struct Context {
+        mesh: CustomMesh,
+    }
+
+    impl VoxelContext<Block> for Context {
+        fn get_visibility(&self, voxel: &Block) -> VoxelVisibility {
+            match voxel {
+                Block::Air => VoxelVisibility::Empty,
+                Block::Solid => VoxelVisibility::Opaque,
+                Block::Custom => VoxelVisibility::Translucent,
+            }
+        }
+
+        fn custom_geometry<'a>(
+            &'a self,
+            voxel: &'a Block,
+            _position: [u32; 3],
+        ) -> Option<&'a CustomMesh> {
+            (*voxel == Block::Custom).then_some(&self.mesh)
+        }
+    }
+
+    impl MergeVoxelContext<Block> for Context {
+        type MergeValue = Block;
+        type MergeValueFacingNeighbour = Block;
+
+        fn merge_value(&self, voxel: &Block) -> Block {
+            *voxel
+        }
+
+        fn merge_value_facing_neighbour(&self, voxel: &Block) -> Block {
+            *voxel
+        }
+    }
+}
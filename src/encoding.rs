@@ -0,0 +1,192 @@
+use crate::{GreedyQuadsBuffer, QuadBuffer, UnorientedQuad};
+
+use std::fmt;
+
+/// The reason that [`QuadBuffer::decode`] rejected its input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The input ended in the middle of a value.
+    UnexpectedEnd,
+    /// A varint doesn't fit in a `u32`, or a quad is wider or taller than `u32::MAX`.
+    Overflow,
+    /// There are bytes left over after the last quad.
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of encoded quads"),
+            Self::Overflow => write!(f, "encoded value does not fit in a u32"),
+            Self::TrailingBytes => write!(f, "trailing bytes after encoded quads"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl QuadBuffer {
+    /// Encodes the quads in a compact binary format, e.g. to send them to clients that generate the mesh themselves.
+    ///
+    /// The format is a sequence of unsigned LEB128 varints: 7 bits per byte, least significant group first, with the
+    /// high bit set on every byte but the last. Values below 128 take a single byte, so the quads of a typical chunk
+    /// take 5 bytes each. The varints are:
+    ///
+    /// 1. The number of quads in each of the 6 groups, in order.
+    /// 2. For each group in order, for each quad in the group: `minimum[0]`, `minimum[1]`, `minimum[2]`, `width - 1`,
+    ///    and `height - 1`.
+    ///
+    /// The faces of the groups are not encoded, so the decoder has to know them, e.g. by using the same
+    /// [`QuadCoordinateConfig`](crate::QuadCoordinateConfig).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let mut quads = QuadBuffer::new();
+    /// quads.groups[0].push(UnorientedQuad { minimum: [1, 2, 3], width: 4, height: 200 });
+    ///
+    /// let bytes = quads.encode();
+    /// assert_eq!(bytes, [1, 0, 0, 0, 0, 0, 1, 2, 3, 3, 199, 1]);
+    /// assert_eq!(QuadBuffer::decode(&bytes).unwrap().groups, quads.groups);
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(6 + 5 * self.num_quads());
+        for group in self.groups.iter() {
+            write_varint(&mut bytes, group.len() as u32);
+        }
+        for quad in self.groups.iter().flatten() {
+            for c in quad.minimum {
+                write_varint(&mut bytes, c);
+            }
+            write_varint(&mut bytes, quad.width - 1);
+            write_varint(&mut bytes, quad.height - 1);
+        }
+        bytes
+    }
+
+    /// Decodes quads that were encoded with [`QuadBuffer::encode`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = VarintReader { bytes };
+        let mut lengths = [0; 6];
+        for length in lengths.iter_mut() {
+            *length = reader.read()? as usize;
+        }
+
+        let mut quads = QuadBuffer::new();
+        for (group, length) in quads.groups.iter_mut().zip(lengths) {
+            // Every quad takes at least 5 bytes, so don't trust a length that the input can't hold.
+            group.reserve(length.min(reader.bytes.len() / 5));
+            for _ in 0..length {
+                let minimum = [reader.read()?, reader.read()?, reader.read()?];
+                let width = reader.read()?.checked_add(1).ok_or(DecodeError::Overflow)?;
+                let height = reader.read()?.checked_add(1).ok_or(DecodeError::Overflow)?;
+                group.push(UnorientedQuad {
+                    minimum,
+                    width,
+                    height,
+                });
+            }
+        }
+
+        if reader.bytes.is_empty() {
+            Ok(quads)
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
+
+impl GreedyQuadsBuffer {
+    /// Same as [`QuadBuffer::encode`] for [`GreedyQuadsBuffer::quads`].
+    pub fn encode(&self) -> Vec<u8> {
+        self.quads.encode()
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct VarintReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> VarintReader<'a> {
+    fn read(&mut self) -> Result<u32, DecodeError> {
+        let mut value = 0u32;
+        for shift in (0..32).step_by(7) {
+            let (&byte, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+            self.bytes = rest;
+            let bits = u32::from(byte & 0x7f);
+            // The last byte of a u32 only has 4 bits left.
+            if shift == 28 && bits > 0xf {
+                return Err(DecodeError::Overflow);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_inverts_encode() {
+        let mut quads = QuadBuffer::new();
+        for (i, group) in quads.groups.iter_mut().enumerate() {
+            for j in 0..i as u32 * 50 {
+                group.push(UnorientedQuad {
+                    minimum: [j, 300 * j, u32::MAX - j],
+                    width: 1 + j,
+                    height: u32::MAX - j,
+                });
+            }
+        }
+
+        let bytes = quads.encode();
+        assert_eq!(QuadBuffer::decode(&bytes).unwrap().groups, quads.groups);
+
+        // Every prefix is missing something.
+        for end in [0, 5, bytes.len() / 2, bytes.len() - 1] {
+            assert_eq!(
+                QuadBuffer::decode(&bytes[..end]).err(),
+                Some(DecodeError::UnexpectedEnd)
+            );
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            QuadBuffer::decode(&trailing).err(),
+            Some(DecodeError::TrailingBytes)
+        );
+    }
+
+    #[test]
+    fn decode_rejects_values_that_overflow() {
+        // A varint with 33 significant bits.
+        let mut bytes = vec![0xff, 0xff, 0xff, 0xff, 0x1f];
+        assert_eq!(
+            QuadBuffer::decode(&bytes).err(),
+            Some(DecodeError::Overflow)
+        );
+
+        // A quad that is u32::MAX + 1 voxels wide.
+        bytes = vec![1, 0, 0, 0, 0, 0, 0, 0, 0];
+        write_varint(&mut bytes, u32::MAX);
+        bytes.push(0);
+        assert_eq!(
+            QuadBuffer::decode(&bytes).err(),
+            Some(DecodeError::Overflow)
+        );
+    }
+}
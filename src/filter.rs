@@ -0,0 +1,455 @@
+use crate::{
+    bounds::assert_in_bounds, greedy_quads, ContextAdapter, ForwardingContext, GreedyQuadsBuffer,
+    MergeContextAdapter, MergeVoxelContext, OrientedBlockFace, VoxelContext, VoxelSource,
+    VoxelVisibility,
+};
+
+use ndshape::Shape;
+
+/// Same as [`greedy_quads`], but only voxels for which `include` returns `true` are meshed. Every other voxel is treated
+/// as [`VoxelVisibility::Empty`], so faces of included voxels that touch an excluded voxel are visible.
+///
+/// This is useful for rendering categories of voxels, like terrain, foliage, and fluids, in separate draw calls without
+/// writing a [`VoxelContext`] for each category.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// #[derive(Clone, Copy, Eq, PartialEq)]
+/// enum Block {
+///     Air,
+///     Stone,
+///     Water,
+/// }
+///
+/// impl Voxel for Block {
+///     fn get_visibility(&self) -> VoxelVisibility {
+///         match self {
+///             Block::Air => VoxelVisibility::Empty,
+///             Block::Stone => VoxelVisibility::Opaque,
+///             Block::Water => VoxelVisibility::Translucent,
+///         }
+///     }
+/// }
+///
+/// impl MergeVoxel for Block {
+///     type MergeValue = Self;
+///     type MergeValueFacingNeighbour = bool;
+///     fn merge_value(&self) -> Self { *self }
+///     fn merge_value_facing_neighbour(&self) -> bool { true }
+/// }
+///
+/// type ChunkShape = ConstShape3u32<4, 4, 3>;
+///
+/// // A stone block next to a water block.
+/// let mut voxels = [Block::Air; ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = Block::Stone;
+/// voxels[ChunkShape::linearize([2, 1, 1]) as usize] = Block::Water;
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_with_filter(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [3, 3, 2],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     |block| *block == Block::Water,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// // Only the water is meshed, including the face that touches the stone.
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_filter<V, S, F, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    include: F,
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    F: Fn(&V::Voxel) -> bool,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    let filter_ctx = ForwardingContext(FilterContext { include, ctx });
+    greedy_quads(voxels, voxels_shape, min, max, faces, output, &filter_ctx);
+}
+
+/// Same as [`greedy_quads`], but only the voxels at the coordinates for which `region` returns `true` are meshed. Every
+/// other voxel is treated as [`VoxelVisibility::Empty`], so the surface of the region itself is meshed where it cuts
+/// through solid voxels.
+///
+/// This is useful for showing only a selection in an editor, e.g. a sphere, without copying the selected voxels. The
+/// coordinates passed to `region` are in the same space as `min` and `max`.
+///
+/// This meshes an array with the linear index of each voxel, which takes 4 bytes per voxel and is allocated on every
+/// call, so that `region` can be checked at the position of each voxel.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // Solid ground, with a 2x2x2 selection buried in it.
+/// let voxels = [BoolVoxel(true); ChunkShape::SIZE as usize];
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_masked(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [17; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     |p| p.iter().all(|&c| (4..6).contains(&c)),
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// // Only the surface of the selection is meshed.
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_masked<V, S, F, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    region: F,
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    F: Fn([u32; 3]) -> bool,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    // The index array has the same shape as the voxels, so every stride and quad is the same.
+    let indices: Vec<u32> = (0..voxels_shape.size()).collect();
+    let mask_ctx = ForwardingContext(MaskContext {
+        voxels,
+        voxels_shape,
+        region,
+        ctx,
+    });
+    greedy_quads(&indices, voxels_shape, min, max, faces, output, &mask_ctx);
+}
+
+/// Treats every voxel that doesn't pass `include` as empty.
+struct FilterContext<'a, F, C> {
+    include: F,
+    ctx: &'a C,
+}
+
+impl<'a, T, F, C> ContextAdapter<T> for FilterContext<'a, F, C>
+where
+    F: Fn(&T) -> bool,
+    C: VoxelContext<T>,
+{
+    type Voxel = T;
+    type Inner = C;
+
+    #[inline]
+    fn inner(&self) -> &C {
+        self.ctx
+    }
+
+    #[inline]
+    fn voxel<'b>(&'b self, voxel: &'b T) -> Option<&'b T> {
+        Some(voxel)
+    }
+
+    #[inline]
+    fn get_visibility(&self, voxel: &T) -> VoxelVisibility {
+        if (self.include)(voxel) {
+            self.ctx.get_visibility(voxel)
+        } else {
+            VoxelVisibility::Empty
+        }
+    }
+
+    #[inline]
+    fn get_visibility_for_face(&self, voxel: &T, face_index: usize) -> VoxelVisibility {
+        if (self.include)(voxel) {
+            self.ctx.get_visibility_for_face(voxel, face_index)
+        } else {
+            VoxelVisibility::Empty
+        }
+    }
+}
+
+impl<'a, T, F, C> MergeContextAdapter<T> for FilterContext<'a, F, C>
+where
+    F: Fn(&T) -> bool,
+    C: MergeVoxelContext<T>,
+{
+    type MergeValue = C::MergeValue;
+    // Excluded neighbors all look the same, so faces touching them can merge.
+    type MergeValueFacingNeighbour = Option<C::MergeValueFacingNeighbour>;
+
+    #[inline]
+    fn merge_value(&self, voxel: &T) -> Self::MergeValue {
+        self.ctx.merge_value(voxel)
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, voxel: &T) -> Self::MergeValueFacingNeighbour {
+        (self.include)(voxel).then(|| self.ctx.merge_value_facing_neighbour(voxel))
+    }
+}
+
+/// Reads the voxel at each index from `voxels`, treating every voxel outside of `region` as empty.
+struct MaskContext<'a, V: ?Sized, S, F, C> {
+    voxels: &'a V,
+    voxels_shape: &'a S,
+    region: F,
+    ctx: &'a C,
+}
+
+impl<'a, V, S, F, C> MaskContext<'a, V, S, F, C>
+where
+    V: ?Sized,
+    S: Shape<3, Coord = u32>,
+    F: Fn([u32; 3]) -> bool,
+{
+    #[inline]
+    fn contains(&self, index: u32) -> bool {
+        (self.region)(self.voxels_shape.delinearize(index))
+    }
+}
+
+impl<'a, V, S, F, C> ContextAdapter<u32> for MaskContext<'a, V, S, F, C>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    F: Fn([u32; 3]) -> bool,
+    C: VoxelContext<V::Voxel>,
+{
+    type Voxel = V::Voxel;
+    type Inner = C;
+
+    #[inline]
+    fn inner(&self) -> &C {
+        self.ctx
+    }
+
+    #[inline]
+    fn voxel<'b>(&'b self, index: &'b u32) -> Option<&'b V::Voxel> {
+        Some(self.voxels.voxel(*index as usize))
+    }
+
+    #[inline]
+    fn get_visibility(&self, index: &u32) -> VoxelVisibility {
+        if self.contains(*index) {
+            self.ctx.get_visibility(self.voxels.voxel(*index as usize))
+        } else {
+            VoxelVisibility::Empty
+        }
+    }
+
+    #[inline]
+    fn get_visibility_for_face(&self, index: &u32, face_index: usize) -> VoxelVisibility {
+        if self.contains(*index) {
+            self.ctx
+                .get_visibility_for_face(self.voxels.voxel(*index as usize), face_index)
+        } else {
+            VoxelVisibility::Empty
+        }
+    }
+}
+
+impl<'a, V, S, F, C> MergeContextAdapter<u32> for MaskContext<'a, V, S, F, C>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    F: Fn([u32; 3]) -> bool,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    type MergeValue = C::MergeValue;
+    // Voxels outside of the region all look the same, so faces touching them can merge.
+    type MergeValueFacingNeighbour = Option<C::MergeValueFacingNeighbour>;
+
+    #[inline]
+    fn merge_value(&self, index: &u32) -> Self::MergeValue {
+        self.ctx.merge_value(self.voxels.voxel(*index as usize))
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, index: &u32) -> Self::MergeValueFacingNeighbour {
+        self.contains(*index).then(|| {
+            self.ctx
+                .merge_value_facing_neighbour(self.voxels.voxel(*index as usize))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, SampleShape, EMPTY, FULL};
+    use crate::{DefaultVoxelContext, MergeVoxel, Voxel, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::ConstShape;
+
+    #[test]
+    fn filtered_categories_cover_all_faces_against_empty() {
+        // Two categories of voxels that never touch, so meshing each one separately gives the same quads as meshing
+        // everything at once.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let [x, y, z] = shape.delinearize(i);
+            if (1..=32).contains(&x) && (1..=32).contains(&z) {
+                if y == 4 {
+                    samples[i as usize] = FULL;
+                } else if y == 8 {
+                    samples[i as usize] = BoolVoxel(true, true);
+                }
+            }
+        }
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut all = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut all,
+            &DefaultVoxelContext,
+        );
+
+        let mut num_quads = 0;
+        for category in [false, true] {
+            let mut buffer = GreedyQuadsBuffer::new(samples.len());
+            greedy_quads_with_filter(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                faces,
+                |voxel: &BoolVoxel| voxel.1 == category,
+                &mut buffer,
+                &DefaultVoxelContext,
+            );
+            for quad in buffer.quads.groups.iter().flatten() {
+                let voxel = samples[shape.linearize(quad.minimum) as usize];
+                assert_eq!(voxel, BoolVoxel(true, category));
+            }
+            num_quads += buffer.quads.num_quads();
+        }
+        assert_eq!(num_quads, all.quads.num_quads());
+    }
+
+    #[test]
+    fn masked_matches_meshing_a_masked_copy() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let [x, y, z] = shape.delinearize(i);
+            samples[i as usize] = BoolVoxel(y < 20, (x + z) % 2 == 0);
+        }
+        let in_selection = |p: [u32; 3]| {
+            let [x, y, z] = p.map(|c| c as f32 - 16.0);
+            (x * x + y * y + z * z).sqrt() < 10.0
+        };
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_masked(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            in_selection,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        let mut copy = samples;
+        for i in 0..SampleShape::SIZE {
+            if !in_selection(shape.delinearize(i)) {
+                copy[i as usize] = EMPTY;
+            }
+        }
+        let mut expected = GreedyQuadsBuffer::new(copy.len());
+        greedy_quads(
+            &copy,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        assert!(expected.quads.num_quads() > 0);
+        assert_eq!(buffer.quads.groups, expected.quads.groups);
+    }
+
+    #[test]
+    fn zero_sized_voxels_can_be_masked() {
+        /// Every voxel is solid, so only the region tells them apart.
+        #[derive(Clone, Copy, Eq, PartialEq)]
+        struct Stone;
+
+        impl Voxel for Stone {
+            fn get_visibility(&self) -> VoxelVisibility {
+                VoxelVisibility::Opaque
+            }
+        }
+
+        impl MergeVoxel for Stone {
+            type MergeValue = Self;
+            type MergeValueFacingNeighbour = Self;
+
+            fn merge_value(&self) -> Self {
+                *self
+            }
+
+            fn merge_value_facing_neighbour(&self) -> Self {
+                *self
+            }
+        }
+
+        let samples = [Stone; SampleShape::USIZE];
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_masked(
+            &samples,
+            &SampleShape {},
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            |p| p.iter().all(|c| (4..8).contains(c)),
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(buffer.quads.num_quads(), 6);
+        for quad in buffer.quads.groups.iter().flatten() {
+            assert_eq!((quad.width, quad.height), (4, 4));
+        }
+    }
+}
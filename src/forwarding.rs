@@ -0,0 +1,349 @@
+use crate::{CustomMesh, LayerId, MaterialId, MergeVoxelContext, VoxelContext, VoxelVisibility};
+
+/// A context that wraps an inner [`VoxelContext`], used through a [`ForwardingContext`].
+///
+/// The adapter decides which voxel of the inner context each of its own voxels, or "keys", stands for, e.g. through an
+/// index into a voxel array, and it may change how visible each key is. Every other property, like colors, materials,
+/// and custom geometry, is forwarded to the inner context by [`ForwardingContext`], so a new [`VoxelContext`] hook only
+/// needs to be forwarded in one place to reach the inner context through every adapter.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// /// Reads each voxel from a palette, so a chunk only stores one byte per voxel.
+/// struct Palette<'a> {
+///     voxels: &'a [BoolVoxel],
+/// }
+///
+/// impl ContextAdapter<u8> for Palette<'_> {
+///     type Voxel = BoolVoxel;
+///     type Inner = DefaultVoxelContext;
+///
+///     fn inner(&self) -> &DefaultVoxelContext {
+///         &DefaultVoxelContext
+///     }
+///
+///     fn voxel<'a>(&'a self, key: &'a u8) -> Option<&'a BoolVoxel> {
+///         Some(&self.voxels[*key as usize])
+///     }
+/// }
+///
+/// impl MergeContextAdapter<u8> for Palette<'_> {
+///     type MergeValue = bool;
+///     type MergeValueFacingNeighbour = bool;
+///
+///     fn merge_value(&self, key: &u8) -> bool {
+///         self.voxels[*key as usize].merge_value()
+///     }
+///
+///     fn merge_value_facing_neighbour(&self, key: &u8) -> bool {
+///         self.voxels[*key as usize].merge_value_facing_neighbour()
+///     }
+/// }
+///
+/// type ChunkShape = ConstShape3u32<4, 3, 3>;
+///
+/// // Two blocks of palette entry 1 next to each other.
+/// let mut keys = [0; ChunkShape::SIZE as usize];
+/// keys[ChunkShape::linearize([1, 1, 1]) as usize] = 1;
+/// keys[ChunkShape::linearize([2, 1, 1]) as usize] = 1;
+/// let palette = Palette {
+///     voxels: &[BoolVoxel(false), BoolVoxel(true)],
+/// };
+///
+/// let mut buffer = GreedyQuadsBuffer::new(keys.len());
+/// greedy_quads(
+///     &keys,
+///     &ChunkShape {},
+///     [0; 3],
+///     [3, 2, 2],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &ForwardingContext(palette),
+/// );
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// ```
+pub trait ContextAdapter<K> {
+    /// The voxel type of the inner context.
+    type Voxel;
+    type Inner: VoxelContext<Self::Voxel>;
+
+    fn inner(&self) -> &Self::Inner;
+
+    /// The voxel of the inner context that `key` stands for, or `None` if it doesn't stand for any voxel, in which case
+    /// it is empty and has the default value of every other hook.
+    fn voxel<'a>(&'a self, key: &'a K) -> Option<&'a Self::Voxel>;
+
+    /// Defaults to the visibility of the voxel that `key` stands for.
+    fn get_visibility(&self, key: &K) -> VoxelVisibility {
+        self.voxel(key).map_or(VoxelVisibility::Empty, |voxel| {
+            self.inner().get_visibility(voxel)
+        })
+    }
+
+    /// Defaults to the visibility of the face of the voxel that `key` stands for, so adapters that override
+    /// [`ContextAdapter::get_visibility`] usually override this as well. This has the same requirements as
+    /// [`VoxelContext::get_visibility_for_face`].
+    fn get_visibility_for_face(&self, key: &K, face_index: usize) -> VoxelVisibility {
+        self.voxel(key).map_or(VoxelVisibility::Empty, |voxel| {
+            self.inner().get_visibility_for_face(voxel, face_index)
+        })
+    }
+
+    /// The position of the voxel that `key` stands for in the inner context's voxel array, given the position of `key`
+    /// in the adapter's array. This is passed to [`VoxelContext::custom_geometry`].
+    ///
+    /// Defaults to `position`.
+    fn position(&self, key: &K, position: [u32; 3]) -> [u32; 3] {
+        let _ = key;
+        position
+    }
+}
+
+/// The merge values of a [`ContextAdapter`] whose inner context is a [`MergeVoxelContext`].
+///
+/// [`MergeVoxelContext::can_merge_pair`] is forwarded to the inner context by [`ForwardingContext`].
+pub trait MergeContextAdapter<K>: ContextAdapter<K> {
+    type MergeValue: Eq;
+    type MergeValueFacingNeighbour: Eq;
+
+    fn merge_value(&self, key: &K) -> Self::MergeValue;
+
+    fn merge_value_facing_neighbour(&self, key: &K) -> Self::MergeValueFacingNeighbour;
+}
+
+/// The [`VoxelContext`] of a [`ContextAdapter`], which reads visibility and merge values from the adapter and forwards
+/// every other hook to the voxel of the inner context that each key stands for.
+pub struct ForwardingContext<A>(pub A);
+
+impl<K, A> VoxelContext<K> for ForwardingContext<A>
+where
+    A: ContextAdapter<K>,
+{
+    #[inline]
+    fn get_visibility(&self, key: &K) -> VoxelVisibility {
+        self.0.get_visibility(key)
+    }
+
+    #[inline]
+    fn get_visibility_for_face(&self, key: &K, face_index: usize) -> VoxelVisibility {
+        self.0.get_visibility_for_face(key, face_index)
+    }
+
+    #[inline]
+    fn face_inset(&self, key: &K, face_index: usize) -> f32 {
+        self.0
+            .voxel(key)
+            .map_or(0.0, |voxel| self.0.inner().face_inset(voxel, face_index))
+    }
+
+    #[inline]
+    fn color(&self, key: &K, face_index: usize) -> [u8; 4] {
+        self.0.voxel(key).map_or([u8::MAX; 4], |voxel| {
+            self.0.inner().color(voxel, face_index)
+        })
+    }
+
+    #[inline]
+    fn texture_rotation(&self, key: &K, face_index: usize) -> u8 {
+        self.0.voxel(key).map_or(0, |voxel| {
+            self.0.inner().texture_rotation(voxel, face_index)
+        })
+    }
+
+    #[inline]
+    fn face_layers<'b>(&'b self, key: &'b K, face_index: usize) -> &'b [LayerId] {
+        self.0
+            .voxel(key)
+            .map_or(&[], |voxel| self.0.inner().face_layers(voxel, face_index))
+    }
+
+    #[inline]
+    fn material_id(&self, key: &K, face_index: usize) -> MaterialId {
+        self.0
+            .voxel(key)
+            .map_or(0, |voxel| self.0.inner().material_id(voxel, face_index))
+    }
+
+    #[inline]
+    fn custom_geometry<'b>(&'b self, key: &'b K, position: [u32; 3]) -> Option<&'b CustomMesh> {
+        self.0.voxel(key).and_then(|voxel| {
+            self.0
+                .inner()
+                .custom_geometry(voxel, self.0.position(key, position))
+        })
+    }
+}
+
+impl<K, A> MergeVoxelContext<K> for ForwardingContext<A>
+where
+    A: MergeContextAdapter<K>,
+    A::Inner: MergeVoxelContext<A::Voxel>,
+{
+    type MergeValue = A::MergeValue;
+    type MergeValueFacingNeighbour = A::MergeValueFacingNeighbour;
+
+    #[inline]
+    fn merge_value(&self, key: &K) -> Self::MergeValue {
+        self.0.merge_value(key)
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, key: &K) -> Self::MergeValueFacingNeighbour {
+        self.0.merge_value_facing_neighbour(key)
+    }
+
+    #[inline]
+    fn can_merge_pair(&self, quad_key: &K, key: &K, face_index: usize) -> bool {
+        match (self.0.voxel(quad_key), self.0.voxel(key)) {
+            (Some(quad_voxel), Some(voxel)) => {
+                self.0.inner().can_merge_pair(quad_voxel, voxel, face_index)
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a different value from every hook than the defaults.
+    struct HookContext {
+        mesh: CustomMesh,
+    }
+
+    impl VoxelContext<u8> for HookContext {
+        fn get_visibility(&self, _voxel: &u8) -> VoxelVisibility {
+            VoxelVisibility::Translucent
+        }
+
+        fn face_inset(&self, _voxel: &u8, _face_index: usize) -> f32 {
+            0.5
+        }
+
+        fn color(&self, voxel: &u8, _face_index: usize) -> [u8; 4] {
+            [*voxel; 4]
+        }
+
+        fn texture_rotation(&self, _voxel: &u8, _face_index: usize) -> u8 {
+            1
+        }
+
+        fn face_layers<'a>(&'a self, _voxel: &'a u8, _face_index: usize) -> &'a [LayerId] {
+            &[7]
+        }
+
+        fn material_id(&self, voxel: &u8, _face_index: usize) -> MaterialId {
+            *voxel as MaterialId
+        }
+
+        fn custom_geometry<'a>(
+            &'a self,
+            _voxel: &'a u8,
+            position: [u32; 3],
+        ) -> Option<&'a CustomMesh> {
+            (position == [1, 2, 3]).then_some(&self.mesh)
+        }
+    }
+
+    impl MergeVoxelContext<u8> for HookContext {
+        type MergeValue = ();
+        type MergeValueFacingNeighbour = ();
+
+        fn merge_value(&self, _voxel: &u8) {}
+
+        fn merge_value_facing_neighbour(&self, _voxel: &u8) {}
+
+        fn can_merge_pair(&self, quad_voxel: &u8, voxel: &u8, _face_index: usize) -> bool {
+            quad_voxel == voxel
+        }
+    }
+
+    /// Reads voxels by index, where `u32::MAX` is outside of the voxels, and moves every position by one along X.
+    struct Indexed<'a> {
+        voxels: &'a [u8],
+        ctx: &'a HookContext,
+    }
+
+    impl ContextAdapter<u32> for Indexed<'_> {
+        type Voxel = u8;
+        type Inner = HookContext;
+
+        fn inner(&self) -> &HookContext {
+            self.ctx
+        }
+
+        fn voxel<'a>(&'a self, index: &'a u32) -> Option<&'a u8> {
+            self.voxels.get(*index as usize)
+        }
+
+        fn position(&self, _index: &u32, [x, y, z]: [u32; 3]) -> [u32; 3] {
+            [x + 1, y, z]
+        }
+    }
+
+    impl MergeContextAdapter<u32> for Indexed<'_> {
+        type MergeValue = ();
+        type MergeValueFacingNeighbour = ();
+
+        fn merge_value(&self, _index: &u32) {}
+
+        fn merge_value_facing_neighbour(&self, _index: &u32) {}
+    }
+
+    #[test]
+    fn every_hook_is_forwarded_to_the_inner_context() {
+        let inner = HookContext {
+            mesh: CustomMesh::default(),
+        };
+        let ctx = ForwardingContext(Indexed {
+            voxels: &[3, 4],
+            ctx: &inner,
+        });
+
+        assert_eq!(ctx.get_visibility(&1), VoxelVisibility::Translucent);
+        assert_eq!(
+            ctx.get_visibility_for_face(&1, 0),
+            VoxelVisibility::Translucent
+        );
+        assert_eq!(ctx.face_inset(&1, 0), 0.5);
+        assert_eq!(ctx.color(&1, 0), [4; 4]);
+        assert_eq!(ctx.texture_rotation(&1, 0), 1);
+        assert_eq!(ctx.face_layers(&1, 0), &[7]);
+        assert_eq!(ctx.material_id(&1, 0), 4);
+        assert_eq!(ctx.custom_geometry(&1, [0, 2, 3]), Some(&inner.mesh));
+        assert!(ctx.can_merge_pair(&0, &0, 0));
+        assert!(!ctx.can_merge_pair(&0, &1, 0));
+
+        // Keys that don't stand for a voxel are empty and get the defaults.
+        let outside = u32::MAX;
+        assert_eq!(ctx.get_visibility(&outside), VoxelVisibility::Empty);
+        assert_eq!(
+            ctx.get_visibility_for_face(&outside, 0),
+            VoxelVisibility::Empty
+        );
+        assert_eq!(ctx.face_inset(&outside, 0), 0.0);
+        assert_eq!(ctx.color(&outside, 0), [u8::MAX; 4]);
+        assert_eq!(ctx.texture_rotation(&outside, 0), 0);
+        assert!(ctx.face_layers(&outside, 0).is_empty());
+        assert_eq!(ctx.material_id(&outside, 0), 0);
+        assert_eq!(ctx.custom_geometry(&outside, [0, 2, 3]), None);
+        assert!(ctx.can_merge_pair(&0, &outside, 0));
+    }
+}
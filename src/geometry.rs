@@ -110,10 +110,12 @@
 //! [`QuadCoordinateConfig`], which also implicitly defines the coordinate
 //! system.
 
+mod ao;
 mod axis;
 mod face;
 mod quad;
 
+pub use ao::*;
 pub use axis::*;
 pub use face::*;
 pub use quad::*;
@@ -158,6 +160,85 @@ pub struct QuadCoordinateConfig {
     pub u_flip_face: Axis,
 }
 
+/// The handedness of a coordinate system, which determines the winding of the triangles generated for it.
+///
+/// See the [`geometry` module documentation][crate::geometry] for more information.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Handedness {
+    Left,
+    #[default]
+    Right,
+}
+
+impl QuadCoordinateConfig {
+    /// Returns a config for the coordinate system with `up` pointing up and the given `handedness`, keeping the order of
+    /// [`Self::faces`], so each face index still refers to the same normal direction.
+    ///
+    /// On every face whose normal isn't along `up`, V points up, so textures are upright. The faces are wound for
+    /// `handedness` (see [`OrientedBlockFace::quad_mesh_indices`]), and [`Self::u_flip_face`] is chosen so that U points
+    /// to the right when looking at the front of a side face.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let config = RIGHT_HANDED_Y_UP_CONFIG.converted_to(Axis::Z, Handedness::Left);
+    /// assert_eq!(config.faces, LEFT_HANDED_Z_UP_CONFIG.faces);
+    ///
+    /// let top = config.face_index(SignedAxis::PosZ);
+    /// assert_eq!(config.faces[top].quad_mesh_normals()[0], [0.0, 0.0, 1.0]);
+    /// ```
+    pub fn converted_to(&self, up: Axis, handedness: Handedness) -> Self {
+        let faces = self.faces.map(|face| {
+            let normal = face.axis();
+            let even = AxisPermutation::even_with_normal_axis(normal);
+            let permutation = if normal == up || even.axes()[2] == up {
+                even
+            } else {
+                AxisPermutation::odd_with_normal_axis(normal)
+            };
+            OrientedBlockFace::new(face.n_sign(), permutation).with_handedness(handedness)
+        });
+        // Looking at the front of a side face, right is up x normal in a right-handed system, and normal x up in a
+        // left-handed one. U points along the axis that follows the normal axis in the cycle X -> Y -> Z -> X, which
+        // is backwards on the positive face of the axis before (or after) the up axis.
+        let u_flip_face = match (up, handedness) {
+            (Axis::X, Handedness::Right) | (Axis::Y, Handedness::Left) => Axis::Z,
+            (Axis::Y, Handedness::Right) | (Axis::Z, Handedness::Left) => Axis::X,
+            (Axis::Z, Handedness::Right) | (Axis::X, Handedness::Left) => Axis::Y,
+        };
+
+        Self { faces, u_flip_face }
+    }
+
+    /// Returns the index into [`Self::faces`] (and therefore into the quad buffer groups) of the face with the normal
+    /// `direction`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let top = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+    /// assert_eq!(top, 4);
+    /// assert_eq!(RIGHT_HANDED_Y_UP_CONFIG.face_direction(top), SignedAxis::PosY);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If none of the faces has the normal `direction`.
+    pub fn face_index(&self, direction: SignedAxis) -> usize {
+        self.faces
+            .iter()
+            .position(|face| face.signed_axis() == direction)
+            .unwrap_or_else(|| panic!("No face with normal {direction:?}"))
+    }
+
+    /// Returns the direction of the normal of the face at `index`.
+    pub fn face_direction(&self, index: usize) -> SignedAxis {
+        self.faces[index].signed_axis()
+    }
+}
+
 /// Coordinate configuration for a right-handed coordinate system with Y up.
 ///
 /// ```text
@@ -181,3 +262,114 @@ pub const RIGHT_HANDED_Y_UP_CONFIG: QuadCoordinateConfig = QuadCoordinateConfig
     ],
     u_flip_face: Axis::X,
 };
+
+/// Coordinate configuration for a right-handed coordinate system with Z up.
+///
+/// ```text
+///       +Z      
+///       | +Y    
+/// -X____|/____+X
+///      /|       
+///    -Y |       
+///       -Z      
+/// ```
+pub const RIGHT_HANDED_Z_UP_CONFIG: QuadCoordinateConfig = QuadCoordinateConfig {
+    // Z is always in the V direction when it's not the normal. When Z is the
+    // normal, we use the even Zxy permutation like the other configs.
+    faces: [
+        OrientedBlockFace::new(-1, AxisPermutation::Xyz),
+        OrientedBlockFace::new(-1, AxisPermutation::Yxz),
+        OrientedBlockFace::new(-1, AxisPermutation::Zxy),
+        OrientedBlockFace::new(1, AxisPermutation::Xyz),
+        OrientedBlockFace::new(1, AxisPermutation::Yxz),
+        OrientedBlockFace::new(1, AxisPermutation::Zxy),
+    ],
+    u_flip_face: Axis::Y,
+};
+
+/// Coordinate configuration for a left-handed coordinate system with Z up.
+///
+/// ```text
+///       +Z      
+///       | -Y    
+/// -X____|/____+X
+///      /|       
+///    +Y |       
+///       -Z      
+/// ```
+pub const LEFT_HANDED_Z_UP_CONFIG: QuadCoordinateConfig = QuadCoordinateConfig {
+    // The same axes as the right-handed config, with the triangles wound the
+    // other way and the U coordinates flipped on the other side axis.
+    faces: [
+        OrientedBlockFace::new(-1, AxisPermutation::Xyz).with_handedness(Handedness::Left),
+        OrientedBlockFace::new(-1, AxisPermutation::Yxz).with_handedness(Handedness::Left),
+        OrientedBlockFace::new(-1, AxisPermutation::Zxy).with_handedness(Handedness::Left),
+        OrientedBlockFace::new(1, AxisPermutation::Xyz).with_handedness(Handedness::Left),
+        OrientedBlockFace::new(1, AxisPermutation::Yxz).with_handedness(Handedness::Left),
+        OrientedBlockFace::new(1, AxisPermutation::Zxy).with_handedness(Handedness::Left),
+    ],
+    u_flip_face: Axis::X,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ilattice::glam::Vec3;
+
+    #[test]
+    fn converted_configs_match_constants() {
+        for (up, handedness, expected) in [
+            (Axis::Y, Handedness::Right, &RIGHT_HANDED_Y_UP_CONFIG),
+            (Axis::Z, Handedness::Right, &RIGHT_HANDED_Z_UP_CONFIG),
+            (Axis::Z, Handedness::Left, &LEFT_HANDED_Z_UP_CONFIG),
+        ] {
+            let config = RIGHT_HANDED_Y_UP_CONFIG.converted_to(up, handedness);
+            assert_eq!(config.faces, expected.faces);
+            assert_eq!(config.u_flip_face, expected.u_flip_face);
+        }
+    }
+
+    #[test]
+    fn converted_faces_are_oriented_for_their_coordinate_system() {
+        let quad = UnorientedQuad {
+            minimum: [0; 3],
+            width: 1,
+            height: 1,
+        };
+        for up in [Axis::X, Axis::Y, Axis::Z] {
+            for handedness in [Handedness::Left, Handedness::Right] {
+                let config = RIGHT_HANDED_Y_UP_CONFIG.converted_to(up, handedness);
+                let up_vector = up.get_unit_vector().as_ivec3();
+                for (face, original) in config.faces.iter().zip(RIGHT_HANDED_Y_UP_CONFIG.faces) {
+                    let normal = face.signed_normal();
+                    assert_eq!(normal, original.signed_normal());
+                    assert_eq!(face.quad_mesh_normals()[0], normal.as_vec3().to_array());
+
+                    // The triangles are counterclockwise from the front in this coordinate system.
+                    let positions = face.quad_mesh_positions(&quad, 1.0).map(Vec3::from);
+                    for triangle in face.quad_mesh_indices(0, false).chunks(3) {
+                        let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+                        let winding = (b - a).cross(c - a).dot(normal.as_vec3());
+                        match handedness {
+                            Handedness::Right => assert!(winding > 0.0),
+                            Handedness::Left => assert!(winding < 0.0),
+                        }
+                    }
+
+                    if face.axis() == up {
+                        continue;
+                    }
+                    // Textures are upright, and U points to the right of a viewer in front of the face.
+                    assert_eq!(face.v.as_ivec3(), up_vector);
+                    let tex_coords = face.tex_coords(config.u_flip_face, false, &quad);
+                    let u_sign = (tex_coords[1][0] - tex_coords[0][0]) as i32;
+                    let right = match handedness {
+                        Handedness::Right => up_vector.cross(normal),
+                        Handedness::Left => normal.cross(up_vector),
+                    };
+                    assert_eq!(face.u.as_ivec3() * u_sign, right);
+                }
+            }
+        }
+    }
+}
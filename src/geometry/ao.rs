@@ -0,0 +1,206 @@
+use crate::{OrientedBlockFace, QuadBuffer, VoxelContext, VoxelSource, VoxelVisibility};
+
+use ilattice::glam::{IVec3, UVec3};
+use ndshape::Shape;
+
+/// Turns per-corner ambient occlusion values into shader-ready vertex attributes.
+///
+/// The input values are in the same convention as [`ao_prefers_flip`](crate::ao_prefers_flip): `0..=3`, where `3`
+/// means the corner is not occluded at all.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// let approx_eq = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-6);
+///
+/// let config = AoConfig::default();
+/// assert!(approx_eq(config.vertex_values([3, 2, 1, 0]), [1.0, 0.7, 0.4, 0.2]));
+///
+/// let half = AoConfig { strength: 0.5, ..Default::default() };
+/// assert!(approx_eq(half.vertex_values([3, 3, 3, 0]), [1.0, 1.0, 1.0, 0.6]));
+///
+/// let raw = AoConfig { smooth: false, ..Default::default() };
+/// assert_eq!(raw.vertex_values([3, 2, 1, 0]), [3.0, 2.0, 1.0, 0.0]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AoConfig {
+    /// How dark occluded corners are, from `0.0` (no occlusion) to `1.0` (the full [`AoConfig::BRIGHTNESS`] curve).
+    /// Ignored unless `smooth` is set.
+    pub strength: f32,
+    /// If set, each value is mapped to a brightness in `0.0..=1.0`. Otherwise the raw integer values are returned as
+    /// floats, e.g. for flat or stylized shading that does its own mapping.
+    pub smooth: bool,
+}
+
+impl Default for AoConfig {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            smooth: true,
+        }
+    }
+}
+
+impl AoConfig {
+    /// The brightness of a corner for each ambient occlusion value at full strength.
+    pub const BRIGHTNESS: [f32; 4] = [0.2, 0.4, 0.7, 1.0];
+
+    /// Returns the vertex attribute for each corner, in the same order as `ao`. Values above 3 are treated as 3.
+    #[inline]
+    pub fn vertex_values(&self, ao: [u8; 4]) -> [f32; 4] {
+        ao.map(|value| {
+            let value = value.min(3);
+            if self.smooth {
+                1.0 - self.strength * (1.0 - Self::BRIGHTNESS[value as usize])
+            } else {
+                value as f32
+            }
+        })
+    }
+}
+
+/// Computes the ambient occlusion of the 4 corners of every quad in `quads`, in the same order as the quads would be
+/// meshed by [`QuadBuffer::to_mesh_data`], so the AO can be applied to some meshes without meshing differently.
+///
+/// Each corner is darkened by the [`VoxelVisibility::Opaque`] voxels that touch it in the layer in front of the face,
+/// as described in the [0fps article](https://0fps.net/2013/07/03/ambient-occlusion-for-minecraft-like-worlds/). The
+/// values are in the order of [`OrientedBlockFace::quad_corners`] and in the convention of
+/// [`ao_prefers_flip`](crate::ao_prefers_flip) and [`AoConfig::vertex_values`]. Only the voxels at the corners of
+/// merged quads are checked, so occluders along the middle of an edge don't darken the quad. Voxels outside of
+/// `voxels_shape`, like those in front of faces on the boundary of the array, are treated as not opaque.
+///
+/// `voxels`, `voxels_shape`, and `faces` must be the same as when the quads were generated.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<4, 4, 4>;
+///
+/// // A block, with another block diagonally above it along +X.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// voxels[ChunkShape::linearize([2, 2, 1]) as usize] = BoolVoxel(true);
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads(&voxels, &ChunkShape {}, [0; 3], [3; 3], faces, &mut buffer, &DefaultVoxelContext);
+/// let ao = compute_ao_for_buffer(&buffer.quads, &voxels, &ChunkShape {}, faces, &DefaultVoxelContext);
+/// assert_eq!(ao.len(), buffer.quads.num_quads());
+///
+/// // The corners of the lower block's top face next to the upper block are darker.
+/// let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+/// let (i, quad) = buffer.quads.groups[pos_y]
+///     .iter()
+///     .enumerate()
+///     .find(|(_, quad)| quad.minimum == [1, 1, 1])
+///     .unwrap();
+/// let i = buffer.quads.groups[..pos_y].iter().map(Vec::len).sum::<usize>() + i;
+/// for (corner, value) in faces[pos_y].quad_corners(quad).iter().zip(ao[i]) {
+///     assert_eq!(value, if corner.x == 2 { 2 } else { 3 });
+/// }
+/// ```
+pub fn compute_ao_for_buffer<V, S, C>(
+    quads: &QuadBuffer,
+    voxels: &V,
+    voxels_shape: &S,
+    faces: &[OrientedBlockFace; 6],
+    ctx: &C,
+) -> Vec<[u8; 4]>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    let shape = UVec3::from(voxels_shape.as_array()).as_ivec3();
+    let is_opaque = |p: IVec3| {
+        if p.cmplt(IVec3::ZERO).any() || p.cmpge(shape).any() {
+            return false;
+        }
+        let index = voxels_shape.linearize(p.as_uvec3().to_array()) as usize;
+        ctx.get_visibility(voxels.voxel(index)) == VoxelVisibility::Opaque
+    };
+
+    let mut ao = Vec::with_capacity(quads.num_quads());
+    for (group, face) in quads.groups.iter().zip(faces.iter()) {
+        let normal = face.signed_normal();
+        let u = face.u.as_ivec3();
+        let v = face.v.as_ivec3();
+        for quad in group.iter() {
+            // The voxel in front of each corner of the quad, and the directions away from the quad along U and V.
+            let front = UVec3::from(quad.minimum).as_ivec3() + normal;
+            let max_u = u * (quad.width as i32 - 1);
+            let max_v = v * (quad.height as i32 - 1);
+            let corners = [
+                (front, -u, -v),
+                (front + max_u, u, -v),
+                (front + max_v, -u, v),
+                (front + max_u + max_v, u, v),
+            ];
+            ao.push(corners.map(|(p, du, dv)| {
+                vertex_ao(is_opaque(p + du), is_opaque(p + dv), is_opaque(p + du + dv))
+            }));
+        }
+    }
+    ao
+}
+
+/// The ambient occlusion of a corner with the given occupied neighbors, from `0` (fully occluded) to `3`.
+fn vertex_ao(side1: bool, side2: bool, corner: bool) -> u8 {
+    if side1 && side2 {
+        0
+    } else {
+        3 - (side1 as u8 + side2 as u8 + corner as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::FULL;
+    use crate::{
+        greedy_quads_bounds_checked, DefaultVoxelContext, GreedyQuadsBuffer,
+        RIGHT_HANDED_Y_UP_CONFIG,
+    };
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    #[test]
+    fn quads_on_the_array_boundary_are_not_occluded() {
+        type ChunkShape = ConstShape3u32<4, 4, 4>;
+        let voxels = [FULL; ChunkShape::USIZE];
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+        greedy_quads_bounds_checked(
+            &voxels,
+            &ChunkShape {},
+            faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(buffer.quads.num_quads(), 6);
+
+        let ao = compute_ao_for_buffer(
+            &buffer.quads,
+            &voxels,
+            &ChunkShape {},
+            faces,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(ao, vec![[3; 4]; 6]);
+    }
+}
@@ -1,6 +1,6 @@
-use crate::{Axis, AxisPermutation, SignedAxis, UnorientedQuad};
+use crate::{Axis, AxisPermutation, Handedness, SignedAxis, UnorientedQuad};
 
-use ilattice::glam::{IVec3, UVec3};
+use ilattice::glam::{IVec3, UVec3, Vec3};
 
 /// Metadata that's used to aid in the geometric calculations for one of the 6 possible cube faces.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -17,6 +17,9 @@ pub struct OrientedBlockFace {
     pub(crate) u: UVec3,
     /// Third in the `permutation` of +X, +Y, and +Z.
     pub(crate) v: UVec3,
+
+    /// Determines which way the triangles are wound.
+    pub(crate) handedness: Handedness,
 }
 
 impl OrientedBlockFace {
@@ -29,9 +32,17 @@ impl OrientedBlockFace {
             n: n_axis.get_unit_vector(),
             u: u_axis.get_unit_vector(),
             v: v_axis.get_unit_vector(),
+            handedness: Handedness::Right,
         }
     }
 
+    /// Returns this face with triangles wound for a coordinate system of `handedness`. See
+    /// [`OrientedBlockFace::quad_mesh_indices`].
+    pub const fn with_handedness(mut self, handedness: Handedness) -> Self {
+        self.handedness = handedness;
+        self
+    }
+
     /// A cube face, using axes with an even permutation.
     pub fn canonical(normal: SignedAxis) -> Self {
         Self::new(
@@ -52,6 +63,18 @@ impl OrientedBlockFace {
         SignedAxis::new(self.n_sign, axis)
     }
 
+    /// The unsigned axis of the normal vector.
+    #[inline]
+    pub fn axis(&self) -> Axis {
+        self.permutation.axes()[0]
+    }
+
+    /// Returns `true` iff the normal points in the positive direction of [`Self::axis`].
+    #[inline]
+    pub fn is_positive(&self) -> bool {
+        self.n_sign > 0
+    }
+
     #[inline]
     pub fn n_sign(&self) -> i32 {
         self.n_sign
@@ -62,11 +85,35 @@ impl OrientedBlockFace {
         self.permutation
     }
 
+    #[inline]
+    pub fn handedness(&self) -> Handedness {
+        self.handedness
+    }
+
+    /// Returns `true` iff corners 0, 1, and 2 of [`OrientedBlockFace::quad_corners`] are counterclockwise when viewed
+    /// from the front of the face in a coordinate system of [`Self::handedness`].
+    #[inline]
+    pub(crate) fn corners_are_counter_clockwise(&self) -> bool {
+        let right_handed = self.n_sign * self.permutation.sign() > 0;
+        match self.handedness {
+            Handedness::Right => right_handed,
+            Handedness::Left => !right_handed,
+        }
+    }
+
     #[inline]
     pub fn signed_normal(&self) -> IVec3 {
         self.n.as_ivec3() * self.n_sign
     }
 
+    /// Returns [`Self::signed_normal`] encoded with [`oct_encode_normal`], for compact vertex formats.
+    ///
+    /// Axis-aligned normals are exactly representable, so [`oct_decode_normal`] returns the normal unchanged.
+    #[inline]
+    pub fn packed_normal(&self) -> u16 {
+        oct_encode_normal(self.signed_normal().as_vec3().to_array())
+    }
+
     /// Returns the 4 corners of the quad in this order:
     ///
     /// ```text
@@ -84,6 +131,16 @@ impl OrientedBlockFace {
     ///
     /// Note that this is natural when UV coordinates have (0,0) at the bottom
     /// left, but when (0,0) is at the top left, V must be flipped.
+    ///
+    /// In other words, corner 0 is (min U, min V), 1 is (max U, min V), 2 is
+    /// (min U, max V), and 3 is (max U, max V), where U and V are the
+    /// coordinates along [`Self::permutation`]'s second and third axes. This
+    /// holds for every face. All per-vertex data follows the same order,
+    /// including [`OrientedBlockFace::quad_mesh_positions`],
+    /// [`OrientedBlockFace::quad_mesh_normals`],
+    /// [`OrientedBlockFace::tex_coords`], ambient occlusion values for
+    /// [`ao_prefers_flip`], [`quad_barycentric_coords`], and every group of 4
+    /// vertices in [`MeshData`](crate::MeshData).
     #[inline]
     pub fn quad_corners(&self, quad: &UnorientedQuad) -> [UVec3; 4] {
         let w_vec = self.u * quad.width;
@@ -101,26 +158,125 @@ impl OrientedBlockFace {
         [minu_minv, maxu_minv, minu_maxv, maxu_maxv]
     }
 
+    /// Returns the exact lattice coordinates of the 4 corners of the quad, in the same order as
+    /// [`OrientedBlockFace::quad_corners`].
+    ///
+    /// This is useful for collision meshes or spatial hashing, where `f32` positions would lose precision.
+    #[inline]
+    pub fn quad_mesh_positions_u32(&self, quad: &UnorientedQuad) -> [[u32; 3]; 4] {
+        self.quad_corners(quad).map(|c| c.to_array())
+    }
+
+    /// Returns the center of the quad, in the same coordinates as [`OrientedBlockFace::quad_corners`].
+    #[inline]
+    pub fn quad_center(&self, quad: &UnorientedQuad) -> Vec3 {
+        let [min, _, _, max] = self.quad_corners(quad);
+        (min + max).as_vec3() / 2.0
+    }
+
+    /// Returns the positions of the 4 corners of the quad, scaled by `voxel_size`, in the same order as
+    /// [`OrientedBlockFace::quad_corners`].
     #[inline]
     pub fn quad_mesh_positions(&self, quad: &UnorientedQuad, voxel_size: f32) -> [[f32; 3]; 4] {
-        self.quad_corners(quad)
-            .map(|c| (voxel_size * c.as_vec3()).to_array())
+        self.quad_mesh_positions_at(quad, voxel_size, Vec3::ZERO)
+    }
+
+    /// Same as [`OrientedBlockFace::quad_mesh_positions`], but `origin` is added to every position, e.g. to place a chunk
+    /// in world space without another pass over the vertices.
+    ///
+    /// The sum is computed in `f32`, so positions lose precision as `origin` gets large: beyond about 2^24 /
+    /// `voxel_size` voxels from the world origin, vertices can no longer be placed exactly on voxel boundaries. Worlds
+    /// that large should use a floating origin instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// # use block_mesh::ilattice::glam::Vec3;
+    /// let face = OrientedBlockFace::canonical(SignedAxis::PosY);
+    /// let quad = UnorientedQuad { minimum: [0; 3], width: 1, height: 1 };
+    /// // A chunk at chunk coordinates (2, 0, 0) with 16 voxels per side.
+    /// let positions = face.quad_mesh_positions_at(&quad, 1.0, Vec3::new(32.0, 0.0, 0.0));
+    /// assert_eq!(positions[0], [32.0, 1.0, 0.0]);
+    /// ```
+    #[inline]
+    pub fn quad_mesh_positions_at(
+        &self,
+        quad: &UnorientedQuad,
+        voxel_size: f32,
+        origin: Vec3,
+    ) -> [[f32; 3]; 4] {
+        self.quad_mesh_positions_u32(quad)
+            .map(|c| (origin + voxel_size * UVec3::from(c).as_vec3()).to_array())
     }
 
+    /// Same as [`OrientedBlockFace::quad_mesh_positions`], but for a voxel that only fills a box inside of its cell. The box
+    /// is inset from the minimum sides of the cell by `min_inset` and from the maximum sides by `max_inset`, in units of
+    /// voxels.
+    ///
+    /// The quad is moved inward along its normal by the inset of its own side, and its edges are moved inward by the
+    /// insets of the adjacent sides.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// # use block_mesh::ilattice::glam::Vec3;
+    /// // A pane that is 0.2 voxels thick, centered on Z.
+    /// let min_inset = Vec3::new(0.0, 0.0, 0.4);
+    /// let max_inset = min_inset;
+    ///
+    /// let face = OrientedBlockFace::canonical(SignedAxis::PosZ);
+    /// let quad = UnorientedQuad { minimum: [0; 3], width: 1, height: 1 };
+    /// let positions = face.quad_mesh_positions_inset(&quad, 1.0, min_inset, max_inset);
+    /// assert!(positions.iter().all(|p| p[2] == 0.6));
+    /// ```
+    #[inline]
+    pub fn quad_mesh_positions_inset(
+        &self,
+        quad: &UnorientedQuad,
+        voxel_size: f32,
+        min_inset: Vec3,
+        max_inset: Vec3,
+    ) -> [[f32; 3]; 4] {
+        let (min_inset, max_inset) = (min_inset.to_array(), max_inset.to_array());
+        self.quad_mesh_positions_u32(quad).map(|corner| {
+            let mut p = UVec3::from(corner).as_vec3().to_array();
+            for i in 0..3 {
+                if corner[i] == quad.minimum[i] {
+                    p[i] += min_inset[i];
+                } else {
+                    p[i] -= max_inset[i];
+                }
+            }
+            (voxel_size * Vec3::from(p)).to_array()
+        })
+    }
+
+    /// Returns the normal of each of the 4 corners of the quad, in the same order as
+    /// [`OrientedBlockFace::quad_corners`]. They are all the same.
     #[inline]
     pub fn quad_mesh_normals(&self) -> [[f32; 3]; 4] {
         [self.signed_normal().as_vec3().to_array(); 4]
     }
 
     /// Returns the 6 vertex indices for the quad in order to make two triangles
-    /// in a mesh. Winding order depends on both the sign of the surface normal
-    /// and the permutation of the UVs.
+    /// in a mesh. Winding order depends on the sign of the surface normal, the
+    /// permutation of the UVs, and the handedness of the face.
     ///
     /// Front faces will be wound counterclockwise, and back faces clockwise, as
-    /// per convention.
+    /// per convention. For a [`Handedness::Left`] face, this is counterclockwise
+    /// in a left-handed coordinate system, which is the reverse of the
+    /// right-handed order.
+    ///
+    /// By default the quad is split along the diagonal from corner 1 to corner 2
+    /// (see [`OrientedBlockFace::quad_corners`]). Set `flip_diagonal` to split
+    /// along the diagonal from corner 0 to corner 3 instead. This matters when
+    /// vertex attributes like ambient occlusion are interpolated across the
+    /// quad; see [`ao_prefers_flip`].
     #[inline]
-    pub fn quad_mesh_indices(&self, start: u32) -> [u32; 6] {
-        quad_indices(start, self.n_sign * self.permutation.sign() > 0)
+    pub fn quad_mesh_indices(&self, start: u32, flip_diagonal: bool) -> [u32; 6] {
+        quad_indices(start, self.corners_are_counter_clockwise(), flip_diagonal)
     }
 
     /// Returns the UV coordinates of the 4 corners of the quad. Returns
@@ -181,13 +337,249 @@ impl OrientedBlockFace {
     }
 }
 
+/// Rotates the texture of a quad by `quarter_turns` quarter turns, given the UVs of its corners from
+/// [`OrientedBlockFace::tex_coords`]. Only the value modulo 4 is used; see [`VoxelContext::texture_rotation`].
+///
+/// Each turn maps `(u, v)` to `(v_max - v, u)`, where `v_max` is the largest V coordinate, so the UVs of a `width` x
+/// `height` quad still start at 0 and every voxel of the quad still shows a whole tile.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// let face = OrientedBlockFace::canonical(SignedAxis::PosY);
+/// let quad = UnorientedQuad { minimum: [0; 3], width: 2, height: 1 };
+/// let tex_coords = face.tex_coords(Axis::X, false, &quad);
+///
+/// let rotated = rotate_tex_coords(tex_coords, 1);
+/// assert_eq!(rotated, [[1.0, 0.0], [1.0, 2.0], [0.0, 0.0], [0.0, 2.0]]);
+/// assert_eq!(rotate_tex_coords(tex_coords, 4), tex_coords);
+/// ```
+///
+/// [`VoxelContext::texture_rotation`]: crate::VoxelContext::texture_rotation
+#[inline]
+pub fn rotate_tex_coords(mut tex_coords: [[f32; 2]; 4], quarter_turns: u8) -> [[f32; 2]; 4] {
+    for _ in 0..quarter_turns % 4 {
+        let v_max = tex_coords.iter().fold(0.0f32, |v_max, uv| v_max.max(uv[1]));
+        tex_coords = tex_coords.map(|[u, v]| [v_max - v, u]);
+    }
+    tex_coords
+}
+
+/// Packs a unit vector into 16 bits with the octahedral encoding: the vector is projected onto an octahedron, which is
+/// unfolded onto a square, and the two coordinates on the square are stored as 8-bit signed normalized integers, X in
+/// the low byte and Y in the high byte. This is the usual layout of an `snorm8x2` vertex attribute.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// for face in RIGHT_HANDED_Y_UP_CONFIG.faces.iter() {
+///     let normal = face.signed_normal().as_vec3().to_array();
+///     assert_eq!(oct_decode_normal(face.packed_normal()), normal);
+/// }
+/// assert_eq!(oct_encode_normal([0.0, 0.0, 1.0]), 0);
+/// ```
+#[inline]
+pub fn oct_encode_normal(normal: [f32; 3]) -> u16 {
+    let [x, y, z] = normal;
+    let l1_norm = x.abs() + y.abs() + z.abs();
+    let (x, y) = (x / l1_norm, y / l1_norm);
+    // Fold the lower hemisphere over the diagonals of the square.
+    let (x, y) = if z < 0.0 {
+        (
+            (1.0 - y.abs()) * sign_not_zero(x),
+            (1.0 - x.abs()) * sign_not_zero(y),
+        )
+    } else {
+        (x, y)
+    };
+    let quantize = |c: f32| (c.clamp(-1.0, 1.0) * 127.0).round() as i8 as u8 as u16;
+    quantize(x) | quantize(y) << 8
+}
+
+/// Unpacks a unit vector packed by [`oct_encode_normal`].
+#[inline]
+pub fn oct_decode_normal(packed: u16) -> [f32; 3] {
+    let dequantize = |byte: u16| (byte as u8 as i8 as f32 / 127.0).max(-1.0);
+    let (x, y) = (dequantize(packed & 0xff), dequantize(packed >> 8));
+    let z = 1.0 - x.abs() - y.abs();
+    let (x, y) = if z < 0.0 {
+        (
+            (1.0 - y.abs()) * sign_not_zero(x),
+            (1.0 - x.abs()) * sign_not_zero(y),
+        )
+    } else {
+        (x, y)
+    };
+    Vec3::new(x, y, z).normalize().to_array()
+}
+
+#[inline]
+fn sign_not_zero(x: f32) -> f32 {
+    if x < 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// For each face in `faces`, the index of the face with the opposite normal. If there is no such face, the index of the
+/// face itself is used.
+pub(crate) fn opposite_face_indices(faces: &[OrientedBlockFace; 6]) -> [usize; 6] {
+    let directions = faces.map(|face| face.signed_axis());
+    let mut opposites = [0; 6];
+    for (i, direction) in directions.iter().enumerate() {
+        let opposite = SignedAxis::new(-direction.signum(), direction.unsigned_axis());
+        opposites[i] = directions.iter().position(|&d| d == opposite).unwrap_or(i);
+    }
+    opposites
+}
+
+/// Returns `true` if a quad with the given per-corner ambient occlusion values
+/// should be split along the flipped diagonal to avoid interpolation artifacts.
+///
+/// `ao` must be in the same order as [`OrientedBlockFace::quad_corners`], where
+/// larger values mean less occlusion (e.g. `0..=3` with `3` being fully lit).
+/// The quad is flipped when the corners on the default diagonal are brighter
+/// than the corners on the other diagonal, so the dark corners are the ones
+/// connected by the shared edge.
+///
+/// See the [0fps article](https://0fps.net/2013/07/03/ambient-occlusion-for-minecraft-like-worlds/)
+/// for an explanation.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// let face = RIGHT_HANDED_Y_UP_CONFIG.faces[4];
+/// let ao = [3, 0, 3, 3];
+/// let indices = face.quad_mesh_indices(0, ao_prefers_flip(ao));
+/// ```
+#[inline]
+pub fn ao_prefers_flip(ao: [u8; 4]) -> bool {
+    ao[1] as u32 + ao[2] as u32 > ao[0] as u32 + ao[3] as u32
+}
+
+/// Returns barycentric coordinates for the 4 corners of a quad, in the same
+/// order as [`OrientedBlockFace::quad_corners`], e.g. for drawing wireframes
+/// in a fragment shader.
+///
+/// Since the corners are shared by both triangles, `flip_diagonal` must match
+/// the value passed to [`OrientedBlockFace::quad_mesh_indices`]. The two
+/// corners on the shared diagonal get `[0, 1, 0]` and `[0, 0, 1]`, and the two
+/// other corners both get `[1, 0, 0]`, so every triangle has one corner of
+/// each. The first coordinate is zero exactly along the diagonal, so a shader
+/// that only considers the second and third coordinates draws the outline of
+/// the quad without the diagonal.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// let face = RIGHT_HANDED_Y_UP_CONFIG.faces[4];
+/// for flip_diagonal in [false, true] {
+///     let barycentric = quad_barycentric_coords(flip_diagonal);
+///     let indices = face.quad_mesh_indices(0, flip_diagonal);
+///     for triangle in indices.chunks(3) {
+///         let sum = triangle
+///             .iter()
+///             .fold([0.0; 3], |s, &i| {
+///                 let b = barycentric[i as usize];
+///                 [s[0] + b[0], s[1] + b[1], s[2] + b[2]]
+///             });
+///         assert_eq!(sum, [1.0; 3]);
+///     }
+/// }
+/// ```
+#[inline]
+pub fn quad_barycentric_coords(flip_diagonal: bool) -> [[f32; 3]; 4] {
+    const X: [f32; 3] = [1.0, 0.0, 0.0];
+    const Y: [f32; 3] = [0.0, 1.0, 0.0];
+    const Z: [f32; 3] = [0.0, 0.0, 1.0];
+    if flip_diagonal {
+        [Y, X, X, Z]
+    } else {
+        [X, Y, Z, X]
+    }
+}
+
 /// Returns the vertex indices for a single quad (two triangles). The triangles
 /// may have either clockwise or counter-clockwise winding. `start` is the first
-/// index.
-fn quad_indices(start: u32, counter_clockwise: bool) -> [u32; 6] {
-    if counter_clockwise {
-        [start, start + 1, start + 2, start + 1, start + 3, start + 2]
-    } else {
-        [start, start + 2, start + 1, start + 1, start + 2, start + 3]
+/// index. When `flip_diagonal` is set, the triangles share the edge between
+/// corners 0 and 3 instead of corners 1 and 2.
+fn quad_indices(start: u32, counter_clockwise: bool, flip_diagonal: bool) -> [u32; 6] {
+    match (counter_clockwise, flip_diagonal) {
+        (true, false) => [start, start + 1, start + 2, start + 1, start + 3, start + 2],
+        (false, false) => [start, start + 2, start + 1, start + 1, start + 2, start + 3],
+        (true, true) => [start, start + 1, start + 3, start, start + 3, start + 2],
+        (false, true) => [start, start + 3, start + 1, start, start + 2, start + 3],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RIGHT_HANDED_Y_UP_CONFIG;
+
+    #[test]
+    fn per_vertex_data_follows_corner_order() {
+        let quad = UnorientedQuad {
+            minimum: [1, 2, 3],
+            width: 4,
+            height: 5,
+        };
+        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces.into_iter().chain(
+            [-1, 1]
+                .into_iter()
+                .flat_map(|n_sign| [Axis::X, Axis::Y, Axis::Z].map(|axis| (n_sign, axis)))
+                .map(|(n_sign, axis)| {
+                    OrientedBlockFace::new(n_sign, AxisPermutation::odd_with_normal_axis(axis))
+                }),
+        );
+        for face in faces {
+            let [c0, c1, c2, c3] = face.quad_corners(&quad).map(|c| c.as_ivec3());
+            let u = face.u.as_ivec3();
+            let v = face.v.as_ivec3();
+            // (min U, min V), (max U, min V), (min U, max V), (max U, max V)
+            assert_eq!(c1 - c0, 4 * u);
+            assert_eq!(c2 - c0, 5 * v);
+            assert_eq!(c3 - c0, 4 * u + 5 * v);
+
+            let corners = face.quad_corners(&quad).map(|c| c.as_vec3().to_array());
+            assert_eq!(face.quad_mesh_positions(&quad, 1.0), corners);
+            assert_eq!(
+                face.quad_mesh_positions_inset(&quad, 1.0, Vec3::ZERO, Vec3::ZERO),
+                corners
+            );
+            assert_eq!(
+                face.quad_mesh_normals(),
+                [face.signed_normal().as_vec3().to_array(); 4]
+            );
+
+            // Without flipping, UVs grow along U and V like the corners do.
+            let [n_axis, u_axis, _] = face.permutation.axes();
+            let unflipped_axis = if face.n_sign < 0 { n_axis } else { u_axis };
+            let uvs = face.tex_coords(unflipped_axis, false, &quad);
+            assert_eq!(uvs, [[0.0, 0.0], [4.0, 0.0], [0.0, 5.0], [4.0, 5.0]]);
+        }
+    }
+
+    #[test]
+    fn oct_encoding_round_trips_within_quantization_error() {
+        for i in 0..1000 {
+            // Points spread over the sphere along a spiral.
+            let z = 1.0 - 2.0 * (i as f32 + 0.5) / 1000.0;
+            let angle = i as f32 * 2.399_963;
+            let r = (1.0 - z * z).sqrt();
+            let normal = Vec3::new(r * angle.cos(), r * angle.sin(), z);
+
+            let decoded = Vec3::from(oct_decode_normal(oct_encode_normal(normal.to_array())));
+            assert!((decoded.length() - 1.0).abs() < 1e-5);
+            assert!(
+                decoded.angle_between(normal) < 0.02,
+                "{normal} decoded as {decoded}"
+            );
+        }
     }
 }
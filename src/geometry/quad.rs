@@ -4,7 +4,11 @@
 /// When using these values for materials and lighting, you can access them
 /// using either the quad's minimum voxel coordinates or the vertex coordinates
 /// given by `OrientedBlockFace::quad_corners`.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+///
+/// The layout is `#[repr(C)]`: 5 consecutive `u32`s (`minimum.x`, `minimum.y`, `minimum.z`, `width`, `height`) with no
+/// padding, so a group of quads can be copied directly into a GPU buffer. See [`PackedQuad`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(C)]
 pub struct UnorientedQuad {
     /// The minimum voxel in the quad.
     pub minimum: [u32; 3],
@@ -14,6 +18,93 @@ pub struct UnorientedQuad {
     pub height: u32,
 }
 
+/// Per-instance data for rendering one quad with GPU instancing, where the vertex shader expands each instance into 4
+/// vertices.
+///
+/// This is the same type as [`UnorientedQuad`], with the same 20-byte `#[repr(C)]` layout:
+///
+/// | Offset | Type       | Field     |
+/// |--------|------------|-----------|
+/// | 0      | `[u32; 3]` | `minimum` |
+/// | 12     | `u32`      | `width`   |
+/// | 16     | `u32`      | `height`  |
+///
+/// The normal is not stored, since every quad in a group has the normal of the group's [`OrientedBlockFace`]; pass it
+/// to the shader per draw call or per group. Get the quads with [`GreedyQuadsBuffer::as_packed_instance_data`].
+///
+/// [`GreedyQuadsBuffer::as_packed_instance_data`]: crate::GreedyQuadsBuffer::as_packed_instance_data
+pub type PackedQuad = UnorientedQuad;
+
+const _: () = assert!(std::mem::size_of::<PackedQuad>() == 20);
+
+impl UnorientedQuad {
+    /// Returns an identifier for this quad in the group `face_index`, derived from only the face index and
+    /// [`UnorientedQuad::minimum`], e.g. to correlate quads across remeshes of an edited chunk.
+    ///
+    /// Quads that start at the same voxel on the same face get the same ID, even if their size changed. The ID is a
+    /// 64-bit FNV-1a hash, so it is the same on every platform and in every version of this crate. Different quads are
+    /// very unlikely to collide, but it's not impossible.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let quad = UnorientedQuad { minimum: [1, 2, 3], width: 1, height: 1 };
+    /// let grown = UnorientedQuad { width: 4, ..quad };
+    /// assert_eq!(quad.stable_id(0), grown.stable_id(0));
+    /// assert_ne!(quad.stable_id(0), quad.stable_id(1));
+    /// ```
+    pub fn stable_id(&self, face_index: usize) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+
+        let mut hash = FNV_OFFSET_BASIS;
+        let bytes = std::iter::once(face_index as u8)
+            .chain(self.minimum.iter().flat_map(|c| c.to_le_bytes()));
+        for byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+        hash
+    }
+
+    /// Returns the lengths of the 4 edges of the quad, scaled by `voxel_size`, going around the corners of
+    /// [`OrientedBlockFace::quad_corners`] in the order 0 -> 1 -> 3 -> 2 -> 0.
+    ///
+    /// U and V are unit axes for every [`OrientedBlockFace`], so the lengths don't depend on the face: the edges along U
+    /// are [`UnorientedQuad::width`] voxels long and the edges along V are [`UnorientedQuad::height`] voxels long.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// # use block_mesh::ilattice::glam::Vec3;
+    /// let quad = UnorientedQuad { minimum: [0; 3], width: 3, height: 2 };
+    /// assert_eq!(quad.edge_lengths(0.5), [1.5, 1.0, 1.5, 1.0]);
+    /// assert_eq!(quad.perimeter(0.5), 5.0);
+    ///
+    /// let face = OrientedBlockFace::canonical(SignedAxis::NegZ);
+    /// let [p0, p1, p2, p3] = face.quad_mesh_positions(&quad, 0.5).map(Vec3::from);
+    /// assert_eq!(p0.distance(p1), 1.5);
+    /// assert_eq!(p1.distance(p3), 1.0);
+    /// assert_eq!(p3.distance(p2), 1.5);
+    /// assert_eq!(p2.distance(p0), 1.0);
+    /// ```
+    #[inline]
+    pub fn edge_lengths(&self, voxel_size: f32) -> [f32; 4] {
+        let width = self.width as f32 * voxel_size;
+        let height = self.height as f32 * voxel_size;
+        [width, height, width, height]
+    }
+
+    /// Returns the total length of the edges of the quad, scaled by `voxel_size`. See
+    /// [`UnorientedQuad::edge_lengths`].
+    #[inline]
+    pub fn perimeter(&self, voxel_size: f32) -> f32 {
+        2.0 * (self.width + self.height) as f32 * voxel_size
+    }
+}
+
 impl From<UnorientedUnitQuad> for UnorientedQuad {
     #[inline]
     fn from(unit: UnorientedUnitQuad) -> Self {
@@ -0,0 +1,348 @@
+use crate::{GreedyQuadsBuffer, MeshData, QuadCoordinateConfig};
+
+/// The glTF `componentType` of 8-bit unsigned integers.
+pub const GLTF_UNSIGNED_BYTE: u32 = 5121;
+/// The glTF `componentType` of 32-bit unsigned integers.
+pub const GLTF_UNSIGNED_INT: u32 = 5125;
+/// The glTF `componentType` of 32-bit floats.
+pub const GLTF_FLOAT: u32 = 5126;
+/// The glTF buffer view `target` for vertex attributes.
+pub const GLTF_ARRAY_BUFFER: u32 = 34962;
+/// The glTF buffer view `target` for indices.
+pub const GLTF_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// How the vertex attributes of a [`GltfPrimitive`] are laid out in its buffer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GltfVertexLayout {
+    /// Each attribute is tightly packed in its own buffer view.
+    Separate,
+    /// All attributes of a vertex are adjacent, in a single buffer view with a `byteStride`.
+    Interleaved,
+}
+
+/// A glTF `bufferView` of [`GltfPrimitive::buffer`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GltfBufferView {
+    pub byte_offset: usize,
+    pub byte_length: usize,
+    /// Only set for interleaved vertex attributes.
+    pub byte_stride: Option<usize>,
+    /// Either [`GLTF_ARRAY_BUFFER`] or [`GLTF_ELEMENT_ARRAY_BUFFER`].
+    pub target: u32,
+}
+
+/// A glTF `accessor` into one of the [`GltfPrimitive::buffer_views`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfAccessor {
+    /// The index of the buffer view in [`GltfPrimitive::buffer_views`].
+    pub buffer_view: usize,
+    pub byte_offset: usize,
+    /// One of [`GLTF_UNSIGNED_BYTE`], [`GLTF_UNSIGNED_INT`], or [`GLTF_FLOAT`].
+    pub component_type: u32,
+    pub normalized: bool,
+    pub count: usize,
+    /// The glTF accessor `type`, e.g. `"VEC3"`.
+    pub accessor_type: &'static str,
+    /// Per-component bounds, which glTF requires for `POSITION`.
+    pub min: Option<Vec<f32>>,
+    pub max: Option<Vec<f32>>,
+}
+
+/// The raw data and metadata of a glTF mesh primitive with `mode` 4 (triangles), to be written with any glTF crate.
+///
+/// All of the data is in [`GltfPrimitive::buffer`] in little-endian byte order, and every offset and stride is a
+/// multiple of 4, as glTF requires. The caller still has to create the `buffer` itself, e.g. as a GLB binary chunk, and
+/// offset the indices of the buffer views and accessors by the number of views and accessors already in the document.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// let mut quads = QuadBuffer::new();
+/// quads.groups[0].push(UnorientedQuad { minimum: [1; 3], width: 2, height: 3 });
+///
+/// let primitive = quads
+///     .to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0)
+///     .to_gltf_primitive(GltfVertexLayout::Interleaved)
+///     .unwrap();
+/// let (semantic, position) = &primitive.attributes[0];
+/// assert_eq!(*semantic, "POSITION");
+/// assert_eq!(position.count, 4);
+/// assert_eq!(primitive.indices.count, 6);
+/// assert_eq!(primitive.buffer_views[0].byte_stride, Some(36));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfPrimitive {
+    pub buffer: Vec<u8>,
+    pub buffer_views: Vec<GltfBufferView>,
+    /// Accessors for `POSITION`, `NORMAL`, `TEXCOORD_0`, and `COLOR_0`, keyed by their glTF attribute semantic.
+    pub attributes: Vec<(&'static str, GltfAccessor)>,
+    /// An accessor for the `u32` triangle indices.
+    pub indices: GltfAccessor,
+}
+
+impl MeshData {
+    /// Converts this mesh into the buffer, buffer views, and accessors of a glTF mesh primitive.
+    ///
+    /// Returns `None` if the mesh has no vertices, since glTF requires accessors to have at least one element and the
+    /// `POSITION` bounds would be undefined.
+    pub fn to_gltf_primitive(&self, layout: GltfVertexLayout) -> Option<GltfPrimitive> {
+        let num_vertices = self.positions.len();
+        if num_vertices == 0 {
+            return None;
+        }
+        let (min, max) = self.positions.iter().fold(
+            ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]),
+            |(min, max), p| {
+                (
+                    [0, 1, 2].map(|i| min[i].min(p[i])),
+                    [0, 1, 2].map(|i| max[i].max(p[i])),
+                )
+            },
+        );
+
+        let attributes = [
+            VertexAttribute {
+                semantic: "POSITION",
+                component_type: GLTF_FLOAT,
+                normalized: false,
+                accessor_type: "VEC3",
+                size: 12,
+                bytes: f32_bytes(self.positions.iter().flatten()),
+            },
+            VertexAttribute {
+                semantic: "NORMAL",
+                component_type: GLTF_FLOAT,
+                normalized: false,
+                accessor_type: "VEC3",
+                size: 12,
+                bytes: f32_bytes(self.normals.iter().flatten()),
+            },
+            VertexAttribute {
+                semantic: "TEXCOORD_0",
+                component_type: GLTF_FLOAT,
+                normalized: false,
+                accessor_type: "VEC2",
+                size: 8,
+                bytes: f32_bytes(self.tex_coords.iter().flatten()),
+            },
+            VertexAttribute {
+                semantic: "COLOR_0",
+                component_type: GLTF_UNSIGNED_BYTE,
+                normalized: true,
+                accessor_type: "VEC4",
+                size: 4,
+                bytes: self.colors.iter().flatten().copied().collect(),
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        let mut buffer_views = Vec::new();
+        let mut accessors = Vec::new();
+        match layout {
+            GltfVertexLayout::Separate => {
+                for attribute in attributes.iter() {
+                    accessors.push((
+                        attribute.semantic,
+                        attribute.accessor(buffer_views.len(), 0, num_vertices),
+                    ));
+                    buffer_views.push(GltfBufferView {
+                        byte_offset: buffer.len(),
+                        byte_length: attribute.bytes.len(),
+                        byte_stride: None,
+                        target: GLTF_ARRAY_BUFFER,
+                    });
+                    buffer.extend_from_slice(&attribute.bytes);
+                }
+            }
+            GltfVertexLayout::Interleaved => {
+                let mut byte_offset = 0;
+                for attribute in attributes.iter() {
+                    accessors.push((
+                        attribute.semantic,
+                        attribute.accessor(0, byte_offset, num_vertices),
+                    ));
+                    byte_offset += attribute.size;
+                }
+                for v in 0..num_vertices {
+                    for attribute in attributes.iter() {
+                        let size = attribute.size;
+                        buffer.extend_from_slice(&attribute.bytes[v * size..(v + 1) * size]);
+                    }
+                }
+                buffer_views.push(GltfBufferView {
+                    byte_offset: 0,
+                    byte_length: buffer.len(),
+                    // The accessor offsets end at the size of a whole vertex.
+                    byte_stride: Some(byte_offset),
+                    target: GLTF_ARRAY_BUFFER,
+                });
+            }
+        }
+        let position = &mut accessors[0].1;
+        position.min = Some(min.to_vec());
+        position.max = Some(max.to_vec());
+
+        let indices = GltfAccessor {
+            buffer_view: buffer_views.len(),
+            byte_offset: 0,
+            component_type: GLTF_UNSIGNED_INT,
+            normalized: false,
+            count: self.indices.len(),
+            accessor_type: "SCALAR",
+            min: None,
+            max: None,
+        };
+        buffer_views.push(GltfBufferView {
+            byte_offset: buffer.len(),
+            byte_length: 4 * self.indices.len(),
+            byte_stride: None,
+            target: GLTF_ELEMENT_ARRAY_BUFFER,
+        });
+        buffer.extend(self.indices.iter().flat_map(|i| i.to_le_bytes()));
+
+        Some(GltfPrimitive {
+            buffer,
+            buffer_views,
+            attributes: accessors,
+            indices,
+        })
+    }
+}
+
+impl GreedyQuadsBuffer {
+    /// Converts the quads into a glTF mesh primitive. This is equivalent to
+    /// `self.quads.to_mesh_data(config, voxel_size).to_gltf_primitive(layout)`, so it returns `None` if there are no
+    /// quads.
+    pub fn to_gltf_primitive(
+        &self,
+        config: &QuadCoordinateConfig,
+        voxel_size: f32,
+        layout: GltfVertexLayout,
+    ) -> Option<GltfPrimitive> {
+        self.quads
+            .to_mesh_data(config, voxel_size)
+            .to_gltf_primitive(layout)
+    }
+}
+
+/// The data of one vertex attribute, before it is laid out in the buffer.
+struct VertexAttribute {
+    semantic: &'static str,
+    component_type: u32,
+    normalized: bool,
+    accessor_type: &'static str,
+    /// The size of one element in bytes.
+    size: usize,
+    bytes: Vec<u8>,
+}
+
+impl VertexAttribute {
+    fn accessor(&self, buffer_view: usize, byte_offset: usize, count: usize) -> GltfAccessor {
+        GltfAccessor {
+            buffer_view,
+            byte_offset,
+            component_type: self.component_type,
+            normalized: self.normalized,
+            count,
+            accessor_type: self.accessor_type,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+fn f32_bytes<'a>(values: impl Iterator<Item = &'a f32>) -> Vec<u8> {
+    values.flat_map(|x| x.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{QuadBuffer, UnorientedQuad, RIGHT_HANDED_Y_UP_CONFIG};
+
+    #[test]
+    fn interleaved_and_separate_layouts_hold_the_same_data() {
+        let mut quads = QuadBuffer::new();
+        quads.groups[1].push(UnorientedQuad {
+            minimum: [1, 2, 3],
+            width: 2,
+            height: 3,
+        });
+        quads.groups[4].push(UnorientedQuad {
+            minimum: [4, 5, 6],
+            width: 1,
+            height: 1,
+        });
+        let mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 0.5);
+
+        let separate = mesh.to_gltf_primitive(GltfVertexLayout::Separate).unwrap();
+        let interleaved = mesh
+            .to_gltf_primitive(GltfVertexLayout::Interleaved)
+            .unwrap();
+        assert_eq!(separate.buffer.len(), interleaved.buffer.len());
+
+        for ((_, a), (_, b)) in separate
+            .attributes
+            .iter()
+            .zip(interleaved.attributes.iter())
+        {
+            assert_eq!(a.min, b.min);
+            assert_eq!(a.max, b.max);
+            for v in 0..mesh.positions.len() {
+                assert_eq!(element(&separate, a, v), element(&interleaved, b, v));
+            }
+        }
+        assert_eq!(
+            element(&separate, &separate.attributes[0].1, 5),
+            f32_bytes(mesh.positions[5].iter())
+        );
+
+        let (_, position) = &separate.attributes[0];
+        for i in 0..3 {
+            let values = mesh.positions.iter().map(|p| p[i]);
+            assert_eq!(
+                position.min.as_ref().unwrap()[i],
+                values.clone().fold(f32::INFINITY, f32::min)
+            );
+            assert_eq!(
+                position.max.as_ref().unwrap()[i],
+                values.fold(f32::NEG_INFINITY, f32::max)
+            );
+        }
+
+        let view = &interleaved.buffer_views[interleaved.indices.buffer_view];
+        let indices: Vec<u32> = interleaved.buffer[view.byte_offset..][..view.byte_length]
+            .chunks(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(indices, mesh.indices);
+    }
+
+    #[test]
+    fn empty_mesh_has_no_primitive() {
+        let quads = QuadBuffer::new();
+        let mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+        for layout in [GltfVertexLayout::Separate, GltfVertexLayout::Interleaved] {
+            assert_eq!(mesh.to_gltf_primitive(layout), None);
+        }
+        let buffer = GreedyQuadsBuffer::new(0);
+        assert_eq!(
+            buffer.to_gltf_primitive(&RIGHT_HANDED_Y_UP_CONFIG, 1.0, GltfVertexLayout::Separate),
+            None
+        );
+    }
+
+    /// The bytes of element `v` of `accessor`.
+    fn element<'a>(primitive: &'a GltfPrimitive, accessor: &GltfAccessor, v: usize) -> &'a [u8] {
+        let view = &primitive.buffer_views[accessor.buffer_view];
+        let size = match accessor.accessor_type {
+            "VEC2" => 8,
+            "VEC3" => 12,
+            _ => 4,
+        };
+        let stride = view.byte_stride.unwrap_or(size);
+        let start = view.byte_offset + accessor.byte_offset + v * stride;
+        &primitive.buffer[start..start + size]
+    }
+}
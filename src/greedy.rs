@@ -1,13 +1,34 @@
+mod atlas;
+mod cache;
+mod chunked;
+mod coplanar;
+mod delta;
+mod material;
+mod merge_2d;
 mod merge_strategy;
+mod plan;
+mod symmetric;
+mod total_f32;
 
+pub use atlas::*;
+pub use cache::*;
+pub use chunked::*;
+pub use coplanar::*;
+pub use delta::*;
+pub use material::*;
+pub use merge_2d::*;
 pub use merge_strategy::*;
+pub use plan::*;
+pub use symmetric::*;
+pub use total_f32::*;
 
 use crate::{
-    bounds::assert_in_bounds, OrientedBlockFace, QuadBuffer, UnorientedQuad, Voxel, VoxelVisibility,
+    simple::face_is_visible_for_face, OrientedBlockFace, PackedQuad, QuadBuffer, QuadDiff,
+    RepresentativeVoxelBuffer, UnorientedQuad, Voxel, VoxelVisibility,
 };
-use crate::{MergeVoxelContext, VoxelContext};
+use crate::{MergeVoxelContext, VoxelContext, VoxelSource};
 
-use ilattice::glam::UVec3;
+use ilattice::glam::{UVec2, UVec3, Vec3};
 use ilattice::prelude::Extent;
 use ndcopy::fill3;
 use ndshape::Shape;
@@ -18,6 +39,62 @@ pub trait MergeVoxel: Voxel {
 
     /// The value used to determine if this voxel can join a given quad in the mesh. This value will be constant for all voxels
     /// in the same quad. Often this is some material identifier so that the same texture can be used for a full quad.
+    ///
+    /// Merge values are compared with `==`, so they must be [`Eq`]. Floats are only [`PartialEq`] because NaN is not equal
+    /// to itself; wrap them in [`TotalF32`] to merge on continuous values.
+    ///
+    /// The merge value doesn't have to be visual. Visibility only decides which faces are meshed, and any face that is
+    /// meshed is compared by merge value alone, so adding a group id keeps voxels of separate logical objects in separate
+    /// quads, e.g. to highlight one entity even where it shares a texture with its neighbor.
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// # use ndshape::{ConstShape, ConstShape3u32};
+    /// #[derive(Clone, Copy, Default)]
+    /// struct EntityVoxel {
+    ///     texture: u16,
+    ///     entity: u32,
+    /// }
+    ///
+    /// impl Voxel for EntityVoxel {
+    ///     fn get_visibility(&self) -> VoxelVisibility {
+    ///         if self.texture == 0 {
+    ///             VoxelVisibility::Empty
+    ///         } else {
+    ///             VoxelVisibility::Opaque
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// impl MergeVoxel for EntityVoxel {
+    ///     // The entity joins the texture so different entities never share a quad.
+    ///     type MergeValue = (u16, u32);
+    ///     type MergeValueFacingNeighbour = bool;
+    ///
+    ///     fn merge_value(&self) -> Self::MergeValue {
+    ///         (self.texture, self.entity)
+    ///     }
+    ///
+    ///     fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+    ///         true
+    ///     }
+    /// }
+    ///
+    /// type Shape = ConstShape3u32<4, 3, 3>;
+    ///
+    /// // Two voxels with the same texture that belong to different entities.
+    /// let mut voxels = [EntityVoxel::default(); Shape::SIZE as usize];
+    /// voxels[Shape::linearize([1, 1, 1]) as usize] = EntityVoxel { texture: 1, entity: 7 };
+    /// voxels[Shape::linearize([2, 1, 1]) as usize] = EntityVoxel { texture: 1, entity: 8 };
+    ///
+    /// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+    /// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+    /// greedy_quads(&voxels, &Shape {}, [0; 3], [3, 2, 2], faces, &mut buffer, &DefaultVoxelContext);
+    ///
+    /// // Each entity gets its own top quad.
+    /// let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+    /// assert_eq!(buffer.quads.groups[pos_y].len(), 2);
+    /// ```
     fn merge_value(&self) -> Self::MergeValue;
 
     fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour;
@@ -30,28 +107,204 @@ pub trait MergeVoxel: Voxel {
 pub struct GreedyQuadsBuffer {
     pub quads: QuadBuffer,
 
+    /// Statistics from the most recent meshing call.
+    pub stats: MeshStats,
+
+    scratch: GreedyScratch,
+}
+
+impl GreedyQuadsBuffer {
+    /// Creates a buffer for voxel arrays with `size` elements.
+    ///
+    /// Each group of quads reserves enough space for every voxel on one side of a cube with `size` voxels, which avoids
+    /// reallocating for most chunks. Use [`GreedyQuadsBuffer::with_group_capacity`] if you know better.
+    pub fn new(size: usize) -> Self {
+        let side_area = (size as f64).cbrt().powi(2) as usize;
+        Self::with_group_capacity(size, [side_area; 6])
+    }
+
+    /// Creates a buffer for voxel arrays with `size` elements, where `quads.groups[i]` can hold `group_capacity[i]` quads
+    /// without reallocating.
+    pub fn with_group_capacity(size: usize, group_capacity: [usize; 6]) -> Self {
+        Self {
+            quads: QuadBuffer::with_group_capacity(group_capacity),
+            stats: MeshStats::default(),
+            scratch: GreedyScratch::new(size),
+        }
+    }
+
+    pub fn reset(&mut self, size: usize) {
+        self.quads.reset();
+        self.stats = MeshStats::default();
+        self.scratch.reset(size);
+    }
+
+    /// Appends every quad in `other` to the end of the corresponding group in `self`, e.g. to draw the output of several
+    /// meshing passes at once. See [`QuadBuffer::append`] for how this affects vertex ranges. [`GreedyQuadsBuffer::stats`]
+    /// is not changed.
+    pub fn append(&mut self, other: &GreedyQuadsBuffer) {
+        self.quads.append(&other.quads);
+    }
+
+    /// Returns the quads for `faces[face_index]` as a tightly packed array that can be copied directly into a GPU buffer
+    /// for instanced rendering. See [`PackedQuad`] for the layout.
+    pub fn as_packed_instance_data(&self, face_index: usize) -> &[PackedQuad] {
+        &self.quads.groups[face_index]
+    }
+
+    /// Sorts the quads in each group by their distance to `camera`. See [`QuadBuffer::sort_by_distance`].
+    pub fn sort_by_distance(
+        &mut self,
+        camera: Vec3,
+        faces: &[OrientedBlockFace; 6],
+        back_to_front: bool,
+    ) {
+        self.quads.sort_by_distance(camera, faces, back_to_front);
+    }
+
+    /// Returns the quads that were added and removed in `other` compared to `self`. See [`QuadBuffer::diff`].
+    pub fn diff(&self, other: &GreedyQuadsBuffer) -> QuadDiff {
+        self.quads.diff(&other.quads)
+    }
+
+    /// Returns a sphere that contains every quad. See [`QuadBuffer::bounding_sphere`].
+    pub fn bounding_sphere(
+        &self,
+        faces: &[OrientedBlockFace; 6],
+        voxel_size: f32,
+    ) -> Option<(Vec3, f32)> {
+        self.quads.bounding_sphere(faces, voxel_size)
+    }
+
+    /// Returns the number of vertices needed to mesh every quad. See [`QuadBuffer::vertex_count`].
+    pub fn vertex_count(&self) -> usize {
+        self.quads.vertex_count()
+    }
+
+    /// Returns the number of triangle indices needed to mesh every quad. See [`QuadBuffer::index_count`].
+    pub fn index_count(&self) -> usize {
+        self.quads.index_count()
+    }
+}
+
+/// Statistics collected while running the [`greedy_quads`] algorithm.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MeshStats {
+    /// The number of quads returned by [`MergeStrategy::find_quad`] that were discarded because they were empty or
+    /// exceeded the maximum size. This is always 0 for a correct [`MergeStrategy`]; in debug builds, such quads cause a
+    /// panic instead.
+    pub rejected_quads: usize,
+}
+
+/// Working memory for the [`greedy_quads`] algorithm, kept separate from the output quads.
+///
+/// [`GreedyQuadsBuffer`] already owns one of these. Use [`greedy_quads_reuse`] with a standalone scratch buffer when you
+/// want a single allocation per meshing worker while handing off the output [`QuadBuffer`] after each call. As long as
+/// every chunk has the same size, repeated calls will not allocate.
+pub struct GreedyScratch {
     // A single array is used for the visited mask because it allows us to index by the same strides as the voxels array. It
     // also only requires a single allocation.
     visited: Vec<bool>,
 }
 
-impl GreedyQuadsBuffer {
+impl GreedyScratch {
+    /// Creates a scratch buffer for voxel arrays with `size` elements.
     pub fn new(size: usize) -> Self {
         Self {
-            quads: QuadBuffer::new(),
             visited: vec![false; size],
         }
     }
 
-    pub fn reset(&mut self, size: usize) {
-        self.quads.reset();
+    /// Creates a scratch buffer for voxel arrays with the given shape.
+    pub fn for_shape<S>(voxels_shape: &S) -> Self
+    where
+        S: Shape<3, Coord = u32>,
+    {
+        Self::new(voxels_shape.usize())
+    }
 
+    /// Resizes the buffer for voxel arrays with `size` elements. This only allocates if the size changes.
+    pub fn reset(&mut self, size: usize) {
         if size != self.visited.len() {
             self.visited = vec![false; size];
         }
     }
 }
 
+/// Tuning parameters for [`greedy_quads_with_config`] and [`greedy_quads_with_merge_strategy`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GreedyQuadsConfig {
+    /// The maximum width (U) and height (V) of any generated quad, regardless of how many voxels could be merged.
+    ///
+    /// This is useful when large quads cause texture stretching or precision issues, or when quads need to fit in a
+    /// lightmap atlas tile. Components must be at least 1.
+    pub max_merge: UVec2,
+
+    /// Which chunk is responsible for the voxels on the boundary of the interior. See [`BoundaryOwnership`].
+    pub boundary_ownership: BoundaryOwnership,
+
+    /// If set, quads never cross a voxel coordinate that is a multiple of this value along U or V, so every quad lies in
+    /// one cell of a fixed grid.
+    ///
+    /// Merging differently in different meshes of the same region, like the levels of detail of a chunk, moves the quad
+    /// boundaries and can cause visible popping when switching between them. Aligning every mesh to the same grid keeps
+    /// many of the boundaries in place. Must be at least 1.
+    pub merge_grid_align: Option<u32>,
+
+    /// Whether faces that are already covered by a quad are left out of later quads, which is the usual greedy meshing.
+    ///
+    /// If `false`, the voxels covered by each quad are not marked as visited, so every visible face starts its own quad
+    /// and is merged as far as it can go, even over faces that other quads already cover. The quads overlap, which is
+    /// useful for layered effects like a glow pass over a base pass, but there are as many quads as visible faces. Voxels
+    /// that are skipped up front, e.g. by [`greedy_quads_with_occlusion_mask`](crate::greedy_quads_with_occlusion_mask),
+    /// are still left out.
+    pub respect_visited: bool,
+}
+
+impl Default for GreedyQuadsConfig {
+    fn default() -> Self {
+        Self {
+            max_merge: UVec2::splat(u32::MAX),
+            boundary_ownership: BoundaryOwnership::Both,
+            merge_grid_align: None,
+            respect_visited: true,
+        }
+    }
+}
+
+/// Controls whether voxels on the minimum and maximum slices of the interior of `[min, max]` are meshed.
+///
+/// With the usual layout, the interiors of neighboring chunks are disjoint and only their padding overlaps, so every
+/// voxel is meshed by exactly one chunk and [`BoundaryOwnership::Both`] is correct. Some worlds instead tile chunks with
+/// a 1-voxel overlap of their interiors, so the maximum interior slice of one chunk is the minimum interior slice of the
+/// next. Meshing that shared slice in both chunks emits every face in it twice, which shows up as z-fighting seams. Use
+/// [`BoundaryOwnership::Lower`] or [`BoundaryOwnership::Upper`] for every chunk in such a world so that exactly one of
+/// the two chunks owns the shared slice.
+///
+/// Visibility is still determined from the actual neighbors, so the faces of an owned boundary voxel are the same as
+/// they would be with [`BoundaryOwnership::Both`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BoundaryOwnership {
+    /// Mesh the minimum interior slice on each axis, but leave the maximum slice to the next chunk.
+    Lower,
+    /// Mesh the maximum interior slice on each axis, but leave the minimum slice to the previous chunk.
+    Upper,
+    /// Mesh the whole interior.
+    Both,
+}
+
+impl BoundaryOwnership {
+    /// The part of `interior` that is meshed with this ownership.
+    fn owned_interior(self, interior: Extent<UVec3>) -> Extent<UVec3> {
+        let shrunk_shape = interior.shape.max(UVec3::ONE) - UVec3::ONE;
+        match self {
+            Self::Lower => Extent::from_min_and_shape(interior.minimum, shrunk_shape),
+            Self::Upper => Extent::from_min_and_shape(interior.minimum + UVec3::ONE, shrunk_shape),
+            Self::Both => interior,
+        }
+    }
+}
+
 /// The "Greedy Meshing" algorithm described by Mikola Lysenko in the [0fps
 /// article](https://0fps.net/2012/06/30/meshing-in-a-minecraft-game/).
 ///
@@ -61,220 +314,595 @@ impl GreedyQuadsBuffer {
 ///
 /// All quads created will have the same "merge value" as defined by the [`MergeVoxel`] trait. The quads can be post-processed
 /// into meshes as the user sees fit.
-pub fn greedy_quads<T, S, C>(
-    voxels: &[T],
+///
+/// Chunks whose interior is entirely empty or entirely opaque are detected up front: an empty chunk returns no quads
+/// right away, and an opaque chunk only meshes the faces on the boundary of its interior.
+///
+/// Merging faces into non-rectangular polygons, like L shapes or staircases, is deliberately not supported, so quads
+/// are always rectangles. It would not reduce the triangle count: the only convex polygons made of whole voxel faces
+/// are rectangles, and triangulating a rectilinear polygon with `n` corners takes `n - 2` triangles, which is never
+/// fewer than 2 triangles for each rectangle in its best split into rectangles.
+pub fn greedy_quads<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    greedy_quads_with_config(
+        voxels,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        &GreedyQuadsConfig::default(),
+        output,
+        ctx,
+    )
+}
+
+/// Same as [`greedy_quads`], but quads of [`VoxelVisibility::Translucent`] voxels are written to the separate
+/// `translucent` buffer, so opaque and translucent geometry can be rendered in separate passes.
+///
+/// Each quad is classified by the voxel at its minimum as it's generated. If opaque and translucent voxels can have the
+/// same merge value, they may be merged into a single quad, so consider including the visibility in your merge value.
+/// The [`MeshStats`] of the whole call are stored in `opaque`.
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_by_visibility<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    opaque: &mut GreedyQuadsBuffer,
+    translucent: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    translucent.quads.reset();
+    translucent.stats = MeshStats::default();
+    let translucent_groups = &mut translucent.quads.groups;
+    let mut on_quad = |face_index: usize, quad: &UnorientedQuad, voxel: &V::Voxel| {
+        let is_translucent =
+            ctx.get_visibility_for_face(voxel, face_index) == VoxelVisibility::Translucent;
+        if is_translucent {
+            translucent_groups[face_index].push(*quad);
+        }
+        !is_translucent
+    };
+    GreedyMeshPlan::new(voxels_shape, faces, min, max)
+        .mesh_skipping::<_, VoxelMerger<V::Voxel>, _>(
+            voxels,
+            &GreedyQuadsConfig::default(),
+            [None; 6],
+            opaque,
+            Some(&mut on_quad),
+            ctx,
+        );
+}
+
+/// Same as [`greedy_quads`], but with custom [`GreedyQuadsConfig`] parameters.
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_config<V, S, C>(
+    voxels: &V,
     voxels_shape: &S,
     min: [u32; 3],
     max: [u32; 3],
     faces: &[OrientedBlockFace; 6],
+    config: &GreedyQuadsConfig,
     output: &mut GreedyQuadsBuffer,
     ctx: &C,
 ) where
+    V: VoxelSource + ?Sized,
     S: Shape<3, Coord = u32>,
-    C: MergeVoxelContext<T>,
+    C: MergeVoxelContext<V::Voxel>,
 {
-    greedy_quads_with_merge_strategy::<_, _, VoxelMerger<T>, _>(
+    greedy_quads_with_merge_strategy::<_, _, VoxelMerger<V::Voxel>, _>(
         voxels,
         voxels_shape,
         min,
         max,
         faces,
+        config,
         output,
         ctx,
     )
 }
 
 /// Run the greedy meshing algorithm with a custom quad merging strategy using the [`MergeStrategy`] trait.
-pub fn greedy_quads_with_merge_strategy<T, S, Merger, C>(
-    voxels: &[T],
+///
+/// [`greedy_quads`] is equivalent to calling this with [`VoxelMerger`] and the default [`GreedyQuadsConfig`]. Any
+/// strategy only needs to decide the size of each quad; this function takes care of scanning for visible faces and
+/// marking the voxels covered by each quad as visited.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// /// A strategy that never merges faces.
+/// struct NoMerge;
+///
+/// impl MergeStrategy for NoMerge {
+///     type Voxel = BoolVoxel;
+///
+///     unsafe fn find_quad<V, C>(
+///         _min_index: u32,
+///         _max_width: u32,
+///         _max_height: u32,
+///         _face_strides: &FaceStrides,
+///         _voxels: &V,
+///         _visited: &[bool],
+///         _ctx: &C,
+///     ) -> (u32, u32)
+///     where
+///         V: VoxelSource<Voxel = BoolVoxel> + ?Sized,
+///         C: MergeVoxelContext<BoolVoxel>,
+///     {
+///         (1, 1)
+///     }
+/// }
+///
+/// type ChunkShape = ConstShape3u32<4, 4, 4>;
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// voxels[ChunkShape::linearize([2, 1, 1]) as usize] = BoolVoxel(true);
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_with_merge_strategy::<_, _, NoMerge, _>(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [3; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &GreedyQuadsConfig::default(),
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(buffer.quads.num_quads(), 10);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_merge_strategy<V, S, Merger, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    config: &GreedyQuadsConfig,
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    Merger: MergeStrategy<Voxel = V::Voxel>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    GreedyMeshPlan::new(voxels_shape, faces, min, max)
+        .mesh_with_merge_strategy::<_, Merger, _>(voxels, config, output, ctx);
+}
+
+/// A quad generated by the greedy meshing algorithm, reported by [`greedy_quads_with_merge_callback`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MergeEvent {
+    /// The index of the group that the quad is in.
+    pub face_index: usize,
+    pub quad: UnorientedQuad,
+    /// The number of voxel faces that were merged into the quad, including the face at its minimum.
+    pub absorbed_voxels: u32,
+}
+
+/// Same as [`greedy_quads_with_config`], but `on_merge` is called for every generated quad, e.g. to measure how well
+/// faces merge in different regions.
+///
+/// Quads are reported as they are generated, so they are in the same order as in `output`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<6, 6, 6>;
+///
+/// // A 4x4x4 cube.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     voxels[i as usize] = BoolVoxel(ChunkShape::delinearize(i).iter().all(|c| (1..=4).contains(c)));
+/// }
+///
+/// let mut absorbed = [0; 6];
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_with_merge_callback(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [5; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &GreedyQuadsConfig::default(),
+///     &mut buffer,
+///     &DefaultVoxelContext,
+///     |event| absorbed[event.face_index] += event.absorbed_voxels,
+/// );
+/// assert_eq!(absorbed, [16; 6]);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_merge_callback<V, S, C, F>(
+    voxels: &V,
     voxels_shape: &S,
     min: [u32; 3],
     max: [u32; 3],
     faces: &[OrientedBlockFace; 6],
+    config: &GreedyQuadsConfig,
     output: &mut GreedyQuadsBuffer,
     ctx: &C,
+    mut on_merge: F,
 ) where
+    V: VoxelSource + ?Sized,
     S: Shape<3, Coord = u32>,
-    Merger: MergeStrategy<Voxel = T>,
-    C: MergeVoxelContext<T>,
+    C: MergeVoxelContext<V::Voxel>,
+    F: FnMut(MergeEvent),
 {
-    assert_in_bounds(voxels, voxels_shape, min, max);
+    let mut on_quad = |face_index, quad: &UnorientedQuad, _: &V::Voxel| {
+        on_merge(MergeEvent {
+            face_index,
+            quad: *quad,
+            absorbed_voxels: quad.width * quad.height,
+        });
+        true
+    };
+    GreedyMeshPlan::new(voxels_shape, faces, min, max)
+        .mesh_skipping::<_, VoxelMerger<V::Voxel>, _>(
+            voxels,
+            config,
+            [None; 6],
+            output,
+            Some(&mut on_quad),
+            ctx,
+        );
+}
 
-    let min = UVec3::from(min).as_ivec3();
-    let max = UVec3::from(max).as_ivec3();
-    let extent = Extent::from_min_and_max(min, max);
+/// Same as [`greedy_quads`], but the voxel at the minimum of every quad is also copied into `representatives` as the
+/// quad is generated, in parallel with the groups of `output.quads`. See [`RepresentativeVoxelBuffer`].
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// /// A block with a tint that is left out of its merge value.
+/// #[derive(Clone, Copy, Eq, PartialEq)]
+/// struct Block {
+///     solid: bool,
+///     tint: u8,
+/// }
+///
+/// impl Voxel for Block {
+///     fn get_visibility(&self) -> VoxelVisibility {
+///         if self.solid { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+///     }
+/// }
+///
+/// impl MergeVoxel for Block {
+///     type MergeValue = bool;
+///     type MergeValueFacingNeighbour = bool;
+///     fn merge_value(&self) -> bool { self.solid }
+///     fn merge_value_facing_neighbour(&self) -> bool { self.solid }
+/// }
+///
+/// type ChunkShape = ConstShape3u32<4, 3, 3>;
+/// let air = Block { solid: false, tint: 0 };
+/// let mut voxels = [air; ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = Block { solid: true, tint: 7 };
+/// voxels[ChunkShape::linearize([2, 1, 1]) as usize] = Block { solid: true, tint: 9 };
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// let mut representatives = RepresentativeVoxelBuffer::new();
+/// greedy_quads_with_representatives(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [3, 2, 2],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &mut representatives,
+///     &DefaultVoxelContext,
+/// );
+/// let top = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+/// assert_eq!(buffer.quads.groups[top].len(), 1);
+/// assert_eq!(representatives.groups[top][0].tint, 7);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_representatives<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    representatives: &mut RepresentativeVoxelBuffer<V::Voxel>,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    V::Voxel: Copy,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    representatives.reset();
+    let mut on_quad = |face_index: usize, _: &UnorientedQuad, voxel: &V::Voxel| {
+        representatives.groups[face_index].push(*voxel);
+        true
+    };
+    GreedyMeshPlan::new(voxels_shape, faces, min, max)
+        .mesh_skipping::<_, VoxelMerger<V::Voxel>, _>(
+            voxels,
+            &GreedyQuadsConfig::default(),
+            [None; 6],
+            output,
+            Some(&mut on_quad),
+            ctx,
+        );
+}
 
-    output.reset(voxels.len());
-    let GreedyQuadsBuffer {
-        visited,
-        quads: QuadBuffer { groups },
-    } = output;
+/// Same as [`greedy_quads`], but writes the quads into `output` and uses the separate `scratch` buffer for working
+/// memory. See [`GreedyScratch`].
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_reuse<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    scratch: &mut GreedyScratch,
+    output: &mut QuadBuffer,
+    ctx: &C,
+) -> MeshStats
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    GreedyMeshPlan::new(voxels_shape, faces, min, max).mesh_into::<_, VoxelMerger<V::Voxel>, _>(
+        voxels,
+        &GreedyQuadsConfig::default(),
+        [None; 6],
+        scratch,
+        output,
+        None,
+        ctx,
+    )
+}
 
-    let interior = extent.padded(-1); // Avoid accessing out of bounds with a 3x3x3 kernel.
-    let interior =
-        Extent::from_min_and_shape(interior.minimum.as_uvec3(), interior.shape.as_uvec3());
+#[allow(clippy::too_many_arguments)]
+fn greedy_quads_for_face<V, S, Merger, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    interior: Extent<UVec3>,
+    face_plan: &FacePlan,
+    config: &GreedyQuadsConfig,
+    skip: Option<&[bool]>,
+    visited: &mut [bool],
+    quads: &mut Vec<UnorientedQuad>,
+    stats: &mut MeshStats,
+    mut on_quad: Option<&mut OnQuad<V::Voxel>>,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    Merger: MergeStrategy<Voxel = V::Voxel>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    // Skipped voxels are treated as if they were already meshed.
+    match skip {
+        Some(skip) => visited.copy_from_slice(skip),
+        None => visited.fill(false),
+    }
 
-    for (group, face) in groups.iter_mut().zip(faces.iter()) {
-        greedy_quads_for_face::<_, _, Merger, _>(
+    for slice_index in 0..face_plan.num_slices(interior) {
+        greedy_quads_for_slice::<_, _, Merger, _>(
             voxels,
             voxels_shape,
-            interior,
-            face,
+            face_plan.slice_extent(interior, slice_index),
+            face_plan,
+            config,
             visited,
-            group,
+            quads,
+            stats,
+            on_quad.as_deref_mut(),
             ctx,
         );
     }
 }
 
-fn greedy_quads_for_face<T, S, Merger, C>(
-    voxels: &[T],
+/// A callback for every generated quad, along with the voxel at its minimum, which returns whether to add the quad to the
+/// output.
+type OnQuad<'a, T> = dyn FnMut(&UnorientedQuad, &T) -> bool + 'a;
+
+/// Meshes the faces of `face_plan` for the voxels in `slice_extent`, which is one layer of the interior along the
+/// face's normal. `visited` must only be reset before the first slice of each face.
+///
+/// If `on_quad` is given, it is called with every quad before it's added to `quads`, along with the voxel at its minimum.
+/// Quads for which it returns `false` are still marked as visited, but left out of `quads`.
+#[allow(clippy::too_many_arguments)]
+fn greedy_quads_for_slice<V, S, Merger, C>(
+    voxels: &V,
     voxels_shape: &S,
-    interior: Extent<UVec3>,
-    face: &OrientedBlockFace,
+    slice_extent: Extent<UVec3>,
+    face_plan: &FacePlan,
+    config: &GreedyQuadsConfig,
     visited: &mut [bool],
     quads: &mut Vec<UnorientedQuad>,
+    stats: &mut MeshStats,
+    mut on_quad: Option<&mut OnQuad<V::Voxel>>,
     ctx: &C,
 ) where
+    V: VoxelSource + ?Sized,
     S: Shape<3, Coord = u32>,
-    Merger: MergeStrategy<Voxel = T>,
-    C: MergeVoxelContext<T>,
+    Merger: MergeStrategy<Voxel = V::Voxel>,
+    C: MergeVoxelContext<V::Voxel>,
 {
-    visited.fill(false);
-
-    let OrientedBlockFace {
-        n_sign,
-        permutation,
-        n,
-        u,
-        v,
-        ..
-    } = face;
-
-    let [n_axis, u_axis, v_axis] = permutation.axes();
+    let FacePlan {
+        face,
+        strides: face_strides,
+    } = face_plan;
+
+    let [n_axis, u_axis, v_axis] = face.permutation.axes();
     let i_n = n_axis.index();
     let i_u = u_axis.index();
     let i_v = v_axis.index();
 
-    let interior_shape = interior.shape.to_array();
-    let num_slices = interior_shape[i_n];
-    let mut slice_shape = [0; 3];
-    slice_shape[i_n] = 1;
-    slice_shape[i_u] = interior_shape[i_u];
-    slice_shape[i_v] = interior_shape[i_v];
-    let mut slice_extent = Extent::from_min_and_shape(interior.minimum, UVec3::from(slice_shape));
-
-    let n_stride = voxels_shape.linearize(n.to_array());
-    let u_stride = voxels_shape.linearize(u.to_array());
-    let v_stride = voxels_shape.linearize(v.to_array());
-    let face_strides = FaceStrides {
-        n_stride,
-        u_stride,
-        v_stride,
-        // The offset to the voxel sharing this cube face.
-        visibility_offset: if *n_sign > 0 {
-            n_stride
-        } else {
-            0u32.wrapping_sub(n_stride)
-        },
-    };
+    let slice_ub = slice_extent.least_upper_bound().to_array();
+    let u_ub = slice_ub[i_u];
+    let v_ub = slice_ub[i_v];
 
-    for _ in 0..num_slices {
-        let slice_ub = slice_extent.least_upper_bound().to_array();
-        let u_ub = slice_ub[i_u];
-        let v_ub = slice_ub[i_v];
-
-        for quad_min in slice_extent.iter3() {
-            let quad_min_array = quad_min.to_array();
-            let quad_min_index = voxels_shape.linearize(quad_min_array);
-            let quad_min_voxel = unsafe { voxels.get_unchecked(quad_min_index as usize) };
-            if unsafe {
-                !face_needs_mesh(
-                    quad_min_voxel,
-                    quad_min_index,
-                    face_strides.visibility_offset,
-                    voxels,
-                    visited,
-                    ctx,
-                )
-            } {
-                continue;
-            }
-            // We have at least one face that needs a mesh. We'll try to expand that face into the biggest quad we can find.
-
-            // These are the boundaries on quad width and height so it is contained in the slice.
-            let max_width = u_ub - quad_min_array[i_u];
-            let max_height = v_ub - quad_min_array[i_v];
-
-            let (quad_width, quad_height) = unsafe {
-                Merger::find_quad(
-                    quad_min_index,
-                    max_width,
-                    max_height,
-                    &face_strides,
-                    voxels,
-                    visited,
-                    ctx,
-                )
-            };
-            debug_assert!(quad_width >= 1);
-            debug_assert!(quad_width <= max_width);
-            debug_assert!(quad_height >= 1);
-            debug_assert!(quad_height <= max_height);
+    for quad_min in slice_extent.iter3() {
+        let quad_min_array = quad_min.to_array();
+        let quad_min_index = voxels_shape.linearize(quad_min_array);
+        let quad_min_voxel = unsafe { get_voxel_unchecked(voxels, quad_min_index) };
+        if unsafe {
+            !face_needs_mesh(
+                quad_min_voxel,
+                quad_min_index,
+                face_strides,
+                voxels,
+                visited,
+                ctx,
+            )
+        } {
+            continue;
+        }
+        // We have at least one face that needs a mesh. We'll try to expand that face into the biggest quad we can find.
+
+        // These are the boundaries on quad width and height so it is contained in the slice and respects the
+        // configured merge limit and grid.
+        let mut max_width = (u_ub - quad_min_array[i_u]).min(config.max_merge.x);
+        let mut max_height = (v_ub - quad_min_array[i_v]).min(config.max_merge.y);
+        if let Some(align) = config.merge_grid_align {
+            max_width = max_width.min(align - quad_min_array[i_u] % align);
+            max_height = max_height.min(align - quad_min_array[i_v] % align);
+        }
 
-            // Mark the quad as visited.
+        let (quad_width, quad_height) = unsafe {
+            Merger::find_quad(
+                quad_min_index,
+                max_width,
+                max_height,
+                face_strides,
+                voxels,
+                visited,
+                ctx,
+            )
+        };
+        // A faulty MergeStrategy must not be able to produce degenerate quads or mark voxels outside of the slice.
+        let quad_is_valid =
+            (1..=max_width).contains(&quad_width) && (1..=max_height).contains(&quad_height);
+        debug_assert!(
+            quad_is_valid,
+            "MergeStrategy returned an invalid quad size ({quad_width}, {quad_height}); \
+             expected a size in (1..={max_width}, 1..={max_height})"
+        );
+        if !quad_is_valid {
+            stats.rejected_quads += 1;
+            continue;
+        }
+
+        // Mark the quad as visited.
+        if config.respect_visited {
             let mut quad_shape = [0; 3];
             quad_shape[i_n] = 1;
             quad_shape[i_u] = quad_width;
             quad_shape[i_v] = quad_height;
             fill3(quad_shape, true, visited, voxels_shape, quad_min_array);
-
-            quads.push(UnorientedQuad {
-                minimum: quad_min.to_array(),
-                width: quad_width,
-                height: quad_height,
-            });
         }
 
-        // Move to the next slice.
-        slice_extent = slice_extent + *n;
+        let quad = UnorientedQuad {
+            minimum: quad_min.to_array(),
+            width: quad_width,
+            height: quad_height,
+        };
+        if on_quad
+            .as_deref_mut()
+            .is_none_or(|on_quad| on_quad(&quad, quad_min_voxel))
+        {
+            quads.push(quad);
+        }
     }
 }
 
 /// Returns true iff the given `voxel` face needs to be meshed. This means that we haven't already meshed it, it is non-empty,
 /// and it's visible (not completely occluded by an adjacent voxel).
-pub(crate) unsafe fn face_needs_mesh<T, C>(
-    voxel: &T,
+pub(crate) unsafe fn face_needs_mesh<V, C>(
+    voxel: &V::Voxel,
     voxel_stride: u32,
-    visibility_offset: u32,
-    voxels: &[T],
+    face_strides: &FaceStrides,
+    voxels: &V,
     visited: &[bool],
     ctx: &C,
 ) -> bool
 where
-    C: VoxelContext<T>,
+    V: VoxelSource + ?Sized,
+    C: VoxelContext<V::Voxel>,
 {
-    if ctx.get_visibility(voxel) == VoxelVisibility::Empty || visited[voxel_stride as usize] {
+    if visited[voxel_stride as usize] {
         return false;
     }
 
-    let adjacent_voxel =
-        voxels.get_unchecked(voxel_stride.wrapping_add(visibility_offset) as usize);
+    let adjacent_voxel = get_voxel_unchecked(
+        voxels,
+        voxel_stride.wrapping_add(face_strides.visibility_offset),
+    );
 
-    // TODO: If the face lies between two transparent voxels, we choose not to mesh it. We might need to extend the IsOpaque
-    // trait with different levels of transparency to support this.
-    match ctx.get_visibility(adjacent_voxel) {
-        VoxelVisibility::Empty => true,
-        VoxelVisibility::Translucent => ctx.get_visibility(voxel) == VoxelVisibility::Opaque,
-        VoxelVisibility::Opaque => false,
-    }
+    face_is_visible_for_face(
+        voxel,
+        adjacent_voxel,
+        face_strides.face_index,
+        face_strides.opposite_face_index,
+        ctx,
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
-    use ndshape::{ConstShape, ConstShape3u32};
+    use crate::test_util::{sphere, split_sphere, BoolVoxel, SampleShape, EMPTY, FULL};
+    use crate::{
+        visible_block_faces, Axis, DefaultVoxelContext, LayerId, MeshOptions, SignedAxis,
+        UnitQuadBuffer, RIGHT_HANDED_Y_UP_CONFIG,
+    };
+    use ndshape::ConstShape;
 
     #[test]
     #[should_panic]
@@ -308,34 +936,1083 @@ mod tests {
         );
     }
 
-    type SampleShape = ConstShape3u32<34, 34, 34>;
+    #[test]
+    fn max_merge_caps_quad_size() {
+        // A 32x1x32 floor.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for z in 1..33 {
+            for x in 1..33 {
+                samples[shape.linearize([x, 1, z]) as usize] = FULL;
+            }
+        }
+        let config = GreedyQuadsConfig {
+            max_merge: UVec2::splat(16),
+            ..Default::default()
+        };
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_config(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &config,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        for quad in buffer.quads.groups.iter().flatten() {
+            assert!(quad.width <= 16 && quad.height <= 16);
+        }
+        // The top face of the floor is split into a 2x2 grid.
+        assert_eq!(buffer.quads.groups[4].len(), 4);
+    }
+
+    #[test]
+    fn merge_grid_align_breaks_quads_at_multiples() {
+        // A 32x1x32 floor from 1 to 32, so the grid lines at 8, 16, and 24 split it into 5 columns and rows.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for z in 1..33 {
+            for x in 1..33 {
+                samples[shape.linearize([x, 1, z]) as usize] = FULL;
+            }
+        }
+        let config = GreedyQuadsConfig {
+            merge_grid_align: Some(8),
+            ..Default::default()
+        };
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_config(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &config,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        for (group, face) in buffer.quads.groups.iter().zip(faces.iter()) {
+            let [_, u_axis, v_axis] = face.permutation.axes();
+            for quad in group.iter() {
+                let u = quad.minimum[u_axis.index()];
+                let v = quad.minimum[v_axis.index()];
+                assert_eq!(u / 8, (u + quad.width - 1) / 8);
+                assert_eq!(v / 8, (v + quad.height - 1) / 8);
+            }
+        }
+        let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+        assert_eq!(buffer.quads.groups[pos_y].len(), 5 * 5);
+    }
+
+    #[test]
+    fn ignoring_visited_starts_a_quad_at_every_face() {
+        let shape = SampleShape {};
+        let samples = sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
 
-    /// Basic voxel type with one byte of texture layers
-    #[derive(Default, Clone, Copy, Eq, PartialEq)]
-    struct BoolVoxel(bool);
+        let mut unit_quads = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut unit_quads,
+            &DefaultVoxelContext,
+        );
+        let config = GreedyQuadsConfig {
+            respect_visited: false,
+            ..Default::default()
+        };
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_config(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &config,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
 
-    const EMPTY: BoolVoxel = BoolVoxel(false);
+        // Every visible face is the minimum of exactly one quad, and the quad at each face is at least as wide as the
+        // quad that starts there when visited voxels are respected, since its row isn't cut short by other quads.
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        for ((group, unit_group), expected_group) in buffer
+            .quads
+            .groups
+            .iter()
+            .zip(unit_quads.groups.iter())
+            .zip(expected.quads.groups.iter())
+        {
+            let mut minimums: Vec<_> = group.iter().map(|quad| quad.minimum).collect();
+            let mut unit_minimums: Vec<_> = unit_group.iter().map(|quad| quad.minimum).collect();
+            minimums.sort_unstable();
+            unit_minimums.sort_unstable();
+            assert_eq!(minimums, unit_minimums);
 
-    impl Voxel for BoolVoxel {
-        fn get_visibility(&self) -> VoxelVisibility {
-            if *self == EMPTY {
-                VoxelVisibility::Empty
-            } else {
-                VoxelVisibility::Opaque
+            for expected_quad in expected_group.iter() {
+                let quad = group
+                    .iter()
+                    .find(|quad| quad.minimum == expected_quad.minimum)
+                    .unwrap();
+                assert!(quad.width >= expected_quad.width);
             }
         }
     }
 
-    impl MergeVoxel for BoolVoxel {
-        type MergeValue = Self;
-        type MergeValueFacingNeighbour = bool;
+    #[test]
+    fn merge_events_match_output_quads() {
+        let shape = SampleShape {};
+        let samples = sphere();
+
+        let mut events = Vec::new();
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_merge_callback(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &GreedyQuadsConfig::default(),
+            &mut buffer,
+            &DefaultVoxelContext,
+            |event| events.push(event),
+        );
+
+        let expected: Vec<_> = buffer
+            .quads
+            .groups
+            .iter()
+            .enumerate()
+            .flat_map(|(face_index, group)| {
+                group.iter().map(move |&quad| MergeEvent {
+                    face_index,
+                    quad,
+                    absorbed_voxels: quad.width * quad.height,
+                })
+            })
+            .collect();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn representatives_are_the_voxels_at_quad_minimums() {
+        /// Merges every solid voxel, whatever its value.
+        struct SolidContext;
 
-        fn merge_value(&self) -> Self::MergeValue {
-            *self
+        impl VoxelContext<u8> for SolidContext {
+            fn get_visibility(&self, voxel: &u8) -> VoxelVisibility {
+                if *voxel == 0 {
+                    VoxelVisibility::Empty
+                } else {
+                    VoxelVisibility::Opaque
+                }
+            }
         }
 
-        fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
-            true
+        impl MergeVoxelContext<u8> for SolidContext {
+            type MergeValue = bool;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, voxel: &u8) -> bool {
+                *voxel != 0
+            }
+
+            fn merge_value_facing_neighbour(&self, voxel: &u8) -> bool {
+                *voxel != 0
+            }
         }
+
+        let shape = SampleShape {};
+        let mut samples = [0; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let p = UVec3::from(shape.delinearize(i)).as_vec3() - 16.0;
+            if p.length() < 15.0 {
+                samples[i as usize] = 1 + (i % 251) as u8;
+            }
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        let mut representatives = RepresentativeVoxelBuffer::new();
+        representatives.groups[0].push(0);
+        greedy_quads_with_representatives(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &mut representatives,
+            &SolidContext,
+        );
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut expected,
+            &SolidContext,
+        );
+        assert_eq!(buffer.quads.groups, expected.quads.groups);
+
+        let groups = buffer
+            .quads
+            .groups
+            .iter()
+            .zip(representatives.groups.iter());
+        for (group, voxels) in groups {
+            assert_eq!(group.len(), voxels.len());
+            for (quad, voxel) in group.iter().zip(voxels.iter()) {
+                assert_eq!(*voxel, samples[shape.linearize(quad.minimum) as usize]);
+            }
+        }
+        // The quads cover voxels with different values, so the representatives aren't all the same.
+        assert!(representatives.groups[0].windows(2).any(|w| w[0] != w[1]));
+    }
+
+    #[test]
+    fn quads_are_split_by_the_visibility_of_their_minimum() {
+        /// The upper half of the sphere is glass.
+        struct GlassContext;
+
+        impl VoxelContext<BoolVoxel> for GlassContext {
+            fn get_visibility(&self, voxel: &BoolVoxel) -> VoxelVisibility {
+                match voxel {
+                    BoolVoxel(false, _) => VoxelVisibility::Empty,
+                    BoolVoxel(true, false) => VoxelVisibility::Opaque,
+                    BoolVoxel(true, true) => VoxelVisibility::Translucent,
+                }
+            }
+        }
+
+        impl MergeVoxelContext<BoolVoxel> for GlassContext {
+            type MergeValue = BoolVoxel;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, voxel: &BoolVoxel) -> Self::MergeValue {
+                *voxel
+            }
+
+            fn merge_value_facing_neighbour(&self, _voxel: &BoolVoxel) -> bool {
+                true
+            }
+        }
+
+        let shape = SampleShape {};
+        let samples = split_sphere();
+
+        let mut opaque = GreedyQuadsBuffer::new(samples.len());
+        let mut translucent = GreedyQuadsBuffer::new(samples.len());
+        translucent.quads.groups[0].push(UnorientedQuad {
+            minimum: [0; 3],
+            width: 1,
+            height: 1,
+        });
+        greedy_quads_by_visibility(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut opaque,
+            &mut translucent,
+            &GlassContext,
+        );
+
+        // Both outputs keep the order of a single greedy_quads call.
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut expected,
+            &GlassContext,
+        );
+        for (face_index, group) in expected.quads.groups.iter().enumerate() {
+            let (expected_translucent, expected_opaque): (Vec<_>, Vec<_>) = group
+                .iter()
+                .partition(|quad| samples[shape.linearize(quad.minimum) as usize].1);
+            assert_eq!(opaque.quads.groups[face_index], expected_opaque);
+            assert_eq!(translucent.quads.groups[face_index], expected_translucent);
+        }
+        assert!(translucent.quads.num_quads() > 0);
+        assert!(opaque.quads.num_quads() > 0);
+    }
+
+    #[test]
+    fn boundary_ownership_skips_unowned_slices() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1, 16, 16]) as usize] = FULL;
+        samples[shape.linearize([32, 16, 16]) as usize] = FULL;
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        let mut mesh_with = |boundary_ownership| {
+            let config = GreedyQuadsConfig {
+                boundary_ownership,
+                ..Default::default()
+            };
+            greedy_quads_with_config(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                &RIGHT_HANDED_Y_UP_CONFIG.faces,
+                &config,
+                &mut buffer,
+                &DefaultVoxelContext,
+            );
+            buffer
+                .quads
+                .groups
+                .iter()
+                .flatten()
+                .map(|quad| quad.minimum)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(mesh_with(BoundaryOwnership::Both).len(), 12);
+        let lower = mesh_with(BoundaryOwnership::Lower);
+        assert_eq!(lower.len(), 6);
+        assert!(lower.iter().all(|&minimum| minimum == [1, 16, 16]));
+        let upper = mesh_with(BoundaryOwnership::Upper);
+        assert_eq!(upper.len(), 6);
+        assert!(upper.iter().all(|&minimum| minimum == [32, 16, 16]));
+    }
+
+    #[test]
+    fn merging_reads_each_face_once() {
+        /// Counts the merge values that are read.
+        #[derive(Default)]
+        struct CountingContext {
+            num_reads: std::cell::Cell<usize>,
+        }
+
+        impl VoxelContext<BoolVoxel> for CountingContext {
+            fn get_visibility(&self, voxel: &BoolVoxel) -> VoxelVisibility {
+                voxel.get_visibility()
+            }
+        }
+
+        impl MergeVoxelContext<BoolVoxel> for CountingContext {
+            type MergeValue = BoolVoxel;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, voxel: &BoolVoxel) -> Self::MergeValue {
+                self.num_reads.set(self.num_reads.get() + 1);
+                *voxel
+            }
+
+            fn merge_value_facing_neighbour(&self, _voxel: &BoolVoxel) -> bool {
+                true
+            }
+        }
+
+        // A large slab, where every face merges into one quad.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let [x, y, z] = shape.delinearize(i);
+            if (1..=32).contains(&x) && (1..=4).contains(&y) && (1..=32).contains(&z) {
+                samples[i as usize] = FULL;
+            }
+        }
+
+        let ctx = CountingContext::default();
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &ctx,
+        );
+        assert_eq!(buffer.quads.num_quads(), 6);
+
+        // Growing a quad checks each of its faces once, plus one read of the quad's own merge value, so tall quads
+        // don't rescan their rows.
+        let num_faces = 2 * 32 * 32 + 4 * 32 * 4;
+        assert_eq!(ctx.num_reads.get(), num_faces + buffer.quads.num_quads());
+    }
+
+    #[test]
+    fn voxels_with_face_insets_are_not_merged() {
+        /// Every voxel is a pane that is 0.2 voxels thick along Z.
+        struct PaneContext;
+
+        impl VoxelContext<BoolVoxel> for PaneContext {
+            fn get_visibility(&self, voxel: &BoolVoxel) -> VoxelVisibility {
+                voxel.get_visibility()
+            }
+
+            fn face_inset(&self, _voxel: &BoolVoxel, face_index: usize) -> f32 {
+                match RIGHT_HANDED_Y_UP_CONFIG.face_direction(face_index) {
+                    SignedAxis::NegZ | SignedAxis::PosZ => 0.4,
+                    _ => 0.0,
+                }
+            }
+        }
+
+        impl MergeVoxelContext<BoolVoxel> for PaneContext {
+            type MergeValue = BoolVoxel;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, voxel: &BoolVoxel) -> Self::MergeValue {
+                *voxel
+            }
+
+            fn merge_value_facing_neighbour(&self, _voxel: &BoolVoxel) -> bool {
+                true
+            }
+        }
+
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1; 3]) as usize] = FULL;
+        samples[shape.linearize([2, 1, 1]) as usize] = FULL;
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &PaneContext,
+        );
+        let pos_z = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosZ);
+        assert_eq!(buffer.quads.groups[pos_z].len(), 2);
+
+        let options = MeshOptions {
+            insets: true,
+            ..Default::default()
+        };
+        let (mesh, _) = buffer.quads.to_mesh_data_with_voxels(
+            &RIGHT_HANDED_Y_UP_CONFIG,
+            &options,
+            &samples,
+            &shape,
+            &PaneContext,
+        );
+        // Every vertex is inside of the pane's slab.
+        assert!(mesh.positions.iter().all(|p| (1.4..=1.6).contains(&p[2])));
+    }
+
+    #[test]
+    fn faces_with_different_texture_rotations_are_not_merged() {
+        /// Nonzero voxels are solid, and their top face is rotated by one less than their value.
+        struct RotationContext;
+
+        impl VoxelContext<u8> for RotationContext {
+            fn get_visibility(&self, voxel: &u8) -> VoxelVisibility {
+                if *voxel == 0 {
+                    VoxelVisibility::Empty
+                } else {
+                    VoxelVisibility::Opaque
+                }
+            }
+
+            fn texture_rotation(&self, voxel: &u8, face_index: usize) -> u8 {
+                match RIGHT_HANDED_Y_UP_CONFIG.face_direction(face_index) {
+                    SignedAxis::PosY => voxel - 1,
+                    _ => 0,
+                }
+            }
+        }
+
+        impl MergeVoxelContext<u8> for RotationContext {
+            type MergeValue = bool;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, _voxel: &u8) -> bool {
+                true
+            }
+
+            fn merge_value_facing_neighbour(&self, _voxel: &u8) -> bool {
+                true
+            }
+        }
+
+        // A row of 4 voxels along X, where the top faces of the last 2 are rotated by a quarter turn.
+        let shape = SampleShape {};
+        let mut samples = [0; SampleShape::SIZE as usize];
+        for x in 0..4 {
+            samples[shape.linearize([1 + x, 1, 1]) as usize] = 1 + (x >= 2) as u8;
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &RotationContext,
+        );
+        let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+        let neg_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::NegY);
+        assert_eq!(buffer.quads.groups[pos_y].len(), 2);
+        assert_eq!(buffer.quads.groups[neg_y].len(), 1);
+
+        let options = MeshOptions {
+            texture_rotations: true,
+            ..Default::default()
+        };
+        let (mesh, _) = buffer.quads.to_mesh_data_with_voxels(
+            &RIGHT_HANDED_Y_UP_CONFIG,
+            &options,
+            &samples,
+            &shape,
+            &RotationContext,
+        );
+        let unrotated = buffer.quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+        assert_eq!(mesh.positions, unrotated.positions);
+        let num_rotated = mesh
+            .tex_coords
+            .chunks(4)
+            .zip(unrotated.tex_coords.chunks(4))
+            .filter(|(rotated, unrotated)| rotated != unrotated)
+            .count();
+        assert_eq!(num_rotated, 1);
+    }
+
+    #[test]
+    fn uniform_chunks_only_mesh_their_boundary() {
+        let shape = SampleShape {};
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let mesh = |samples: &[BoolVoxel]| {
+            let mut buffer = GreedyQuadsBuffer::new(samples.len());
+            greedy_quads(
+                samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                faces,
+                &mut buffer,
+                &DefaultVoxelContext,
+            );
+            buffer.quads
+        };
+
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        assert_eq!(mesh(&samples).num_quads(), 0);
+
+        // A solid interior with empty padding has one full quad per face.
+        for i in 0..SampleShape::SIZE {
+            let p = shape.delinearize(i);
+            samples[i as usize] = BoolVoxel(p.iter().all(|c| (1..33).contains(c)), false);
+        }
+        let quads = mesh(&samples);
+        for group in quads.groups.iter() {
+            assert_eq!(group.len(), 1);
+            assert_eq!((group[0].width, group[0].height), (32, 32));
+        }
+
+        // Solid padding hides the faces next to it.
+        for i in 0..SampleShape::SIZE {
+            if shape.delinearize(i)[1] == 0 {
+                samples[i as usize] = FULL;
+            }
+        }
+        let neg_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::NegY);
+        let quads = mesh(&samples);
+        assert!(quads.groups[neg_y].is_empty());
+        assert_eq!(quads.num_quads(), 5);
+
+        assert_eq!(mesh(&[FULL; SampleShape::SIZE as usize]).num_quads(), 0);
+    }
+
+    #[test]
+    fn merge_groups_split_quads_with_the_same_texture() {
+        /// A texture and the id of the object the voxel belongs to.
+        #[derive(Clone, Copy, Default, Eq, PartialEq)]
+        struct GroupVoxel(u8, u8);
+
+        impl Voxel for GroupVoxel {
+            fn get_visibility(&self) -> VoxelVisibility {
+                if self.0 == 0 {
+                    VoxelVisibility::Empty
+                } else {
+                    VoxelVisibility::Opaque
+                }
+            }
+        }
+
+        impl MergeVoxel for GroupVoxel {
+            type MergeValue = Self;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self) -> Self::MergeValue {
+                *self
+            }
+
+            fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+                true
+            }
+        }
+
+        // A 4x1x2 slab with one texture, split between two groups along X.
+        let shape = SampleShape {};
+        let mut samples = [GroupVoxel::default(); SampleShape::SIZE as usize];
+        for x in 0..4 {
+            for z in 0..2 {
+                samples[shape.linearize([1 + x, 1, 1 + z]) as usize] = GroupVoxel(1, (x / 2) as u8);
+            }
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+        let mut minimums: Vec<_> = buffer.quads.groups[pos_y]
+            .iter()
+            .map(|quad| (quad.minimum, quad.width * quad.height))
+            .collect();
+        minimums.sort();
+        assert_eq!(minimums, [([1, 1, 1], 4), ([3, 1, 1], 4)]);
+    }
+
+    #[test]
+    fn pair_predicate_limits_merging() {
+        /// Nonzero voxels are grass of the biome given by their value, and only similar biomes can merge.
+        struct BiomeContext;
+
+        impl VoxelContext<u8> for BiomeContext {
+            fn get_visibility(&self, voxel: &u8) -> VoxelVisibility {
+                if *voxel == 0 {
+                    VoxelVisibility::Empty
+                } else {
+                    VoxelVisibility::Opaque
+                }
+            }
+        }
+
+        impl MergeVoxelContext<u8> for BiomeContext {
+            type MergeValue = bool;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, _voxel: &u8) -> bool {
+                true
+            }
+
+            fn merge_value_facing_neighbour(&self, _voxel: &u8) -> bool {
+                true
+            }
+
+            fn can_merge_pair(&self, quad_voxel: &u8, voxel: &u8, _face_index: usize) -> bool {
+                quad_voxel.abs_diff(*voxel) <= 1
+            }
+        }
+
+        // A row of 4 voxels along X with biomes 1 through 4.
+        let shape = SampleShape {};
+        let mut samples = [0; SampleShape::SIZE as usize];
+        for x in 0..4 {
+            samples[shape.linearize([1 + x, 1, 1]) as usize] = 1 + x as u8;
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &BiomeContext,
+        );
+        // Biomes 1 and 2 merge, then 3 and 4, even though 2 and 3 are also similar.
+        let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+        let mut widths: Vec<_> = buffer.quads.groups[pos_y]
+            .iter()
+            .map(|quad| (quad.minimum[0], quad.width.max(quad.height)))
+            .collect();
+        widths.sort();
+        assert_eq!(widths, [(1, 2), (3, 2)]);
+        // The ends of the row each touch a single biome.
+        let pos_x = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosX);
+        assert_eq!(buffer.quads.groups[pos_x].len(), 1);
+    }
+
+    #[test]
+    fn faces_with_different_layers_are_not_merged() {
+        /// Nonzero voxels are solid, and voxels greater than 1 have snow and then moss over their top face.
+        struct LayerContext;
+
+        impl VoxelContext<u8> for LayerContext {
+            fn get_visibility(&self, voxel: &u8) -> VoxelVisibility {
+                if *voxel == 0 {
+                    VoxelVisibility::Empty
+                } else {
+                    VoxelVisibility::Opaque
+                }
+            }
+
+            fn face_layers<'a>(&'a self, voxel: &'a u8, face_index: usize) -> &'a [LayerId] {
+                match RIGHT_HANDED_Y_UP_CONFIG.face_direction(face_index) {
+                    SignedAxis::PosY if *voxel > 1 => &[SNOW, MOSS],
+                    _ => &[],
+                }
+            }
+        }
+
+        impl MergeVoxelContext<u8> for LayerContext {
+            type MergeValue = bool;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, _voxel: &u8) -> bool {
+                true
+            }
+
+            fn merge_value_facing_neighbour(&self, _voxel: &u8) -> bool {
+                true
+            }
+        }
+
+        const SNOW: LayerId = 7;
+        const MOSS: LayerId = 3;
+
+        // A row of 4 voxels along X, where the last 2 have layers.
+        let shape = SampleShape {};
+        let mut samples = [0; SampleShape::SIZE as usize];
+        for x in 0..4 {
+            samples[shape.linearize([1 + x, 1, 1]) as usize] = 1 + (x >= 2) as u8;
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &LayerContext,
+        );
+        let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+        assert_eq!(buffer.quads.groups[pos_y].len(), 2);
+        assert_eq!(buffer.quads.num_quads(), 7);
+
+        let options = MeshOptions {
+            layer_offset: Some(0.01),
+            ..Default::default()
+        };
+        let (mesh, layers) = buffer.quads.to_mesh_data_with_voxels(
+            &RIGHT_HANDED_Y_UP_CONFIG,
+            &options,
+            &samples,
+            &shape,
+            &LayerContext,
+        );
+        assert_eq!(layers.len(), 9);
+        assert_eq!(mesh.positions.len(), 4 * layers.len());
+        let layered: Vec<_> = layers
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.is_some())
+            .collect();
+        assert_eq!(layered.len(), 2);
+        // Each layer is over the face and the layers before it.
+        for (n, (i, layer)) in layered.into_iter().enumerate() {
+            assert_eq!(*layer, Some([SNOW, MOSS][n]));
+            let base = &mesh.positions[4 * (i - n - 1)..][..4];
+            let quad = &mesh.positions[4 * i..][..4];
+            for (base, p) in base.iter().zip(quad.iter()) {
+                assert_eq!([base[0], base[2]], [p[0], p[2]]);
+                assert!((p[1] - base[1] - 0.01 * (n + 1) as f32).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn bottom_slabs_have_half_height_sides() {
+        /// Every voxel is a slab filling the bottom half of its cell.
+        struct SlabContext;
+
+        impl VoxelContext<BoolVoxel> for SlabContext {
+            fn get_visibility(&self, voxel: &BoolVoxel) -> VoxelVisibility {
+                if voxel.0 {
+                    VoxelVisibility::Translucent
+                } else {
+                    VoxelVisibility::Empty
+                }
+            }
+
+            fn face_inset(&self, _voxel: &BoolVoxel, face_index: usize) -> f32 {
+                match RIGHT_HANDED_Y_UP_CONFIG.face_direction(face_index) {
+                    SignedAxis::PosY => 0.5,
+                    _ => 0.0,
+                }
+            }
+        }
+
+        impl MergeVoxelContext<BoolVoxel> for SlabContext {
+            type MergeValue = BoolVoxel;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, voxel: &BoolVoxel) -> Self::MergeValue {
+                *voxel
+            }
+
+            fn merge_value_facing_neighbour(&self, _voxel: &BoolVoxel) -> bool {
+                true
+            }
+        }
+
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1; 3]) as usize] = FULL;
+        samples[shape.linearize([2, 1, 1]) as usize] = FULL;
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &SlabContext,
+        );
+        // Neither the slabs nor their top faces are merged.
+        let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+        assert_eq!(buffer.quads.groups[pos_y].len(), 2);
+
+        let options = MeshOptions {
+            insets: true,
+            ..Default::default()
+        };
+        let (mesh, _) = buffer.quads.to_mesh_data_with_voxels(
+            &RIGHT_HANDED_Y_UP_CONFIG,
+            &options,
+            &samples,
+            &shape,
+            &SlabContext,
+        );
+        assert!(mesh.positions.iter().all(|p| (1.0..=1.5).contains(&p[1])));
+        for (face_index, face) in RIGHT_HANDED_Y_UP_CONFIG.faces.iter().enumerate() {
+            if face.axis() == Axis::Y {
+                continue;
+            }
+            // Every side face spans half of the cell's height.
+            let start = 4 * buffer.quads.groups[..face_index]
+                .iter()
+                .map(Vec::len)
+                .sum::<usize>();
+            let end = start + 4 * buffer.quads.groups[face_index].len();
+            for quad_positions in mesh.positions[start..end].chunks(4) {
+                let min_y = quad_positions.iter().map(|p| p[1]).fold(f32::MAX, f32::min);
+                let max_y = quad_positions.iter().map(|p| p[1]).fold(f32::MIN, f32::max);
+                assert_eq!((min_y, max_y), (1.0, 1.5));
+            }
+        }
+    }
+
+    #[test]
+    fn cached_merge_values_are_computed_once() {
+        struct CountingContext(std::cell::Cell<usize>);
+
+        impl VoxelContext<BoolVoxel> for CountingContext {
+            fn get_visibility(&self, voxel: &BoolVoxel) -> VoxelVisibility {
+                voxel.get_visibility()
+            }
+        }
+
+        impl MergeVoxelContext<BoolVoxel> for CountingContext {
+            type MergeValue = BoolVoxel;
+            type MergeValueFacingNeighbour = bool;
+
+            fn merge_value(&self, voxel: &BoolVoxel) -> Self::MergeValue {
+                self.0.set(self.0.get() + 1);
+                *voxel
+            }
+
+            fn merge_value_facing_neighbour(&self, _voxel: &BoolVoxel) -> bool {
+                true
+            }
+        }
+
+        let shape = SampleShape {};
+        let samples = sphere();
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+
+        let ctx = CountingContext(Default::default());
+        let mut cache = MergeValueCache::new();
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_merge_value_cache(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut cache,
+            &mut buffer,
+            &ctx,
+        );
+
+        assert_eq!(buffer.quads.groups, expected.quads.groups);
+        assert_eq!(ctx.0.get(), samples.len());
+    }
+
+    #[test]
+    fn squarish_merger_covers_all_visible_faces() {
+        let shape = SampleShape {};
+        let samples = sphere();
+
+        let mut unit_quads = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut unit_quads,
+            &DefaultVoxelContext,
+        );
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_merge_strategy::<_, _, SquarishVoxelMerger<_>, _>(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &GreedyQuadsConfig::default(),
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        for (group, unit_group) in buffer.quads.groups.iter().zip(unit_quads.groups.iter()) {
+            let area: u32 = group.iter().map(|q| q.width * q.height).sum();
+            assert_eq!(area as usize, unit_group.len());
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "invalid quad size")]
+    fn panics_with_degenerate_quad_in_debug() {
+        struct DegenerateMerger;
+
+        impl MergeStrategy for DegenerateMerger {
+            type Voxel = BoolVoxel;
+
+            unsafe fn find_quad<V, C>(
+                _min_index: u32,
+                _max_width: u32,
+                _max_height: u32,
+                _face_strides: &FaceStrides,
+                _voxels: &V,
+                _visited: &[bool],
+                _ctx: &C,
+            ) -> (u32, u32)
+            where
+                V: VoxelSource<Voxel = BoolVoxel> + ?Sized,
+                C: MergeVoxelContext<BoolVoxel>,
+            {
+                (0, 1)
+            }
+        }
+
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1; 3]) as usize] = FULL;
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_merge_strategy::<_, _, DegenerateMerger, _>(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &GreedyQuadsConfig::default(),
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is out of bounds")]
+    fn panics_with_out_of_bounds_stride_in_debug() {
+        struct OutOfBoundsMerger;
+
+        impl MergeStrategy for OutOfBoundsMerger {
+            type Voxel = BoolVoxel;
+
+            unsafe fn find_quad<V, C>(
+                min_index: u32,
+                _max_width: u32,
+                _max_height: u32,
+                face_strides: &FaceStrides,
+                voxels: &V,
+                _visited: &[bool],
+                _ctx: &C,
+            ) -> (u32, u32)
+            where
+                V: VoxelSource<Voxel = BoolVoxel> + ?Sized,
+                C: MergeVoxelContext<BoolVoxel>,
+            {
+                // Walk off the end of the array.
+                get_voxel_unchecked(voxels, min_index + 100 * face_strides.n_stride);
+                (1, 1)
+            }
+        }
+
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1; 3]) as usize] = FULL;
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_merge_strategy::<_, _, OutOfBoundsMerger, _>(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &GreedyQuadsConfig::default(),
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
     }
 }
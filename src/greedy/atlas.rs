@@ -0,0 +1,158 @@
+use crate::{GreedyQuadsBuffer, Rect};
+
+/// The region of a texture atlas assigned to one quad by [`atlas_pack`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct QuadAtlasRect {
+    /// The index of the quad's group in [`QuadBuffer::groups`](crate::QuadBuffer::groups).
+    pub face_index: usize,
+    /// The index of the quad in its group.
+    pub quad_index: usize,
+    /// The texels of the atlas covered by the quad, 1 texel per voxel face, so `rect.width` and `rect.height` are the
+    /// quad's [`UnorientedQuad::width`](crate::UnorientedQuad::width) and
+    /// [`UnorientedQuad::height`](crate::UnorientedQuad::height).
+    pub rect: Rect,
+}
+
+impl QuadAtlasRect {
+    /// Returns the UV coordinates of the quad's corners in an atlas of `atlas_size` by `atlas_size` texels, in the same
+    /// order as [`OrientedBlockFace::quad_corners`](crate::OrientedBlockFace::quad_corners), with U along the quad's
+    /// width and V along its height.
+    #[inline]
+    pub fn tex_coords(&self, atlas_size: u32) -> [[f32; 2]; 4] {
+        let scale = 1.0 / atlas_size as f32;
+        let [u0, v0] = self.rect.minimum.map(|c| c as f32 * scale);
+        let u1 = u0 + self.rect.width as f32 * scale;
+        let v1 = v0 + self.rect.height as f32 * scale;
+        [[u0, v0], [u1, v0], [u0, v1], [u1, v1]]
+    }
+}
+
+/// Assigns every quad in `buffer` a disjoint rectangle of texels in an `atlas_size` by `atlas_size` texture, with 1
+/// texel per voxel face, e.g. for baking lightmaps or AO of a whole chunk into one texture.
+///
+/// The result has one element per quad, in the order of [`QuadBuffer::groups`](crate::QuadBuffer::groups). Quads are
+/// packed into shelves from the tallest to the shortest, which wastes little space for the mostly small quads of a
+/// greedy mesh. There is no gutter between the rectangles, so leave one in the quads' content if the atlas is sampled
+/// with bilinear filtering.
+///
+/// Panics if the quads don't fit in the atlas.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// let mut buffer = GreedyQuadsBuffer::new(0);
+/// buffer.quads.groups[0].push(UnorientedQuad { minimum: [0; 3], width: 3, height: 2 });
+/// buffer.quads.groups[1].push(UnorientedQuad { minimum: [0; 3], width: 1, height: 4 });
+///
+/// let rects = atlas_pack(&buffer, 8);
+/// assert_eq!(rects[0].rect, Rect { minimum: [1, 0], width: 3, height: 2 });
+/// assert_eq!(rects[1].rect, Rect { minimum: [0, 0], width: 1, height: 4 });
+/// assert_eq!(rects[1].tex_coords(8)[3], [0.125, 0.5]);
+/// ```
+pub fn atlas_pack(buffer: &GreedyQuadsBuffer, atlas_size: u32) -> Vec<QuadAtlasRect> {
+    let mut rects: Vec<QuadAtlasRect> = buffer
+        .quads
+        .groups
+        .iter()
+        .enumerate()
+        .flat_map(|(face_index, group)| {
+            group
+                .iter()
+                .enumerate()
+                .map(move |(quad_index, quad)| QuadAtlasRect {
+                    face_index,
+                    quad_index,
+                    rect: Rect {
+                        minimum: [0; 2],
+                        width: quad.width,
+                        height: quad.height,
+                    },
+                })
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse((rects[i].rect.height, rects[i].rect.width)));
+
+    // Each shelf is as tall as its first (tallest) rectangle.
+    let (mut x, mut shelf_y, mut shelf_height) = (0, 0, 0);
+    for i in order {
+        let rect = &mut rects[i].rect;
+        assert!(
+            rect.width <= atlas_size,
+            "A quad of width {} doesn't fit in an atlas of size {atlas_size}",
+            rect.width
+        );
+        if x + rect.width > atlas_size {
+            shelf_y += shelf_height;
+            x = 0;
+            shelf_height = 0;
+        }
+        if shelf_height == 0 {
+            shelf_height = rect.height;
+        }
+        assert!(
+            shelf_y + shelf_height <= atlas_size,
+            "The quads don't fit in an atlas of size {atlas_size}"
+        );
+        rect.minimum = [x, shelf_y];
+        x += rect.width;
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sphere, SampleShape};
+    use crate::{greedy_quads, DefaultVoxelContext, UnorientedQuad, RIGHT_HANDED_Y_UP_CONFIG};
+
+    #[test]
+    fn atlas_rects_are_disjoint_and_match_quads() {
+        let shape = SampleShape {};
+        let samples = sphere();
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        let atlas_size = 128;
+        let rects = atlas_pack(&buffer, atlas_size);
+        assert_eq!(rects.len(), buffer.quads.num_quads());
+
+        let mut covered = vec![false; (atlas_size * atlas_size) as usize];
+        for atlas_rect in rects.iter() {
+            let quad = &buffer.quads.groups[atlas_rect.face_index][atlas_rect.quad_index];
+            let rect = atlas_rect.rect;
+            assert_eq!((rect.width, rect.height), (quad.width, quad.height));
+            for y in rect.minimum[1]..rect.minimum[1] + rect.height {
+                for x in rect.minimum[0]..rect.minimum[0] + rect.width {
+                    let texel = &mut covered[(y * atlas_size + x) as usize];
+                    assert!(!*texel);
+                    *texel = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn atlas_pack_panics_when_quads_dont_fit() {
+        let mut buffer = GreedyQuadsBuffer::new(0);
+        buffer.quads.groups[0].extend(
+            [UnorientedQuad {
+                minimum: [0; 3],
+                width: 2,
+                height: 2,
+            }; 5],
+        );
+        atlas_pack(&buffer, 4);
+    }
+}
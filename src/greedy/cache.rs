@@ -0,0 +1,132 @@
+use super::{greedy_quads, GreedyQuadsBuffer};
+use crate::{
+    ContextAdapter, ForwardingContext, MergeContextAdapter, MergeVoxelContext, OrientedBlockFace,
+};
+
+use ndshape::Shape;
+
+/// Merge values that are computed once per voxel, for [`greedy_quads_with_merge_value_cache`].
+///
+/// The buffer can be reused between calls to avoid reallocating.
+pub struct MergeValueCache<M, N> {
+    /// The linear index of every voxel, which is meshed in place of the voxels themselves.
+    indices: Vec<u32>,
+    merge_values: Vec<M>,
+    merge_values_facing_neighbour: Vec<N>,
+}
+
+impl<M, N> Default for MergeValueCache<M, N> {
+    fn default() -> Self {
+        Self {
+            indices: Vec::new(),
+            merge_values: Vec::new(),
+            merge_values_facing_neighbour: Vec::new(),
+        }
+    }
+}
+
+impl<M, N> MergeValueCache<M, N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn fill<T, C>(&mut self, voxels: &[T], ctx: &C)
+    where
+        C: MergeVoxelContext<T, MergeValue = M, MergeValueFacingNeighbour = N>,
+    {
+        if self.indices.len() != voxels.len() {
+            self.indices = (0..voxels.len() as u32).collect();
+        }
+        self.merge_values.clear();
+        self.merge_values
+            .extend(voxels.iter().map(|voxel| ctx.merge_value(voxel)));
+        self.merge_values_facing_neighbour.clear();
+        self.merge_values_facing_neighbour.extend(
+            voxels
+                .iter()
+                .map(|voxel| ctx.merge_value_facing_neighbour(voxel)),
+        );
+    }
+}
+
+/// Same as [`greedy_quads`], but [`MergeVoxelContext::merge_value`] and
+/// [`MergeVoxelContext::merge_value_facing_neighbour`] are called exactly once per voxel, and the results are stored
+/// in `cache`.
+///
+/// The default algorithm recomputes merge values for every comparison while growing a quad, which is cheapest when they
+/// are trivial, like copying a block ID. Use this when computing them is expensive, e.g. hashing texture IDs.
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_merge_value_cache<T, S, C>(
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    cache: &mut MergeValueCache<C::MergeValue, C::MergeValueFacingNeighbour>,
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+    C::MergeValue: Copy,
+    C::MergeValueFacingNeighbour: Copy,
+{
+    cache.fill(voxels, ctx);
+
+    // The index array has the same shape as the voxels, so every stride and quad is the same.
+    let cached_ctx = ForwardingContext(CachedMergeContext { voxels, cache, ctx });
+    greedy_quads(
+        &cache.indices,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        output,
+        &cached_ctx,
+    );
+}
+
+/// Reads the voxel at each index from `voxels` and its merge values from `cache`.
+struct CachedMergeContext<'a, T, C: MergeVoxelContext<T>> {
+    voxels: &'a [T],
+    cache: &'a MergeValueCache<C::MergeValue, C::MergeValueFacingNeighbour>,
+    ctx: &'a C,
+}
+
+impl<'a, T, C> ContextAdapter<u32> for CachedMergeContext<'a, T, C>
+where
+    C: MergeVoxelContext<T>,
+{
+    type Voxel = T;
+    type Inner = C;
+
+    #[inline]
+    fn inner(&self) -> &C {
+        self.ctx
+    }
+
+    #[inline]
+    fn voxel<'b>(&'b self, index: &'b u32) -> Option<&'b T> {
+        Some(&self.voxels[*index as usize])
+    }
+}
+
+impl<'a, T, C> MergeContextAdapter<u32> for CachedMergeContext<'a, T, C>
+where
+    C: MergeVoxelContext<T>,
+    C::MergeValue: Copy,
+    C::MergeValueFacingNeighbour: Copy,
+{
+    type MergeValue = C::MergeValue;
+    type MergeValueFacingNeighbour = C::MergeValueFacingNeighbour;
+
+    #[inline]
+    fn merge_value(&self, index: &u32) -> Self::MergeValue {
+        self.cache.merge_values[*index as usize]
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, index: &u32) -> Self::MergeValueFacingNeighbour {
+        self.cache.merge_values_facing_neighbour[*index as usize]
+    }
+}
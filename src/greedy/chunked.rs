@@ -0,0 +1,415 @@
+use super::{
+    greedy_quads_for_slice, FacePlan, GreedyMeshPlan, GreedyQuadsBuffer, GreedyScratch, MeshStats,
+    VoxelMerger,
+};
+use crate::bounds::assert_voxels_fit_shape;
+use crate::{MergeVoxelContext, OrientedBlockFace, QuadBuffer, VoxelSource};
+
+use ilattice::glam::UVec3;
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// The state of a [`greedy_quads`](crate::greedy_quads) run that is split into many small steps, so it can be spread
+/// across frames on a single thread. See [`greedy_quads_chunked`].
+///
+/// Each step meshes one slice of the interior for one face direction. The progress owns the visited mask of the face
+/// that is being meshed, so it can be kept between steps while the voxels and output buffer are only borrowed for each
+/// step. The voxels must not change until meshing is finished; call [`GreedyMeshProgress::restart`] after an edit.
+pub struct GreedyMeshProgress {
+    interior: Extent<UVec3>,
+    faces: [FacePlan; 6],
+    scratch: GreedyScratch,
+    face_index: usize,
+    slice_index: u32,
+    stats: MeshStats,
+}
+
+impl GreedyMeshProgress {
+    /// Prepares to mesh the interior of `[min, max]` in voxel arrays of `voxels_shape`.
+    ///
+    /// Panics if `[min, max]` is not contained in `voxels_shape`.
+    pub fn new<S>(
+        voxels_shape: &S,
+        faces: &[OrientedBlockFace; 6],
+        min: [u32; 3],
+        max: [u32; 3],
+    ) -> Self
+    where
+        S: Shape<3, Coord = u32>,
+    {
+        let plan = GreedyMeshPlan::new(voxels_shape, faces, min, max);
+        Self {
+            interior: plan.interior,
+            faces: plan.faces,
+            scratch: GreedyScratch::for_shape(voxels_shape),
+            face_index: 0,
+            slice_index: 0,
+            stats: MeshStats::default(),
+        }
+    }
+
+    /// Returns `true` iff every face direction has been meshed.
+    pub fn is_finished(&self) -> bool {
+        self.face_index == self.faces.len()
+    }
+
+    /// The statistics collected so far.
+    pub fn stats(&self) -> MeshStats {
+        self.stats
+    }
+
+    /// Starts meshing from the beginning on the next step, e.g. because the voxels changed.
+    pub fn restart(&mut self) {
+        self.face_index = 0;
+        self.slice_index = 0;
+        self.stats = MeshStats::default();
+    }
+
+    /// Meshes the next slice into `output`, returning the number of quads that were added, or `None` if meshing is
+    /// already finished. `output` is reset by the first step.
+    ///
+    /// `voxels`, `voxels_shape`, and `output` must be the same for every step.
+    pub fn step<V, S, C>(
+        &mut self,
+        voxels: &V,
+        voxels_shape: &S,
+        output: &mut QuadBuffer,
+        ctx: &C,
+    ) -> Option<usize>
+    where
+        V: VoxelSource + ?Sized,
+        S: Shape<3, Coord = u32>,
+        C: MergeVoxelContext<V::Voxel>,
+    {
+        if self.face_index == 0 && self.slice_index == 0 {
+            assert_voxels_fit_shape(voxels, voxels_shape);
+            output.reset();
+        }
+        assert_eq!(
+            self.scratch.visited.len(),
+            voxels.len(),
+            "The progress was created for a different voxel array"
+        );
+
+        // Skip any face direction with no slices.
+        while !self.is_finished()
+            && self.slice_index == self.faces[self.face_index].num_slices(self.interior)
+        {
+            self.face_index += 1;
+            self.slice_index = 0;
+        }
+        if self.is_finished() {
+            return None;
+        }
+
+        let face_plan = &self.faces[self.face_index];
+        if self.slice_index == 0 {
+            self.scratch.visited.fill(false);
+        }
+        let quads = &mut output.groups[self.face_index];
+        let num_quads = quads.len();
+        greedy_quads_for_slice::<_, _, VoxelMerger<V::Voxel>, _>(
+            voxels,
+            voxels_shape,
+            face_plan.slice_extent(self.interior, self.slice_index),
+            face_plan,
+            &Default::default(),
+            &mut self.scratch.visited,
+            quads,
+            &mut self.stats,
+            None,
+            ctx,
+        );
+
+        self.slice_index += 1;
+        if self.slice_index == face_plan.num_slices(self.interior) {
+            self.face_index += 1;
+            self.slice_index = 0;
+        }
+
+        Some(quads.len() - num_quads)
+    }
+}
+
+/// Same as [`greedy_quads_reuse`](crate::greedy_quads_reuse), but returns an iterator that does one step of
+/// [`GreedyMeshProgress`] per call to `next`, yielding the number of quads added by that step.
+///
+/// Dropping the iterator keeps the state in `progress`, so meshing can be resumed by calling this again with the same
+/// arguments, e.g. on the next frame.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let mut progress = GreedyMeshProgress::new(&ChunkShape {}, faces, [0; 3], [17; 3]);
+/// let mut quads = QuadBuffer::new();
+/// let mut frames = 0;
+/// while !progress.is_finished() {
+///     // Mesh at most 8 slices per frame.
+///     greedy_quads_chunked(&voxels, &ChunkShape {}, &mut progress, &mut quads, &DefaultVoxelContext)
+///         .take(8)
+///         .for_each(drop);
+///     frames += 1;
+/// }
+/// assert_eq!(frames, 6 * 16 / 8);
+/// assert_eq!(quads.num_quads(), 6);
+/// ```
+pub fn greedy_quads_chunked<'a, V, S, C>(
+    voxels: &'a V,
+    voxels_shape: &'a S,
+    progress: &'a mut GreedyMeshProgress,
+    output: &'a mut QuadBuffer,
+    ctx: &'a C,
+) -> GreedyQuadsChunked<'a, V, S, C>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    GreedyQuadsChunked {
+        voxels,
+        voxels_shape,
+        progress,
+        output,
+        ctx,
+    }
+}
+
+/// The iterator returned by [`greedy_quads_chunked`].
+pub struct GreedyQuadsChunked<'a, V: ?Sized, S, C> {
+    voxels: &'a V,
+    voxels_shape: &'a S,
+    progress: &'a mut GreedyMeshProgress,
+    output: &'a mut QuadBuffer,
+    ctx: &'a C,
+}
+
+impl<'a, V, S, C> Iterator for GreedyQuadsChunked<'a, V, S, C>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.progress
+            .step(self.voxels, self.voxels_shape, self.output, self.ctx)
+    }
+}
+
+/// Same as [`greedy_quads`](crate::greedy_quads), but stops once `budget` quads have been emitted. Returns `true` iff
+/// the mesh is complete, i.e. every quad fit in the budget.
+///
+/// Quads are emitted in the same deterministic order as [`greedy_quads`](crate::greedy_quads), face group by face group
+/// and slice by slice, so the output is always a prefix of the complete mesh, and calling this again with a larger
+/// budget only adds quads. This lets a coarse mesh appear quickly when streaming over a slow connection, e.g. by
+/// sending the quads past the previous budget on each call. Meshing stops at the end of the slice that exceeded the
+/// budget, so the excess quads of that slice are still generated before they are discarded.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// let complete =
+///     greedy_quads_with_budget(&voxels, &ChunkShape {}, [0; 3], [17; 3], faces, 4, &mut buffer, &DefaultVoxelContext);
+/// assert!(!complete);
+/// assert_eq!(buffer.quads.num_quads(), 4);
+///
+/// let complete =
+///     greedy_quads_with_budget(&voxels, &ChunkShape {}, [0; 3], [17; 3], faces, 6, &mut buffer, &DefaultVoxelContext);
+/// assert!(complete);
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_budget<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    budget: usize,
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) -> bool
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    let mut progress = GreedyMeshProgress::new(voxels_shape, faces, min, max);
+    let mut num_quads = 0;
+    // Keep going while the budget isn't exceeded, so a mesh that fits exactly is reported as complete.
+    while num_quads <= budget {
+        match progress.step(voxels, voxels_shape, &mut output.quads, ctx) {
+            Some(added) => num_quads += added,
+            None => break,
+        }
+    }
+    output.stats = progress.stats();
+
+    // Only the groups up to the current one have quads, so the excess is at the end of the last nonempty groups.
+    let mut excess = num_quads.saturating_sub(budget);
+    for group in output.quads.groups.iter_mut().rev() {
+        let removed = excess.min(group.len());
+        group.truncate(group.len() - removed);
+        excess -= removed;
+    }
+
+    progress.is_finished() && num_quads <= budget
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{split_sphere, SampleShape};
+    use crate::{greedy_quads, DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+
+    #[test]
+    fn chunked_matches_greedy_quads() {
+        let shape = SampleShape {};
+        let samples = split_sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+
+        let mut progress = GreedyMeshProgress::new(&shape, faces, [0; 3], [33; 3]);
+        let mut quads = QuadBuffer::new();
+        let mut num_steps = 0;
+        let mut num_quads = 0;
+        while !progress.is_finished() {
+            for added in greedy_quads_chunked(
+                &samples,
+                &shape,
+                &mut progress,
+                &mut quads,
+                &DefaultVoxelContext,
+            )
+            .take(5)
+            {
+                num_steps += 1;
+                num_quads += added;
+            }
+        }
+        assert_eq!(num_steps, 6 * 32);
+        assert_eq!(num_quads, quads.num_quads());
+        assert_eq!(quads.groups, expected.quads.groups);
+        assert_eq!(
+            progress.step(&samples, &shape, &mut quads, &DefaultVoxelContext),
+            None
+        );
+
+        // Restarting meshes everything again from scratch.
+        progress.restart();
+        greedy_quads_chunked(
+            &samples,
+            &shape,
+            &mut progress,
+            &mut quads,
+            &DefaultVoxelContext,
+        )
+        .for_each(drop);
+        assert_eq!(quads.groups, expected.quads.groups);
+    }
+
+    #[test]
+    fn budgeted_meshes_are_prefixes_of_the_full_mesh() {
+        let shape = SampleShape {};
+        let samples = split_sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        let expected: Vec<_> = expected.quads.groups.iter().flatten().copied().collect();
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        for budget in [0, 1, 100, expected.len() / 2, expected.len() - 1] {
+            let complete = greedy_quads_with_budget(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                faces,
+                budget,
+                &mut buffer,
+                &DefaultVoxelContext,
+            );
+            assert!(!complete);
+            let quads: Vec<_> = buffer.quads.groups.iter().flatten().copied().collect();
+            assert_eq!(quads, expected[..budget]);
+        }
+
+        for budget in [expected.len(), expected.len() + 1] {
+            let complete = greedy_quads_with_budget(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                faces,
+                budget,
+                &mut buffer,
+                &DefaultVoxelContext,
+            );
+            assert!(complete);
+            assert_eq!(buffer.quads.num_quads(), expected.len());
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use super::merge_strategy::faces_can_merge;
+use crate::{GreedyQuadsBuffer, MergeVoxelContext, OrientedBlockFace, UnorientedQuad, VoxelSource};
+
+use ilattice::glam::UVec3;
+use ndshape::Shape;
+
+/// Merges the quads of each group in `output` that lie in the same plane and share a whole edge, as long as their faces
+/// could have been merged by [`greedy_quads`](crate::greedy_quads). Returns the number of quads that were removed.
+///
+/// The greedy scan already merges every face it can into the quad it is building, so this only finds merges when the
+/// quads were limited by something else, like [`GreedyQuadsConfig::max_merge`](crate::GreedyQuadsConfig::max_merge) or
+/// [`GreedyQuadsConfig::merge_grid_align`](crate::GreedyQuadsConfig::merge_grid_align), separately meshed regions that
+/// were combined with [`QuadBuffer::append`](crate::QuadBuffer::append), or partial remeshing. Those limits are not
+/// respected here.
+///
+/// `voxels`, `voxels_shape`, `faces`, and `ctx` must be the same as when the quads were generated. Two quads are merged
+/// if the voxels at their minimums have the same merge values and texture keys, as within a quad, which is exact as
+/// long as [`MergeVoxelContext::can_merge_pair`] is transitive. Merging alternates between the U and V directions until
+/// no more quads can be merged, and the quads of each group are reordered.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// # use block_mesh::ilattice::glam::UVec2;
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // A 16^3 cube, meshed with quads of at most 4x4 voxels.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     voxels[i as usize] = BoolVoxel(ChunkShape::delinearize(i).iter().all(|c| (1..=16).contains(c)));
+/// }
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let config = GreedyQuadsConfig { max_merge: UVec2::splat(4), ..Default::default() };
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_with_config(&voxels, &ChunkShape {}, [0; 3], [17; 3], faces, &config, &mut buffer, &DefaultVoxelContext);
+/// assert_eq!(buffer.quads.num_quads(), 6 * 16);
+///
+/// let removed = merge_coplanar_postpass(&voxels, &ChunkShape {}, faces, &mut buffer, &DefaultVoxelContext);
+/// assert_eq!(removed, 6 * 15);
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// ```
+pub fn merge_coplanar_postpass<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) -> usize
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    let num_quads = output.quads.num_quads();
+    let groups = output.quads.groups.iter_mut().zip(faces.iter());
+    for (face_index, (group, face)) in groups.enumerate() {
+        let normal = face.signed_normal();
+        let face_voxels = |quad: &UnorientedQuad| {
+            let p = UVec3::from(quad.minimum);
+            let neighbour = (p.as_ivec3() + normal).as_uvec3();
+            (
+                voxels.voxel(voxels_shape.linearize(p.to_array()) as usize),
+                voxels.voxel(voxels_shape.linearize(neighbour.to_array()) as usize),
+            )
+        };
+        let can_merge = |quad: &UnorientedQuad, other: &UnorientedQuad| {
+            let (quad_voxel, quad_neighbour) = face_voxels(quad);
+            let (voxel, neighbour) = face_voxels(other);
+            faces_can_merge(
+                quad_voxel,
+                quad_neighbour,
+                voxel,
+                neighbour,
+                face_index,
+                ctx,
+            )
+        };
+
+        let [n_axis, u_axis, v_axis] = face.permutation.axes().map(|axis| axis.index());
+        loop {
+            let merged_u = merge_along(group, [n_axis, u_axis, v_axis], true, &can_merge);
+            let merged_v = merge_along(group, [n_axis, v_axis, u_axis], false, &can_merge);
+            if !merged_u && !merged_v {
+                break;
+            }
+        }
+    }
+    num_quads - output.quads.num_quads()
+}
+
+/// Merges each quad in `group` with the quads that follow it along the `axes[1]` axis, if they are in the same plane
+/// and have the same extent along `axes[2]`. The quads are sized along `axes[1]` by their width iff `along_u`. Returns
+/// `true` iff any quads were merged.
+fn merge_along<F>(
+    group: &mut Vec<UnorientedQuad>,
+    axes: [usize; 3],
+    along_u: bool,
+    can_merge: &F,
+) -> bool
+where
+    F: Fn(&UnorientedQuad, &UnorientedQuad) -> bool,
+{
+    let [n_axis, along_axis, across_axis] = axes;
+    let sizes = |quad: &UnorientedQuad| {
+        if along_u {
+            (quad.width, quad.height)
+        } else {
+            (quad.height, quad.width)
+        }
+    };
+    // Sorting puts the quads of each row next to each other, in order along the row.
+    group.sort_unstable_by_key(|quad| {
+        let [n, along, across] = axes.map(|axis| quad.minimum[axis]);
+        (n, across, sizes(quad).1, along)
+    });
+
+    let num_quads = group.len();
+    let mut merged: usize = 0;
+    for i in 0..num_quads {
+        let quad = group[i];
+        if let Some(last) = merged.checked_sub(1).map(|last| &mut group[last]) {
+            let (last_along, last_across) = sizes(last);
+            let is_adjacent = last.minimum[n_axis] == quad.minimum[n_axis]
+                && last.minimum[across_axis] == quad.minimum[across_axis]
+                && last_across == sizes(&quad).1
+                && last.minimum[along_axis] + last_along == quad.minimum[along_axis];
+            if is_adjacent && can_merge(last, &quad) {
+                if along_u {
+                    last.width += quad.width;
+                } else {
+                    last.height += quad.height;
+                }
+                continue;
+            }
+        }
+        group[merged] = quad;
+        merged += 1;
+    }
+    group.truncate(merged);
+    merged < num_quads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sample, SampleShape};
+    use crate::{
+        greedy_quads, greedy_quads_with_config, DefaultVoxelContext, GreedyQuadsConfig, MergeVoxel,
+        Voxel, VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG,
+    };
+    use ilattice::glam::{UVec2, Vec3};
+
+    #[test]
+    fn postpass_recovers_merges_without_crossing_materials() {
+        // A sphere with a different material in each octant.
+        let shape = SampleShape {};
+        let samples = sample(|p| {
+            if p.length() < 15.0 {
+                MaterialVoxel(true, p.cmpgt(Vec3::ZERO).bitmask() as u8)
+            } else {
+                EMPTY
+            }
+        });
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        let config = GreedyQuadsConfig {
+            max_merge: UVec2::splat(2),
+            ..Default::default()
+        };
+        greedy_quads_with_config(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &config,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let num_split = buffer.quads.num_quads();
+        assert!(num_split > expected.quads.num_quads());
+
+        let removed =
+            merge_coplanar_postpass(&samples, &shape, faces, &mut buffer, &DefaultVoxelContext);
+        assert_eq!(removed, num_split - buffer.quads.num_quads());
+        assert!(removed > 0);
+
+        // Every face is still covered once, and every quad has a single material.
+        for (group, expected_group) in buffer.quads.groups.iter().zip(expected.quads.groups.iter())
+        {
+            let area = |group: &[UnorientedQuad]| -> u32 {
+                group.iter().map(|quad| quad.width * quad.height).sum()
+            };
+            assert_eq!(area(group), area(expected_group));
+        }
+        for (group, face) in buffer.quads.groups.iter().zip(faces.iter()) {
+            for quad in group.iter() {
+                let material = samples[shape.linearize(quad.minimum) as usize].1;
+                for u in 0..quad.width {
+                    for v in 0..quad.height {
+                        let p = UVec3::from(quad.minimum) + u * face.u + v * face.v;
+                        assert_eq!(samples[shape.linearize(p.to_array()) as usize].1, material);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Occupancy and a material.
+    #[derive(Default, Clone, Copy, Eq, PartialEq)]
+    struct MaterialVoxel(bool, u8);
+
+    const EMPTY: MaterialVoxel = MaterialVoxel(false, 0);
+
+    impl Voxel for MaterialVoxel {
+        fn get_visibility(&self) -> VoxelVisibility {
+            if self.0 {
+                VoxelVisibility::Opaque
+            } else {
+                VoxelVisibility::Empty
+            }
+        }
+    }
+
+    impl MergeVoxel for MaterialVoxel {
+        type MergeValue = Self;
+        type MergeValueFacingNeighbour = bool;
+
+        fn merge_value(&self) -> Self::MergeValue {
+            *self
+        }
+
+        fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+            true
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use super::{greedy_quads_for_slice, GreedyMeshPlan, GreedyQuadsBuffer, MeshStats, VoxelMerger};
+use crate::bounds::assert_voxels_fit_shape;
+use crate::{MergeVoxelContext, OrientedBlockFace, VoxelContext, VoxelSource, VoxelVisibility};
+
+use ndshape::Shape;
+
+/// Same as [`greedy_quads`](crate::greedy_quads), but only remeshes the parts of `output` that can be affected by
+/// voxels whose occupancy changed since the last call.
+///
+/// `occupancy` is a snapshot of which voxels were not [`VoxelVisibility::Empty`] when `output` was last meshed, packed as
+/// in [`is_occupied`](crate::is_occupied). It is compared with `voxels` to find the changed voxels, and then updated to
+/// match `voxels`. Since quads never span more than one layer of voxels along their normal, only the layers containing
+/// a changed voxel or its neighbor along the normal are meshed again, so scattered single-voxel edits only cost a few
+/// slices per face. Returns the number of slices that were remeshed, and [`GreedyQuadsBuffer::stats`] only counts those
+/// slices.
+///
+/// Start with an all-zero `occupancy` and an empty `output`. Every other argument must be the same on every call.
+///
+/// This is not a drop-in replacement for [`greedy_quads`](crate::greedy_quads) in two ways:
+///
+/// - The quads of a face are not in the same order. The quads of the remeshed slices are removed and then appended after
+///   the quads of the other slices, so compare the output as a set, and recompute anything stored in parallel with the
+///   quads, like [`compute_ao_for_buffer`](crate::compute_ao_for_buffer), after every call.
+/// - Changes that keep the occupancy of every voxel are not detected. This includes replacing one material with another,
+///   but also changing a voxel between [`VoxelVisibility::Opaque`] and [`VoxelVisibility::Translucent`], which changes
+///   which faces of its neighbors are visible. Mesh the whole chunk with [`greedy_quads`](crate::greedy_quads) after
+///   such an edit.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// let mut occupancy = vec![0; ChunkShape::USIZE.div_ceil(64)];
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+///
+/// voxels[ChunkShape::linearize([4, 4, 4]) as usize] = BoolVoxel(true);
+/// greedy_quads_delta(&mut occupancy, &voxels, &ChunkShape {}, [0; 3], [17; 3], faces, &mut buffer, &DefaultVoxelContext);
+/// assert_eq!(buffer.quads.num_quads(), 6);
+///
+/// // Place a second block next to the first one. For each face, only its layer and the layer next to it are meshed again.
+/// voxels[ChunkShape::linearize([5, 4, 4]) as usize] = BoolVoxel(true);
+/// let num_slices =
+///     greedy_quads_delta(&mut occupancy, &voxels, &ChunkShape {}, [0; 3], [17; 3], faces, &mut buffer, &DefaultVoxelContext);
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// assert_eq!(num_slices, 6 * 2);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_delta<V, S, C>(
+    occupancy: &mut [u64],
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) -> u32
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    let plan = GreedyMeshPlan::new(voxels_shape, faces, min, max);
+    assert_voxels_fit_shape(voxels, voxels_shape);
+    let num_words = voxels.len().div_ceil(64);
+    assert!(
+        num_words <= occupancy.len(),
+        "occupancy has {} words but the voxels require {num_words}",
+        occupancy.len()
+    );
+
+    let changed = update_occupancy(&mut occupancy[..num_words], voxels, voxels_shape, ctx);
+
+    let GreedyQuadsBuffer {
+        quads,
+        stats,
+        scratch,
+    } = output;
+    scratch.reset(voxels.len());
+    *stats = MeshStats::default();
+
+    let interior = plan.interior;
+    let mut num_remeshed = 0;
+    for (group, face_plan) in quads.groups.iter_mut().zip(plan.faces.iter()) {
+        let face = &face_plan.face;
+        let i_n = face.permutation.axes()[0].index();
+        let n_min = interior.minimum.to_array()[i_n];
+        let num_slices = face_plan.num_slices(interior);
+
+        // A change to a voxel affects its own face and the face of its neighbor that touches it.
+        let mut dirty = vec![false; num_slices as usize];
+        for p in changed.iter() {
+            for n in [p[i_n], p[i_n].wrapping_add_signed(-face.n_sign)] {
+                if let Some(slice) = dirty.get_mut(n.wrapping_sub(n_min) as usize) {
+                    *slice = true;
+                }
+            }
+        }
+        if !dirty.contains(&true) {
+            continue;
+        }
+
+        group.retain(|quad| !dirty[(quad.minimum[i_n] - n_min) as usize]);
+        // Slices don't share any voxels, so the visited mask only needs to be reset once per face.
+        scratch.visited.fill(false);
+        for slice_index in 0..num_slices {
+            if dirty[slice_index as usize] {
+                greedy_quads_for_slice::<_, _, VoxelMerger<V::Voxel>, _>(
+                    voxels,
+                    voxels_shape,
+                    face_plan.slice_extent(interior, slice_index),
+                    face_plan,
+                    &Default::default(),
+                    &mut scratch.visited,
+                    group,
+                    stats,
+                    None,
+                    ctx,
+                );
+                num_remeshed += 1;
+            }
+        }
+    }
+    num_remeshed
+}
+
+/// Sets `occupancy` to the occupancy of `voxels` and returns the coordinates of every voxel that changed.
+fn update_occupancy<V, S, C>(
+    occupancy: &mut [u64],
+    voxels: &V,
+    voxels_shape: &S,
+    ctx: &C,
+) -> Vec<[u32; 3]>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    let mut changed = Vec::new();
+    for (word_index, word) in occupancy.iter_mut().enumerate() {
+        let start = 64 * word_index;
+        let end = (start + 64).min(voxels.len());
+        let mut current = 0;
+        for index in start..end {
+            if ctx.get_visibility(voxels.voxel(index)) != VoxelVisibility::Empty {
+                current |= 1 << (index - start);
+            }
+        }
+
+        let mut diff = *word ^ current;
+        *word = current;
+        while diff != 0 {
+            let index = start + diff.trailing_zeros() as usize;
+            changed.push(voxels_shape.delinearize(index as u32));
+            diff &= diff - 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{split_sphere, SampleShape};
+    use crate::{greedy_quads, DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::ConstShape;
+
+    #[test]
+    fn delta_matches_greedy_quads_after_scattered_edits() {
+        let shape = SampleShape {};
+        let mut samples = split_sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut occupancy = vec![0; SampleShape::USIZE.div_ceil(64)];
+        let mut delta = GreedyQuadsBuffer::new(samples.len());
+        let edits: [&[[u32; 3]]; 3] = [
+            &[],
+            &[[16, 16, 1], [3, 20, 9], [30, 2, 2], [16, 31, 16]],
+            &[[3, 20, 9], [16, 16, 16], [1, 1, 1], [32, 32, 32]],
+        ];
+        for edit in edits {
+            for &p in edit {
+                let voxel = &mut samples[shape.linearize(p) as usize];
+                voxel.0 = !voxel.0;
+            }
+
+            let num_slices = greedy_quads_delta(
+                &mut occupancy,
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                faces,
+                &mut delta,
+                &DefaultVoxelContext,
+            );
+            if !edit.is_empty() {
+                assert!(num_slices <= 6 * 2 * edit.len() as u32);
+            }
+
+            let mut expected = GreedyQuadsBuffer::new(samples.len());
+            greedy_quads(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                faces,
+                &mut expected,
+                &DefaultVoxelContext,
+            );
+            for (actual, expected) in delta.quads.groups.iter().zip(expected.quads.groups.iter()) {
+                let mut actual = actual.clone();
+                let mut expected = expected.clone();
+                actual.sort_by_key(|quad| quad.minimum);
+                expected.sort_by_key(|quad| quad.minimum);
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+    #[test]
+    fn visibility_swaps_are_not_detected() {
+        /// 1 is opaque and 2 is translucent.
+        struct SwapContext;
+
+        impl VoxelContext<u8> for SwapContext {
+            fn get_visibility(&self, voxel: &u8) -> VoxelVisibility {
+                match voxel {
+                    0 => VoxelVisibility::Empty,
+                    1 => VoxelVisibility::Opaque,
+                    _ => VoxelVisibility::Translucent,
+                }
+            }
+        }
+
+        impl MergeVoxelContext<u8> for SwapContext {
+            type MergeValue = u8;
+            type MergeValueFacingNeighbour = u8;
+
+            fn merge_value(&self, voxel: &u8) -> u8 {
+                *voxel
+            }
+
+            fn merge_value_facing_neighbour(&self, voxel: &u8) -> u8 {
+                *voxel
+            }
+        }
+
+        let shape = SampleShape {};
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let mut samples = [0; SampleShape::USIZE];
+        samples[shape.linearize([4, 4, 4]) as usize] = 1;
+        samples[shape.linearize([5, 4, 4]) as usize] = 1;
+
+        let mut occupancy = vec![0; SampleShape::USIZE.div_ceil(64)];
+        let mut delta = GreedyQuadsBuffer::new(samples.len());
+        let mesh_delta = |occupancy: &mut [u64], samples: &[u8], delta: &mut GreedyQuadsBuffer| {
+            greedy_quads_delta(
+                occupancy,
+                samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                faces,
+                delta,
+                &SwapContext,
+            )
+        };
+        mesh_delta(&mut occupancy, &samples, &mut delta);
+        assert_eq!(delta.quads.num_quads(), 6);
+
+        // Making one of the blocks translucent exposes the face of the other block between them, but the occupancy is
+        // the same, so nothing is remeshed.
+        samples[shape.linearize([5, 4, 4]) as usize] = 2;
+        assert_eq!(mesh_delta(&mut occupancy, &samples, &mut delta), 0);
+        assert_eq!(delta.quads.num_quads(), 6);
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &SwapContext,
+        );
+        assert_eq!(expected.quads.num_quads(), 11);
+
+        // Meshing the whole chunk brings the buffer back in sync, and later edits are applied on top of it.
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut delta,
+            &SwapContext,
+        );
+        samples[shape.linearize([4, 4, 4]) as usize] = 0;
+        mesh_delta(&mut occupancy, &samples, &mut delta);
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &SwapContext,
+        );
+        for (actual, expected) in delta.quads.groups.iter().zip(expected.quads.groups.iter()) {
+            let mut actual = actual.clone();
+            let mut expected = expected.clone();
+            actual.sort_by_key(|quad| quad.minimum);
+            expected.sort_by_key(|quad| quad.minimum);
+            assert_eq!(actual, expected);
+        }
+    }
+}
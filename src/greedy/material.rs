@@ -0,0 +1,226 @@
+use crate::{
+    greedy_quads, GreedyQuadsBuffer, MaterialId, MergeVoxelContext, OrientedBlockFace,
+    UnorientedQuad, VoxelSource,
+};
+
+use ndshape::Shape;
+use std::collections::BTreeMap;
+
+/// Quads grouped by both their face and their [`VoxelContext::material_id`], so each group can be drawn with a single
+/// draw call. Filled by [`greedy_quads_by_material`].
+///
+/// [`VoxelContext::material_id`]: crate::VoxelContext::material_id
+#[derive(Clone, Debug, Default)]
+pub struct MaterialQuadBuffer {
+    groups: BTreeMap<(usize, MaterialId), Vec<UnorientedQuad>>,
+}
+
+impl MaterialQuadBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the buffer. The groups keep their allocations for the next meshing call.
+    pub fn reset(&mut self) {
+        for group in self.groups.values_mut() {
+            group.clear();
+        }
+    }
+
+    /// Returns the quads of `faces[face_index]` with the material `material`, which is empty if there are none.
+    pub fn group_for(&self, face_index: usize, material: MaterialId) -> &[UnorientedQuad] {
+        self.groups
+            .get(&(face_index, material))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns every non-empty group along with its face index and material, ordered by face index and then material.
+    pub fn groups(&self) -> impl Iterator<Item = ((usize, MaterialId), &[UnorientedQuad])> {
+        self.groups
+            .iter()
+            .filter(|(_, group)| !group.is_empty())
+            .map(|(&key, group)| (key, group.as_slice()))
+    }
+
+    /// Returns the total count of quads across all groups.
+    pub fn num_quads(&self) -> usize {
+        self.groups.values().map(Vec::len).sum()
+    }
+
+    fn push(&mut self, face_index: usize, material: MaterialId, quad: UnorientedQuad) {
+        self.groups
+            .entry((face_index, material))
+            .or_default()
+            .push(quad);
+    }
+}
+
+/// Same as [`greedy_quads`], but the quads are also grouped by [`VoxelContext::material_id`] in `output`, so quads of
+/// one material are ready for a single draw call without sorting them afterwards.
+///
+/// Faces with different materials are never merged, so each quad has the material of the voxel at its minimum. `buffer`
+/// is the working memory, and it's left with the quads grouped only by face, as from [`greedy_quads`].
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// #[derive(Clone, Copy, Eq, PartialEq)]
+/// enum Block {
+///     Air,
+///     Stone,
+///     Glass,
+/// }
+///
+/// /// Stone and glass are both opaque and merge with each other, but they have different materials.
+/// struct Context;
+///
+/// impl VoxelContext<Block> for Context {
+///     fn get_visibility(&self, voxel: &Block) -> VoxelVisibility {
+///         match voxel {
+///             Block::Air => VoxelVisibility::Empty,
+///             _ => VoxelVisibility::Opaque,
+///         }
+///     }
+///
+///     fn material_id(&self, voxel: &Block, _face_index: usize) -> MaterialId {
+///         *voxel as MaterialId
+///     }
+/// }
+///
+/// impl MergeVoxelContext<Block> for Context {
+///     type MergeValue = bool;
+///     type MergeValueFacingNeighbour = bool;
+///     fn merge_value(&self, voxel: &Block) -> bool { *voxel != Block::Air }
+///     fn merge_value_facing_neighbour(&self, voxel: &Block) -> bool { *voxel != Block::Air }
+/// }
+///
+/// // A row of 2 stone blocks and 2 glass blocks along X.
+/// type ChunkShape = ConstShape3u32<6, 3, 3>;
+/// let mut voxels = [Block::Air; ChunkShape::SIZE as usize];
+/// for x in 1..=4 {
+///     voxels[ChunkShape::linearize([x, 1, 1]) as usize] = if x <= 2 { Block::Stone } else { Block::Glass };
+/// }
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// let mut by_material = MaterialQuadBuffer::new();
+/// greedy_quads_by_material(&voxels, &ChunkShape {}, [0; 3], [5, 2, 2], faces, &mut buffer, &mut by_material, &Context);
+///
+/// let top = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+/// let stone = by_material.group_for(top, Block::Stone as MaterialId);
+/// let glass = by_material.group_for(top, Block::Glass as MaterialId);
+/// assert_eq!(stone.len(), 1);
+/// assert_eq!(stone[0].minimum, [1, 1, 1]);
+/// assert_eq!(glass.len(), 1);
+/// assert_eq!(glass[0].minimum, [3, 1, 1]);
+/// assert_eq!(by_material.num_quads(), buffer.quads.num_quads());
+/// ```
+///
+/// [`VoxelContext::material_id`]: crate::VoxelContext::material_id
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_by_material<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    buffer: &mut GreedyQuadsBuffer,
+    output: &mut MaterialQuadBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    greedy_quads(voxels, voxels_shape, min, max, faces, buffer, ctx);
+
+    output.reset();
+    for (face_index, group) in buffer.quads.groups.iter().enumerate() {
+        for &quad in group.iter() {
+            let voxel = voxels.voxel(voxels_shape.linearize(quad.minimum) as usize);
+            output.push(face_index, ctx.material_id(voxel, face_index), quad);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sample, SampleShape};
+    use crate::{VoxelContext, VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG};
+    use ilattice::glam::{UVec3, Vec3};
+
+    #[test]
+    fn quads_are_split_and_grouped_by_material() {
+        // A sphere with a different material in each octant, which would otherwise all merge.
+        let shape = SampleShape {};
+        let samples = sample(|p| (p.length() < 15.0).then(|| p.cmpgt(Vec3::ZERO).bitmask()));
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        let mut output = MaterialQuadBuffer::new();
+        greedy_quads_by_material(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut buffer,
+            &mut output,
+            &Context,
+        );
+        assert_eq!(output.num_quads(), buffer.quads.num_quads());
+
+        let mut num_materials = 0;
+        for ((face_index, material), group) in output.groups() {
+            num_materials += 1;
+            let face = &faces[face_index];
+            for quad in group.iter() {
+                for u in 0..quad.width {
+                    for v in 0..quad.height {
+                        let p = UVec3::from(quad.minimum) + u * face.u + v * face.v;
+                        assert_eq!(
+                            samples[shape.linearize(p.to_array()) as usize],
+                            Some(material)
+                        );
+                    }
+                }
+            }
+        }
+        // Every face of the sphere crosses 4 octants.
+        assert_eq!(num_materials, 6 * 4);
+        assert!(output.group_for(0, 8).is_empty());
+    }
+
+    /// Merges every solid voxel, with the material of each voxel from its value.
+    struct Context;
+
+    impl VoxelContext<Option<u32>> for Context {
+        fn get_visibility(&self, voxel: &Option<u32>) -> VoxelVisibility {
+            if voxel.is_some() {
+                VoxelVisibility::Opaque
+            } else {
+                VoxelVisibility::Empty
+            }
+        }
+
+        fn material_id(&self, voxel: &Option<u32>, _face_index: usize) -> MaterialId {
+            voxel.unwrap_or(0)
+        }
+    }
+
+    impl MergeVoxelContext<Option<u32>> for Context {
+        type MergeValue = bool;
+        type MergeValueFacingNeighbour = bool;
+
+        fn merge_value(&self, voxel: &Option<u32>) -> bool {
+            voxel.is_some()
+        }
+
+        fn merge_value_facing_neighbour(&self, voxel: &Option<u32>) -> bool {
+            voxel.is_some()
+        }
+    }
+}
@@ -0,0 +1,174 @@
+/// An axis-aligned rectangle of cells in a 2D grid, as returned by [`greedy_merge_2d`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Rect {
+    /// The cell with the smallest X and Y in the rectangle.
+    pub minimum: [u32; 2],
+    /// The number of cells along X.
+    pub width: u32,
+    /// The number of cells along Y.
+    pub height: u32,
+}
+
+/// Covers every set cell of a `width` by `height` boolean grid with disjoint rectangles, using the same greedy merging
+/// as [`VoxelMerger`](crate::VoxelMerger) without any voxels.
+///
+/// `mask` is in row-major order, so cell `[x, y]` is `mask[(y * width + x) as usize]`. Cells are visited in the same
+/// order, and each unvisited set cell becomes the minimum of a rectangle that first grows as wide as possible along X,
+/// and then as tall as possible along Y while every row is set and unvisited. This is useful for decomposing arbitrary
+/// regions, e.g. when packing lightmaps or merging faces on some attribute that the meshing functions don't know about.
+///
+/// Panics if `mask.len()` is not `width * height`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// // An L shape.
+/// #[rustfmt::skip]
+/// let mask = [
+///     true, false, false,
+///     true, false, false,
+///     true, true,  true,
+/// ];
+/// let rects = greedy_merge_2d(&mask, 3, 3);
+/// assert_eq!(
+///     rects,
+///     [
+///         Rect { minimum: [0, 0], width: 1, height: 3 },
+///         Rect { minimum: [1, 2], width: 2, height: 1 },
+///     ]
+/// );
+/// ```
+pub fn greedy_merge_2d(mask: &[bool], width: u32, height: u32) -> Vec<Rect> {
+    assert_eq!(
+        mask.len(),
+        width as usize * height as usize,
+        "mask has {} cells but the grid is {width}x{height}",
+        mask.len()
+    );
+
+    let mut visited = vec![false; mask.len()];
+    let is_free = |visited: &[bool], index: usize| mask[index] && !visited[index];
+    // The number of consecutive free cells along X starting at `index`, up to `max_width`.
+    let row_width = |visited: &[bool], index: usize, max_width: u32| {
+        (0..max_width)
+            .take_while(|&dx| is_free(visited, index + dx as usize))
+            .count() as u32
+    };
+
+    let mut rects = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y * width + x) as usize;
+            if !is_free(&visited, index) {
+                continue;
+            }
+
+            let rect_width = row_width(&visited, index, width - x);
+            let mut rect_height = 1;
+            while y + rect_height < height
+                && row_width(&visited, index + (rect_height * width) as usize, rect_width)
+                    == rect_width
+            {
+                rect_height += 1;
+            }
+
+            for dy in 0..rect_height {
+                let row_start = index + (dy * width) as usize;
+                visited[row_start..row_start + rect_width as usize].fill(true);
+            }
+            rects.push(Rect {
+                minimum: [x, y],
+                width: rect_width,
+                height: rect_height,
+            });
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{EMPTY, FULL};
+    use crate::{
+        greedy_quads, DefaultVoxelContext, GreedyQuadsBuffer, SignedAxis, RIGHT_HANDED_Y_UP_CONFIG,
+    };
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    const WIDTH: u32 = 23;
+    const HEIGHT: u32 = 17;
+
+    /// A `WIDTH` by `HEIGHT` mask with holes of many sizes.
+    fn sample_mask() -> Vec<bool> {
+        (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let (x, y) = (i % WIDTH, i / WIDTH);
+                (x * 7 + y * 3) % 5 != 0 && (x / 4 + y / 3) % 3 != 0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rects_cover_set_cells_exactly_once() {
+        let (width, height) = (WIDTH, HEIGHT);
+        let mask = sample_mask();
+
+        let rects = greedy_merge_2d(&mask, width, height);
+        let mut covered = vec![0; mask.len()];
+        for rect in rects.iter() {
+            assert!(rect.width >= 1 && rect.height >= 1);
+            for y in rect.minimum[1]..rect.minimum[1] + rect.height {
+                for x in rect.minimum[0]..rect.minimum[0] + rect.width {
+                    covered[(y * width + x) as usize] += 1;
+                }
+            }
+        }
+        for (&set, &count) in mask.iter().zip(covered.iter()) {
+            assert_eq!(count, u32::from(set));
+        }
+
+        // A full grid is a single rectangle.
+        assert_eq!(
+            greedy_merge_2d(&[true; 12], 4, 3),
+            [Rect {
+                minimum: [0, 0],
+                width: 4,
+                height: 3
+            }]
+        );
+        assert!(greedy_merge_2d(&[], 0, 5).is_empty());
+    }
+    #[test]
+    fn rects_match_the_quads_of_a_slice() {
+        // The mask as a single layer of voxels, so the +Z faces are a slice whose U and V are X and Y.
+        type ChunkShape = ConstShape3u32<{ WIDTH + 2 }, { HEIGHT + 2 }, 3>;
+        let mask = sample_mask();
+        let mut voxels = [EMPTY; ChunkShape::USIZE];
+        for (i, _) in mask.iter().enumerate().filter(|(_, &set)| set) {
+            let (x, y) = (i as u32 % WIDTH, i as u32 / WIDTH);
+            voxels[ChunkShape::linearize([x + 1, y + 1, 1]) as usize] = FULL;
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+        greedy_quads(
+            &voxels,
+            &ChunkShape {},
+            [0; 3],
+            [WIDTH + 1, HEIGHT + 1, 2],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let pos_z = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosZ);
+        let quads: Vec<_> = buffer.quads.groups[pos_z]
+            .iter()
+            .map(|quad| Rect {
+                minimum: [quad.minimum[0] - 1, quad.minimum[1] - 1],
+                width: quad.width,
+                height: quad.height,
+            })
+            .collect();
+        assert_eq!(greedy_merge_2d(&mask, WIDTH, HEIGHT), quads);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::greedy::face_needs_mesh;
-use crate::MergeVoxelContext;
+use crate::{LayerId, MaterialId, MergeVoxelContext, VoxelContext, VoxelSource};
 
 // TODO: implement a MergeStrategy for voxels with an ambient occlusion value at each vertex
 
@@ -11,13 +11,17 @@ pub trait MergeStrategy {
     ///
     /// `min_index`: The linear index for the minimum voxel in this quad.
     ///
-    /// `max_width`: The maximum possible width for the quad to be constructed.
+    /// `max_width`: The maximum possible width for the quad to be constructed. This already accounts for
+    ///              [`GreedyQuadsConfig::max_merge`](crate::GreedyQuadsConfig::max_merge) and
+    ///              [`GreedyQuadsConfig::merge_grid_align`](crate::GreedyQuadsConfig::merge_grid_align).
     ///
-    /// `max_height`: The maximum possible height for the quad to be constructed.
+    /// `max_height`: The maximum possible height for the quad to be constructed. This already accounts for
+    ///               [`GreedyQuadsConfig::max_merge`](crate::GreedyQuadsConfig::max_merge) and
+    ///               [`GreedyQuadsConfig::merge_grid_align`](crate::GreedyQuadsConfig::merge_grid_align).
     ///
     /// `face_strides`: Strides to help with indexing in the necessary directions for this cube face.
     ///
-    /// `voxels`: The entire array of voxel data.
+    /// `voxels`: The entire array of voxel data. Read it with [`get_voxel_unchecked`].
     ///
     /// `visited`: The bitmask of which voxels have already been meshed. A quad's extent will be marked as visited (`true`)
     ///            after `find_quad` returns.
@@ -26,24 +30,60 @@ pub trait MergeStrategy {
     ///
     /// Some implementations may use unchecked indexing of `voxels` for performance. If this trait is not invoked with correct
     /// arguments, access out of bounds may cause undefined behavior.
-    unsafe fn find_quad<C>(
+    unsafe fn find_quad<V, C>(
         min_index: u32,
         max_width: u32,
         max_height: u32,
         face_strides: &FaceStrides,
-        voxels: &[Self::Voxel],
+        voxels: &V,
         visited: &[bool],
         ctx: &C,
     ) -> (u32, u32)
     where
+        V: VoxelSource<Voxel = Self::Voxel> + ?Sized,
         C: MergeVoxelContext<Self::Voxel>;
 }
 
+/// Returns the voxel at the linear index `stride`, like `voxels.get_unchecked(stride as usize)`.
+///
+/// In debug builds, the index is bounds-checked and an out-of-bounds `stride` panics with a message that includes it, so
+/// a faulty [`MergeStrategy`] or [`MergeVoxelContext`] can be diagnosed instead of causing undefined behavior. Custom
+/// strategies should use this for their own reads of `voxels`.
+///
+/// # Safety
+///
+/// In release builds, `stride` must be less than `voxels.len()`.
+#[inline]
+pub unsafe fn get_voxel_unchecked<V>(voxels: &V, stride: u32) -> &V::Voxel
+where
+    V: VoxelSource + ?Sized,
+{
+    if cfg!(debug_assertions) && stride as usize >= voxels.len() {
+        panic!(
+            "voxel stride {stride} (or {} if it wrapped) is out of bounds for {} voxels",
+            stride as i32,
+            voxels.len()
+        )
+    }
+    voxels.get_unchecked(stride as usize)
+}
+
+/// Linear index offsets for moving through the voxel array along the `{N, U, V}` axes of a cube face, along with the
+/// index of that face.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct FaceStrides {
+    /// The offset to the next voxel along the face's normal axis.
     pub n_stride: u32,
+    /// The offset to the next voxel in the U direction.
     pub u_stride: u32,
+    /// The offset to the next voxel in the V direction.
     pub v_stride: u32,
+    /// The (possibly wrapping) offset from a voxel to the adjacent voxel that shares this face.
     pub visibility_offset: u32,
+    /// The index of this face in the `faces` array passed to the meshing algorithm.
+    pub face_index: usize,
+    /// The index of the face with the opposite normal, i.e. the face of the adjacent voxel.
+    pub opposite_face_index: usize,
 }
 
 pub struct VoxelMerger<T> {
@@ -53,48 +93,67 @@ pub struct VoxelMerger<T> {
 impl<T> MergeStrategy for VoxelMerger<T> {
     type Voxel = T;
 
-    unsafe fn find_quad<C>(
+    unsafe fn find_quad<V, C>(
         min_index: u32,
         max_width: u32,
         max_height: u32,
         face_strides: &FaceStrides,
-        voxels: &[T],
+        voxels: &V,
         visited: &[bool],
         ctx: &C,
     ) -> (u32, u32)
     where
+        V: VoxelSource<Voxel = T> + ?Sized,
         C: MergeVoxelContext<Self::Voxel>,
     {
+        let quad_voxel = get_voxel_unchecked(voxels, min_index);
+        if has_face_inset(quad_voxel, ctx) {
+            return (1, 1);
+        }
+
         // Greedily search for the biggest visible quad where all merge values are the same.
-        let quad_value = ctx.merge_value(voxels.get_unchecked(min_index as usize));
-        let quad_neighbour_value = ctx.merge_value_facing_neighbour(
-            voxels.get_unchecked(min_index.wrapping_add(face_strides.visibility_offset) as usize),
-        );
+        let quad_value = ctx.merge_value(quad_voxel);
+        let quad_neighbour_value = ctx.merge_value_facing_neighbour(get_voxel_unchecked(
+            voxels,
+            min_index.wrapping_add(face_strides.visibility_offset),
+        ));
+        let quad_key = FaceKey::new(quad_voxel, face_strides.face_index, ctx);
 
         // Start by finding the widest quad in the U direction.
         let mut row_start_stride = min_index;
         let quad_width = Self::get_row_width(
             voxels,
             visited,
+            quad_voxel,
             &quad_value,
             &quad_neighbour_value,
-            face_strides.visibility_offset,
+            &quad_key,
+            face_strides,
             row_start_stride,
             face_strides.u_stride,
             max_width,
             ctx,
         );
 
-        // Now see how tall we can make the quad in the V direction without changing the width.
+        // Now see how tall we can make the quad in the V direction without changing the width. Each row is checked
+        // from its start up to `quad_width`, and the first row that falls short ends the quad. The faces of the rows
+        // that fit are read once, but the faces of the failing row are thrown away and read again by the quads that
+        // cover them later, which is at most `quad_width` extra reads per quad.
+        //
+        // We don't cache which columns failed, because nothing could use it: no row after the first failure is ever
+        // checked for this quad, and whether a column fails depends on this quad's merge value and face key, so the
+        // next quad can't reuse it either.
         row_start_stride += face_strides.v_stride;
         let mut quad_height = 1;
         while quad_height < max_height {
             let row_width = Self::get_row_width(
                 voxels,
                 visited,
+                quad_voxel,
                 &quad_value,
                 &quad_neighbour_value,
-                face_strides.visibility_offset,
+                &quad_key,
+                face_strides,
                 row_start_stride,
                 face_strides.u_stride,
                 quad_width,
@@ -111,29 +170,123 @@ impl<T> MergeStrategy for VoxelMerger<T> {
     }
 }
 
+/// A [`MergeStrategy`] that grows quads in width and height alternately, one row or column at a time.
+///
+/// [`VoxelMerger`] first finds the widest possible row and then grows it as tall as possible, which minimizes the
+/// number of quads in many cases but can produce long, thin quads. This strategy tends to produce more square quads, which
+/// can reduce overdraw and shading cost per fragment on some hardware. Use it with
+/// [`greedy_quads_with_merge_strategy`](crate::greedy_quads_with_merge_strategy).
+pub struct SquarishVoxelMerger<T> {
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T> MergeStrategy for SquarishVoxelMerger<T> {
+    type Voxel = T;
+
+    unsafe fn find_quad<V, C>(
+        min_index: u32,
+        max_width: u32,
+        max_height: u32,
+        face_strides: &FaceStrides,
+        voxels: &V,
+        visited: &[bool],
+        ctx: &C,
+    ) -> (u32, u32)
+    where
+        V: VoxelSource<Voxel = T> + ?Sized,
+        C: MergeVoxelContext<Self::Voxel>,
+    {
+        let quad_voxel = get_voxel_unchecked(voxels, min_index);
+        if has_face_inset(quad_voxel, ctx) {
+            return (1, 1);
+        }
+
+        let quad_value = ctx.merge_value(quad_voxel);
+        let quad_neighbour_value = ctx.merge_value_facing_neighbour(get_voxel_unchecked(
+            voxels,
+            min_index.wrapping_add(face_strides.visibility_offset),
+        ));
+        let quad_key = FaceKey::new(quad_voxel, face_strides.face_index, ctx);
+
+        let mut quad_width = 1;
+        let mut quad_height = 1;
+        let mut can_grow_width = true;
+        let mut can_grow_height = true;
+        while can_grow_width || can_grow_height {
+            if can_grow_width {
+                // Try to add a column in the U direction.
+                can_grow_width = quad_width < max_width
+                    && VoxelMerger::get_row_width(
+                        voxels,
+                        visited,
+                        quad_voxel,
+                        &quad_value,
+                        &quad_neighbour_value,
+                        &quad_key,
+                        face_strides,
+                        min_index.wrapping_add(quad_width * face_strides.u_stride),
+                        face_strides.v_stride,
+                        quad_height,
+                        ctx,
+                    ) == quad_height;
+                if can_grow_width {
+                    quad_width += 1;
+                }
+            }
+            if can_grow_height {
+                // Try to add a row in the V direction.
+                can_grow_height = quad_height < max_height
+                    && VoxelMerger::get_row_width(
+                        voxels,
+                        visited,
+                        quad_voxel,
+                        &quad_value,
+                        &quad_neighbour_value,
+                        &quad_key,
+                        face_strides,
+                        min_index.wrapping_add(quad_height * face_strides.v_stride),
+                        face_strides.u_stride,
+                        quad_width,
+                        ctx,
+                    ) == quad_width;
+                if can_grow_height {
+                    quad_height += 1;
+                }
+            }
+        }
+
+        (quad_width, quad_height)
+    }
+}
+
 impl<T> VoxelMerger<T> {
-    unsafe fn get_row_width<C>(
-        voxels: &[T],
+    unsafe fn get_row_width<V, C>(
+        voxels: &V,
         visited: &[bool],
+        quad_voxel: &T,
         quad_merge_voxel_value: &C::MergeValue,
         quad_merge_voxel_value_facing_neighbour: &C::MergeValueFacingNeighbour,
-        visibility_offset: u32,
+        quad_key: &FaceKey,
+        face_strides: &FaceStrides,
         start_stride: u32,
         delta_stride: u32,
         max_width: u32,
         ctx: &C,
     ) -> u32
     where
+        V: VoxelSource<Voxel = T> + ?Sized,
         C: MergeVoxelContext<T>,
     {
         let mut quad_width = 0;
         let mut row_stride = start_stride;
         while quad_width < max_width {
-            let voxel = voxels.get_unchecked(row_stride as usize);
-            let neighbour =
-                voxels.get_unchecked(row_stride.wrapping_add(visibility_offset) as usize);
+            let voxel = get_voxel_unchecked(voxels, row_stride);
+            let neighbour = get_voxel_unchecked(
+                voxels,
+                row_stride.wrapping_add(face_strides.visibility_offset),
+            );
 
-            if !face_needs_mesh(voxel, row_stride, visibility_offset, voxels, visited, ctx) {
+            if !face_needs_mesh(voxel, row_stride, face_strides, voxels, visited, ctx) {
                 break;
             }
 
@@ -146,6 +299,16 @@ impl<T> VoxelMerger<T> {
                 break;
             }
 
+            if FaceKey::new(voxel, face_strides.face_index, ctx) != *quad_key
+                || !ctx.can_merge_pair(quad_voxel, voxel, face_strides.face_index)
+            {
+                break;
+            }
+
+            if has_face_inset(voxel, ctx) {
+                break;
+            }
+
             quad_width += 1;
             row_stride += delta_stride;
         }
@@ -153,3 +316,60 @@ impl<T> VoxelMerger<T> {
         quad_width
     }
 }
+
+/// Voxels with inset faces don't fill their cell, so they can't be merged with any other voxel.
+#[inline]
+fn has_face_inset<T, C>(voxel: &T, ctx: &C) -> bool
+where
+    C: VoxelContext<T>,
+{
+    (0..6).any(|face_index| ctx.face_inset(voxel, face_index) != 0.0)
+}
+
+/// Returns `true` iff the face of `voxel` with the normal of `faces[face_index]`, whose neighbor along the normal is
+/// `neighbour`, may join a quad whose minimum is the same face of `quad_voxel` with the neighbor `quad_neighbour`, by the
+/// same rules as [`VoxelMerger`]. Visibility is not checked.
+pub(crate) fn faces_can_merge<T, C>(
+    quad_voxel: &T,
+    quad_neighbour: &T,
+    voxel: &T,
+    neighbour: &T,
+    face_index: usize,
+    ctx: &C,
+) -> bool
+where
+    C: MergeVoxelContext<T>,
+{
+    ctx.merge_value(voxel).eq(&ctx.merge_value(quad_voxel))
+        && ctx
+            .merge_value_facing_neighbour(neighbour)
+            .eq(&ctx.merge_value_facing_neighbour(quad_neighbour))
+        && FaceKey::new(voxel, face_index, ctx) == FaceKey::new(quad_voxel, face_index, ctx)
+        && !has_face_inset(voxel, ctx)
+        && !has_face_inset(quad_voxel, ctx)
+        && ctx.can_merge_pair(quad_voxel, voxel, face_index)
+}
+
+/// The parts of a face besides its merge values that must be the same for all faces of a quad: faces with different
+/// texture rotations can't share UVs, faces with different layers can't share layer quads, and faces with different
+/// materials are drawn separately.
+#[derive(PartialEq)]
+struct FaceKey<'a> {
+    texture_rotation: u8,
+    face_layers: &'a [LayerId],
+    material_id: MaterialId,
+}
+
+impl<'a> FaceKey<'a> {
+    #[inline]
+    fn new<T, C>(voxel: &'a T, face_index: usize, ctx: &'a C) -> Self
+    where
+        C: VoxelContext<T>,
+    {
+        Self {
+            texture_rotation: ctx.texture_rotation(voxel, face_index) % 4,
+            face_layers: ctx.face_layers(voxel, face_index),
+            material_id: ctx.material_id(voxel, face_index),
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use super::{
+    greedy_quads_for_face, FaceStrides, GreedyQuadsBuffer, GreedyQuadsConfig, GreedyScratch,
+    MergeStrategy, MeshStats, OnQuad, VoxelMerger,
+};
+use crate::bounds::{assert_extent_in_shape, assert_voxels_fit_shape};
+use crate::{
+    geometry::opposite_face_indices, MergeVoxelContext, OrientedBlockFace, QuadBuffer,
+    UnorientedQuad, VoxelContext, VoxelSource, VoxelVisibility,
+};
+
+use ilattice::glam::UVec3;
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// A callback for every generated quad, along with the index of its face and the voxel at its minimum, which returns
+/// whether to add the quad to the output.
+type OnFaceQuad<'a, T> = dyn FnMut(usize, &UnorientedQuad, &T) -> bool + 'a;
+
+/// The setup work of [`greedy_quads`](crate::greedy_quads) for one combination of chunk shape, faces, and extent, done
+/// once so it can be reused for every chunk with that layout.
+///
+/// When remeshing many chunks of the same shape, e.g. a whole region after a world edit, this avoids recomputing the
+/// interior extent, the per-face strides, and the opposite face lookup for every chunk.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// let mut chunks = vec![[BoolVoxel(false); ChunkShape::SIZE as usize]; 4];
+/// for (i, chunk) in chunks.iter_mut().enumerate() {
+///     for x in 1..=i as u32 + 1 {
+///         chunk[ChunkShape::linearize([x, 1, 1]) as usize] = BoolVoxel(true);
+///     }
+/// }
+///
+/// let plan = GreedyMeshPlan::new(&ChunkShape {}, &RIGHT_HANDED_Y_UP_CONFIG.faces, [0; 3], [17; 3]);
+/// let mut buffer = GreedyQuadsBuffer::new(ChunkShape::USIZE);
+/// for chunk in chunks.iter() {
+///     plan.mesh(chunk, &mut buffer, &DefaultVoxelContext);
+///     // A row of blocks always merges into one quad per face.
+///     assert_eq!(buffer.quads.num_quads(), 6);
+/// }
+/// ```
+pub struct GreedyMeshPlan<'a, S> {
+    voxels_shape: &'a S,
+    pub(super) interior: Extent<UVec3>,
+    pub(super) faces: [FacePlan; 6],
+}
+
+/// Everything needed to mesh one face direction that doesn't depend on the voxel values.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct FacePlan {
+    pub face: OrientedBlockFace,
+    pub strides: FaceStrides,
+}
+
+impl<'a, S> GreedyMeshPlan<'a, S>
+where
+    S: Shape<3, Coord = u32>,
+{
+    /// Precomputes the meshing setup for voxel arrays of `voxels_shape`, meshing the interior of `[min, max]`.
+    ///
+    /// Panics if `[min, max]` is not contained in `voxels_shape`.
+    pub fn new(
+        voxels_shape: &'a S,
+        faces: &[OrientedBlockFace; 6],
+        min: [u32; 3],
+        max: [u32; 3],
+    ) -> Self {
+        assert_extent_in_shape(voxels_shape, min, max);
+
+        let min = UVec3::from(min).as_ivec3();
+        let max = UVec3::from(max).as_ivec3();
+        let extent = Extent::from_min_and_max(min, max);
+        let interior = extent.padded(-1); // Avoid accessing out of bounds with a 3x3x3 kernel.
+        let interior =
+            Extent::from_min_and_shape(interior.minimum.as_uvec3(), interior.shape.as_uvec3());
+
+        let opposite_faces = opposite_face_indices(faces);
+        let faces = [0, 1, 2, 3, 4, 5].map(|face_index| {
+            FacePlan::new(
+                voxels_shape,
+                faces[face_index],
+                face_index,
+                opposite_faces[face_index],
+            )
+        });
+
+        Self {
+            voxels_shape,
+            interior,
+            faces,
+        }
+    }
+
+    /// Same as [`greedy_quads`](crate::greedy_quads) with the shape, faces, and extent of this plan.
+    pub fn mesh<V, C>(&self, voxels: &V, output: &mut GreedyQuadsBuffer, ctx: &C)
+    where
+        V: VoxelSource + ?Sized,
+        C: MergeVoxelContext<V::Voxel>,
+    {
+        self.mesh_with_merge_strategy::<_, VoxelMerger<V::Voxel>, _>(
+            voxels,
+            &GreedyQuadsConfig::default(),
+            output,
+            ctx,
+        )
+    }
+
+    /// Same as [`greedy_quads_with_merge_strategy`](crate::greedy_quads_with_merge_strategy) with the shape, faces,
+    /// and extent of this plan.
+    pub fn mesh_with_merge_strategy<V, Merger, C>(
+        &self,
+        voxels: &V,
+        config: &GreedyQuadsConfig,
+        output: &mut GreedyQuadsBuffer,
+        ctx: &C,
+    ) where
+        V: VoxelSource + ?Sized,
+        Merger: MergeStrategy<Voxel = V::Voxel>,
+        C: MergeVoxelContext<V::Voxel>,
+    {
+        self.mesh_skipping::<_, Merger, _>(voxels, config, [None; 6], output, None, ctx);
+    }
+
+    /// Same as [`GreedyMeshPlan::mesh_with_merge_strategy`], but voxels that are set in `skip[i]` are treated as
+    /// already meshed in face group `i`, so no quad in that group covers them. Skipping voxels without a visible face
+    /// in that group leaves the output unchanged.
+    ///
+    /// If `on_quad` is given, it is called with the face index of every quad, the quad, and the voxel at its minimum, in
+    /// the order the quads are generated. Quads for which it returns `false` are left out of `output`, e.g. to write
+    /// them somewhere else.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn mesh_skipping<V, Merger, C>(
+        &self,
+        voxels: &V,
+        config: &GreedyQuadsConfig,
+        skip: [Option<&[bool]>; 6],
+        output: &mut GreedyQuadsBuffer,
+        on_quad: Option<&mut OnFaceQuad<V::Voxel>>,
+        ctx: &C,
+    ) where
+        V: VoxelSource + ?Sized,
+        Merger: MergeStrategy<Voxel = V::Voxel>,
+        C: MergeVoxelContext<V::Voxel>,
+    {
+        assert!(
+            config.max_merge.min_element() >= 1,
+            "max_merge={:?} must be at least 1 in each dimension",
+            config.max_merge
+        );
+        assert!(
+            config.merge_grid_align != Some(0),
+            "merge_grid_align must be at least 1"
+        );
+
+        let GreedyQuadsBuffer {
+            quads,
+            stats,
+            scratch,
+        } = output;
+        *stats = self.mesh_into::<_, Merger, _>(voxels, config, skip, scratch, quads, on_quad, ctx);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn mesh_into<V, Merger, C>(
+        &self,
+        voxels: &V,
+        config: &GreedyQuadsConfig,
+        skip: [Option<&[bool]>; 6],
+        scratch: &mut GreedyScratch,
+        output: &mut QuadBuffer,
+        mut on_quad: Option<&mut OnFaceQuad<V::Voxel>>,
+        ctx: &C,
+    ) -> MeshStats
+    where
+        V: VoxelSource + ?Sized,
+        Merger: MergeStrategy<Voxel = V::Voxel>,
+        C: MergeVoxelContext<V::Voxel>,
+    {
+        assert_voxels_fit_shape(voxels, self.voxels_shape);
+
+        output.reset();
+        scratch.reset(voxels.len());
+        let GreedyScratch { visited } = scratch;
+        let QuadBuffer { groups } = output;
+
+        let interior = config.boundary_ownership.owned_interior(self.interior);
+        let mut stats = MeshStats::default();
+
+        // Chunks of a single kind of voxel are common in generated worlds. If every voxel is empty, nothing is visible,
+        // and if every voxel is opaque, only the faces on the boundary of the interior can be visible.
+        let uniform_visibility = self.uniform_visibility(voxels, ctx);
+        if uniform_visibility == Some(VoxelVisibility::Empty) {
+            return stats;
+        }
+
+        let faces = groups.iter_mut().zip(self.faces.iter()).zip(skip);
+        for (face_index, ((group, face_plan), skip)) in faces.enumerate() {
+            let mut on_face_quad = on_quad.as_deref_mut().map(|on_quad| {
+                move |quad: &UnorientedQuad, voxel: &V::Voxel| on_quad(face_index, quad, voxel)
+            });
+            let face_interior = match uniform_visibility {
+                Some(_) => face_plan
+                    .exposed_slice(self.interior)
+                    .intersection(&interior),
+                None => interior,
+            };
+            greedy_quads_for_face::<_, _, Merger, _>(
+                voxels,
+                self.voxels_shape,
+                face_interior,
+                face_plan,
+                config,
+                skip,
+                visited,
+                group,
+                &mut stats,
+                on_face_quad
+                    .as_mut()
+                    .map(|on_quad| on_quad as &mut OnQuad<V::Voxel>),
+                ctx,
+            );
+        }
+        stats
+    }
+
+    /// Returns the visibility of every face of every voxel on the interior if they are all [`VoxelVisibility::Empty`] or
+    /// all [`VoxelVisibility::Opaque`]. The corners and the center are checked first, so most chunks that aren't uniform
+    /// are rejected without scanning every voxel.
+    fn uniform_visibility<V, C>(&self, voxels: &V, ctx: &C) -> Option<VoxelVisibility>
+    where
+        V: VoxelSource + ?Sized,
+        C: VoxelContext<V::Voxel>,
+    {
+        if self.interior.is_empty() {
+            return Some(VoxelVisibility::Empty);
+        }
+
+        let visibility_at = |p: UVec3| {
+            let index = self.voxels_shape.linearize(p.to_array());
+            let voxel = unsafe { voxels.get_unchecked(index as usize) };
+            let visibility = ctx.get_visibility_for_face(voxel, 0);
+            let is_uniform = visibility != VoxelVisibility::Translucent
+                && (1..6)
+                    .all(|face_index| ctx.get_visibility_for_face(voxel, face_index) == visibility);
+            is_uniform.then_some(visibility)
+        };
+
+        let visibility = visibility_at(self.interior.minimum)?;
+        let corners =
+            Extent::from_min_and_lub(self.interior.minimum, self.interior.max()).corners3();
+        let center = self.interior.minimum + self.interior.shape / 2;
+        let is_uniform = corners
+            .into_iter()
+            .chain([center])
+            .all(|p| visibility_at(p) == Some(visibility))
+            && self
+                .interior
+                .iter3()
+                .all(|p| visibility_at(p) == Some(visibility));
+        is_uniform.then_some(visibility)
+    }
+}
+
+impl FacePlan {
+    fn new<S>(
+        voxels_shape: &S,
+        face: OrientedBlockFace,
+        face_index: usize,
+        opposite_face_index: usize,
+    ) -> Self
+    where
+        S: Shape<3, Coord = u32>,
+    {
+        let n_stride = voxels_shape.linearize(face.n.to_array());
+        let u_stride = voxels_shape.linearize(face.u.to_array());
+        let v_stride = voxels_shape.linearize(face.v.to_array());
+        let strides = FaceStrides {
+            n_stride,
+            u_stride,
+            v_stride,
+            // The offset to the voxel sharing this cube face.
+            visibility_offset: if face.n_sign > 0 {
+                n_stride
+            } else {
+                0u32.wrapping_sub(n_stride)
+            },
+            face_index,
+            opposite_face_index,
+        };
+
+        Self { face, strides }
+    }
+
+    /// The number of slices of `interior` along this face's normal.
+    pub(super) fn num_slices(&self, interior: Extent<UVec3>) -> u32 {
+        interior.shape.to_array()[self.face.permutation.axes()[0].index()]
+    }
+
+    /// The layer of `interior` whose faces in this direction border the voxels outside of `interior`.
+    fn exposed_slice(&self, interior: Extent<UVec3>) -> Extent<UVec3> {
+        let num_slices = self.num_slices(interior);
+        let slice_index = if self.face.n_sign > 0 {
+            num_slices.saturating_sub(1)
+        } else {
+            0
+        };
+        self.slice_extent(interior, slice_index)
+    }
+
+    /// The layer of `interior` at `slice_index` along this face's normal.
+    pub(super) fn slice_extent(&self, interior: Extent<UVec3>, slice_index: u32) -> Extent<UVec3> {
+        let [n_axis, u_axis, v_axis] = self.face.permutation.axes();
+        let interior_shape = interior.shape.to_array();
+        let mut slice_shape = [0; 3];
+        slice_shape[n_axis.index()] = 1;
+        slice_shape[u_axis.index()] = interior_shape[u_axis.index()];
+        slice_shape[v_axis.index()] = interior_shape[v_axis.index()];
+        Extent::from_min_and_shape(
+            interior.minimum + slice_index * self.face.n,
+            UVec3::from(slice_shape),
+        )
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{
+    bounds::assert_in_bounds, greedy_quads, shell::interior_extent, GreedyQuadsBuffer,
+    MergeVoxelContext, MeshStats, OrientedBlockFace, PaddedChunkShape, UnorientedQuad, VoxelSource,
+};
+
+use ilattice::glam::UVec3;
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// Same as [`greedy_quads`], but the quads are mirror-symmetric whenever the voxels are: if the voxels on the interior
+/// of `[min, max]` are symmetric under reflection through the center of the interior along an axis, so are the quads.
+///
+/// [`greedy_quads`] always scans and grows quads towards the maximum of each axis, so the two halves of a symmetric
+/// structure get different quads, which can show up as asymmetric shading, e.g. with ambient occlusion. Here, the
+/// interior is split into halves at its center on every axis (with a 1-voxel middle layer for odd sizes), and each half
+/// is scanned from the center outwards, so reflecting one half reproduces the quads of the other. Quads never cross the
+/// center of an axis, so a large flat face that [`greedy_quads`] covers with 1 quad is split into 4, or 9 for odd sizes.
+///
+/// The quads are grouped as usual, so `output.quads.groups[i]` has the quads for `faces[i]`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<6, 3, 3>;
+///
+/// // A row of 4 voxels along X.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for x in 1..=4 {
+///     voxels[ChunkShape::linearize([x, 1, 1]) as usize] = BoolVoxel(true);
+/// }
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_symmetric(&voxels, &ChunkShape {}, [0; 3], [5, 2, 2], faces, &mut buffer, &DefaultVoxelContext);
+///
+/// // The long sides of the row are split into 2 quads at its center, one for each half.
+/// assert_eq!(buffer.quads.num_quads(), 2 + 4 * 2);
+/// let top = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+/// let mut top_minimums: Vec<_> = buffer.quads.groups[top].iter().map(|quad| quad.minimum).collect();
+/// top_minimums.sort();
+/// assert_eq!(top_minimums, [[1, 1, 1], [3, 1, 1]]);
+/// ```
+pub fn greedy_quads_symmetric<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    output.quads.reset();
+    output.stats = MeshStats::default();
+
+    let interior = interior_extent(min, max);
+    let ranges = [0, 1, 2].map(|axis| {
+        half_ranges(
+            interior.minimum.to_array()[axis],
+            interior.shape.to_array()[axis],
+        )
+    });
+
+    let mut scratch = GreedyQuadsBuffer::new(0);
+    for x in ranges[0].iter() {
+        for y in ranges[1].iter() {
+            for z in ranges[2].iter() {
+                let region = [x, y, z];
+                mesh_region(
+                    voxels,
+                    voxels_shape,
+                    region,
+                    faces,
+                    &mut scratch,
+                    output,
+                    ctx,
+                );
+            }
+        }
+    }
+}
+
+/// The voxels `start..start + len` along one axis of a region, and whether they are scanned in the negative direction.
+#[derive(Clone, Copy)]
+struct HalfRange {
+    start: u32,
+    len: u32,
+    flip: bool,
+}
+
+impl HalfRange {
+    /// Maps a coordinate in the padded copy of the region to a coordinate in the original voxels.
+    #[inline]
+    fn to_original(self, c: u32) -> u32 {
+        if self.flip {
+            self.start + self.len - c
+        } else {
+            self.start - 1 + c
+        }
+    }
+}
+
+/// Splits the `len` voxels starting at `start` into a lower half that is scanned downwards from the center, a middle
+/// voxel if `len` is odd, and an upper half that is scanned upwards from the center.
+fn half_ranges(start: u32, len: u32) -> Vec<HalfRange> {
+    let half = len / 2;
+    let mut ranges = Vec::with_capacity(3);
+    if half > 0 {
+        ranges.push(HalfRange {
+            start,
+            len: half,
+            flip: true,
+        });
+    }
+    if len % 2 == 1 {
+        ranges.push(HalfRange {
+            start: start + half,
+            len: 1,
+            flip: false,
+        });
+    }
+    if half > 0 {
+        ranges.push(HalfRange {
+            start: start + len - half,
+            len: half,
+            flip: false,
+        });
+    }
+    ranges
+}
+
+/// Meshes a padded copy of `region` where every flipped axis is reversed, and appends the quads, mapped back to the
+/// original coordinates, to `output`.
+fn mesh_region<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    region: [&HalfRange; 3],
+    faces: &[OrientedBlockFace; 6],
+    scratch: &mut GreedyQuadsBuffer,
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    let copy_shape = PaddedChunkShape::new(region.map(|r| r.len));
+    let mut indices = vec![0; copy_shape.usize()];
+    let copy_extent = Extent::from_min_and_shape(UVec3::ZERO, UVec3::from(copy_shape.as_array()));
+    for q in copy_extent.iter3() {
+        let q = q.to_array();
+        let p = [0, 1, 2].map(|axis| region[axis].to_original(q[axis]));
+        indices[copy_shape.linearize(q) as usize] = voxels_shape.linearize(p);
+    }
+    let copy = IndexedVoxels { voxels, indices };
+
+    // A face whose normal axis is reversed points the other way in the copy. The face index stays the same, so the
+    // context still sees the original face.
+    let copy_faces = faces.map(|face| {
+        if region[face.permutation.axes()[0].index()].flip {
+            OrientedBlockFace {
+                n_sign: -face.n_sign,
+                ..face
+            }
+        } else {
+            face
+        }
+    });
+    greedy_quads(
+        &copy,
+        &copy_shape,
+        copy_shape.min(),
+        copy_shape.max(),
+        &copy_faces,
+        scratch,
+        ctx,
+    );
+    output.stats.rejected_quads += scratch.stats.rejected_quads;
+
+    for ((group, copy_group), face) in output
+        .quads
+        .groups
+        .iter_mut()
+        .zip(scratch.quads.groups.iter())
+        .zip(faces.iter())
+    {
+        let [_, u_axis, v_axis] = face.permutation.axes();
+        for quad in copy_group.iter() {
+            let mut size = [1; 3];
+            size[u_axis.index()] = quad.width;
+            size[v_axis.index()] = quad.height;
+            let minimum = [0, 1, 2].map(|axis| {
+                let range = region[axis];
+                if range.flip {
+                    range.to_original(quad.minimum[axis] + size[axis] - 1)
+                } else {
+                    range.to_original(quad.minimum[axis])
+                }
+            });
+            group.push(UnorientedQuad {
+                minimum,
+                width: quad.width,
+                height: quad.height,
+            });
+        }
+    }
+}
+
+/// Reads `voxels[indices[i]]` for index `i`.
+struct IndexedVoxels<'a, V: ?Sized> {
+    voxels: &'a V,
+    indices: Vec<u32>,
+}
+
+impl<V> VoxelSource for IndexedVoxels<'_, V>
+where
+    V: VoxelSource + ?Sized,
+{
+    type Voxel = V::Voxel;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    #[inline]
+    unsafe fn get_unchecked(&self, index: usize) -> &V::Voxel {
+        self.voxels
+            .get_unchecked(*self.indices.get_unchecked(index) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, SampleShape, EMPTY};
+    use crate::{geometry::opposite_face_indices, DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ilattice::glam::Vec3;
+    use ndshape::ConstShape;
+    use std::collections::HashSet;
+
+    #[test]
+    fn symmetric_voxels_get_symmetric_quads() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let p = UVec3::from(shape.delinearize(i)).as_vec3() - 16.0;
+            // An ellipsoid, so each axis has a different profile.
+            samples[i as usize] = BoolVoxel((p / Vec3::new(14.0, 9.0, 5.0)).length() < 1.0, false);
+        }
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        // The interior is centered on the ellipsoid.
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_symmetric(
+            &samples,
+            &shape,
+            [0; 3],
+            [32; 3],
+            faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let quads = quad_set(&buffer);
+
+        // The same faces are covered as with the usual greedy meshing.
+        let mut greedy = GreedyQuadsBuffer::new(samples.len());
+        crate::greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [32; 3],
+            faces,
+            &mut greedy,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(
+            unit_faces(&quads, faces),
+            unit_faces(&quad_set(&greedy), faces)
+        );
+
+        let opposite = opposite_face_indices(faces);
+        for axis in 0..3 {
+            let mirrored: HashSet<_> = quads
+                .iter()
+                .map(|&(face_index, quad)| {
+                    let face = &faces[face_index];
+                    let size = quad_size(face, &quad);
+                    let mut minimum = quad.minimum;
+                    minimum[axis] = 32 - (minimum[axis] + size[axis] - 1);
+                    let face_index = if face.permutation.axes()[0].index() == axis {
+                        opposite[face_index]
+                    } else {
+                        face_index
+                    };
+                    let [_, u_axis, v_axis] = faces[face_index].permutation.axes();
+                    let quad = UnorientedQuad {
+                        minimum,
+                        width: size[u_axis.index()],
+                        height: size[v_axis.index()],
+                    };
+                    (face_index, quad)
+                })
+                .collect();
+            assert_eq!(mirrored, quads, "not symmetric along axis {axis}");
+        }
+    }
+
+    fn quad_set(buffer: &GreedyQuadsBuffer) -> HashSet<(usize, UnorientedQuad)> {
+        let quads: HashSet<_> = buffer
+            .quads
+            .groups
+            .iter()
+            .enumerate()
+            .flat_map(|(face_index, group)| group.iter().map(move |&quad| (face_index, quad)))
+            .collect();
+        assert_eq!(quads.len(), buffer.quads.num_quads());
+        quads
+    }
+
+    fn unit_faces(
+        quads: &HashSet<(usize, UnorientedQuad)>,
+        faces: &[OrientedBlockFace; 6],
+    ) -> HashSet<(usize, [u32; 3])> {
+        let mut unit_faces = HashSet::new();
+        for &(face_index, quad) in quads {
+            let face = &faces[face_index];
+            let size = UVec3::from(quad_size(face, &quad));
+            for offset in Extent::from_min_and_shape(UVec3::ZERO, size).iter3() {
+                let p = UVec3::from(quad.minimum) + offset;
+                assert!(unit_faces.insert((face_index, p.to_array())));
+            }
+        }
+        unit_faces
+    }
+
+    fn quad_size(face: &OrientedBlockFace, quad: &UnorientedQuad) -> [u32; 3] {
+        let [_, u_axis, v_axis] = face.permutation.axes();
+        let mut size = [1; 3];
+        size[u_axis.index()] = quad.width;
+        size[v_axis.index()] = quad.height;
+        size
+    }
+}
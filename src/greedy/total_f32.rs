@@ -0,0 +1,132 @@
+use std::hash::{Hash, Hasher};
+
+/// An `f32` that implements [`Eq`] and [`Hash`] by comparing bit patterns, so it can be used as a
+/// [`MergeVoxel::MergeValue`](crate::MergeVoxel::MergeValue).
+///
+/// Two values are equal iff they have the same bits. This means that a NaN is equal to any NaN with the same payload,
+/// so voxels with NaN merge values still merge with each other, but `0.0` and `-0.0` are not equal, so those voxels
+/// don't merge.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// assert_eq!(TotalF32(f32::NAN), TotalF32(f32::NAN));
+/// assert_ne!(TotalF32(0.0), TotalF32(-0.0));
+/// assert_eq!(TotalF32(0.5).0, 0.5);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TotalF32(pub f32);
+
+impl PartialEq for TotalF32 {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for TotalF32 {}
+
+impl Hash for TotalF32 {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl From<f32> for TotalF32 {
+    #[inline]
+    fn from(value: f32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<TotalF32> for f32 {
+    #[inline]
+    fn from(value: TotalF32) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultVoxelContext;
+    use crate::{
+        greedy_quads, GreedyQuadsBuffer, MergeVoxel, Voxel, VoxelVisibility,
+        RIGHT_HANDED_Y_UP_CONFIG,
+    };
+    use ndshape::{ConstShape, ConstShape3u32, Shape};
+
+    #[test]
+    fn float_merge_values_merge_by_bits() {
+        let shape = SampleShape {};
+        for (value, expected_quads) in [(0.5, 6), (f32::NAN, 6)] {
+            let mut samples = [EMPTY; SampleShape::SIZE as usize];
+            for i in 0..SampleShape::SIZE {
+                let p = shape.delinearize(i);
+                if p.iter().all(|&c| (1..=4).contains(&c)) {
+                    samples[i as usize] = FloatVoxel(Some(value));
+                }
+            }
+
+            let mut buffer = GreedyQuadsBuffer::new(samples.len());
+            greedy_quads(
+                &samples,
+                &shape,
+                [0; 3],
+                [5; 3],
+                &RIGHT_HANDED_Y_UP_CONFIG.faces,
+                &mut buffer,
+                &DefaultVoxelContext,
+            );
+            assert_eq!(buffer.quads.num_quads(), expected_quads);
+        }
+
+        // Signed zeros are different values.
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1, 1, 1]) as usize] = FloatVoxel(Some(0.0));
+        samples[shape.linearize([2, 1, 1]) as usize] = FloatVoxel(Some(-0.0));
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [5; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(buffer.quads.num_quads(), 10);
+    }
+
+    type SampleShape = ConstShape3u32<6, 6, 6>;
+
+    #[derive(Clone, Copy)]
+    struct FloatVoxel(Option<f32>);
+
+    const EMPTY: FloatVoxel = FloatVoxel(None);
+
+    impl Voxel for FloatVoxel {
+        fn get_visibility(&self) -> VoxelVisibility {
+            if self.0.is_some() {
+                VoxelVisibility::Opaque
+            } else {
+                VoxelVisibility::Empty
+            }
+        }
+    }
+
+    impl MergeVoxel for FloatVoxel {
+        type MergeValue = TotalF32;
+        type MergeValueFacingNeighbour = bool;
+
+        fn merge_value(&self) -> Self::MergeValue {
+            TotalF32(self.0.unwrap_or_default())
+        }
+
+        fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+            true
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    ContextAdapter, ForwardingContext, MergeContextAdapter, MergeVoxelContext, VoxelContext,
+    VoxelVisibility,
+};
+
+/// A context that swaps the roles of [`VoxelVisibility::Empty`] and [`VoxelVisibility::Opaque`] voxels of `inner`, so
+/// the empty regions are meshed as if they were solid. [`VoxelVisibility::Translucent`] voxels are unchanged.
+///
+/// The resulting faces lie on the same surfaces as usual, but they belong to the empty voxels and face into the solid
+/// regions, so they are visible from inside of the solid, e.g. to render tunnels and caves while looking through the
+/// rock around them. Everything other than visibility is read from `inner`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<5, 5, 5>;
+///
+/// // Solid rock with a single empty cell in the middle.
+/// let mut voxels = [BoolVoxel(true); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([2, 2, 2]) as usize] = BoolVoxel(false);
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [4; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &InvertedVoxelContext::new(&DefaultVoxelContext),
+/// );
+/// // The empty cell is meshed like a block, with its faces pointing into the rock.
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// assert!(buffer.quads.groups.iter().flatten().all(|quad| quad.minimum == [2; 3]));
+/// ```
+pub type InvertedVoxelContext<'a, C> = ForwardingContext<Inverted<'a, C>>;
+
+impl<'a, C> InvertedVoxelContext<'a, C> {
+    pub fn new(inner: &'a C) -> Self {
+        ForwardingContext(Inverted { inner })
+    }
+}
+
+/// The [`ContextAdapter`] of an [`InvertedVoxelContext`].
+pub struct Inverted<'a, C> {
+    inner: &'a C,
+}
+
+#[inline]
+fn invert(visibility: VoxelVisibility) -> VoxelVisibility {
+    match visibility {
+        VoxelVisibility::Empty => VoxelVisibility::Opaque,
+        VoxelVisibility::Translucent => VoxelVisibility::Translucent,
+        VoxelVisibility::Opaque => VoxelVisibility::Empty,
+    }
+}
+
+impl<'a, T, C> ContextAdapter<T> for Inverted<'a, C>
+where
+    C: VoxelContext<T>,
+{
+    type Voxel = T;
+    type Inner = C;
+
+    #[inline]
+    fn inner(&self) -> &C {
+        self.inner
+    }
+
+    #[inline]
+    fn voxel<'b>(&'b self, voxel: &'b T) -> Option<&'b T> {
+        Some(voxel)
+    }
+
+    #[inline]
+    fn get_visibility(&self, voxel: &T) -> VoxelVisibility {
+        invert(self.inner.get_visibility(voxel))
+    }
+
+    #[inline]
+    fn get_visibility_for_face(&self, voxel: &T, face_index: usize) -> VoxelVisibility {
+        // Keep voxels that are now empty empty through every face.
+        match self.get_visibility(voxel) {
+            VoxelVisibility::Empty => VoxelVisibility::Empty,
+            _ => invert(self.inner.get_visibility_for_face(voxel, face_index)),
+        }
+    }
+}
+
+impl<'a, T, C> MergeContextAdapter<T> for Inverted<'a, C>
+where
+    C: MergeVoxelContext<T>,
+{
+    type MergeValue = C::MergeValue;
+    type MergeValueFacingNeighbour = C::MergeValueFacingNeighbour;
+
+    #[inline]
+    fn merge_value(&self, voxel: &T) -> Self::MergeValue {
+        self.inner.merge_value(voxel)
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, voxel: &T) -> Self::MergeValueFacingNeighbour {
+        self.inner.merge_value_facing_neighbour(voxel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, SampleShape, EMPTY};
+    use crate::{
+        greedy_quads, DefaultVoxelContext, GreedyQuadsBuffer, SignedAxis, RIGHT_HANDED_Y_UP_CONFIG,
+    };
+    use ilattice::glam::UVec3;
+    use ndshape::{ConstShape, Shape};
+
+    #[test]
+    fn inverted_faces_cover_the_same_surface_from_the_other_side() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let [x, y, z] = shape.delinearize(i);
+            // A solid block with a tunnel along X through its middle.
+            let solid = [x, y, z].iter().all(|c| (4..=28).contains(c));
+            let tunnel = (14..=18).contains(&y) && (14..=18).contains(&z);
+            samples[i as usize] = BoolVoxel(solid && !tunnel, false);
+        }
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut outside = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut outside,
+            &DefaultVoxelContext,
+        );
+        let mut inside = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut inside,
+            &InvertedVoxelContext::new(&DefaultVoxelContext),
+        );
+
+        // Every face of the solid is a face of the adjacent empty voxel with the opposite normal.
+        let mut expected = Vec::new();
+        for (face_index, face) in faces.iter().enumerate() {
+            for quad in outside.quads.groups[face_index].iter() {
+                let direction = face.signed_axis();
+                let minimum = (UVec3::from(quad.minimum).as_ivec3() + direction.get_unit_vector())
+                    .as_uvec3()
+                    .to_array();
+                let opposite = SignedAxis::new(-direction.signum(), direction.unsigned_axis());
+                expected.push((opposite, minimum, quad.width, quad.height));
+            }
+        }
+        let mut actual = Vec::new();
+        for (face_index, face) in faces.iter().enumerate() {
+            for quad in inside.quads.groups[face_index].iter() {
+                actual.push((face.signed_axis(), quad.minimum, quad.width, quad.height));
+            }
+        }
+        let key = |&(direction, minimum, _, _): &(SignedAxis, [u32; 3], u32, u32)| {
+            (direction as u8, minimum)
+        };
+        expected.sort_by_key(key);
+        actual.sort_by_key(key);
+        assert_eq!(actual, expected);
+    }
+}
@@ -79,19 +79,84 @@
 //! assert!(buffer.quads.num_quads() > 0);
 //! ```
 
+mod bevel;
+mod bitmask;
 mod bounds;
+mod bounds_checked;
+mod boxes;
 mod buffer;
+mod cavities;
+mod collector;
+mod custom;
+mod encoding;
+mod filter;
+mod forwarding;
 pub mod geometry;
 mod greedy;
+mod inverted;
+mod manifold;
+mod mesh;
+mod neighbors;
+mod rle;
+mod shape;
+mod shell;
 mod simple;
+mod source;
+mod surface_nets;
+#[cfg(test)]
+mod test_util;
+pub mod testing;
+mod visibility;
+mod writer;
 
+#[cfg(feature = "bevy")]
+mod bevy_mesh;
+#[cfg(feature = "gltf")]
+mod gltf;
+#[cfg(feature = "half")]
+mod mesh_f16;
+#[cfg(feature = "ndarray")]
+mod ndarray_voxels;
+
+pub use bevel::*;
+pub use bitmask::*;
+pub use bounds_checked::*;
+pub use boxes::*;
 pub use buffer::*;
+pub use cavities::*;
+pub use collector::*;
+pub use custom::*;
+pub use encoding::*;
+pub use filter::*;
+pub use forwarding::*;
 #[doc(inline)]
 pub use geometry::*;
 pub use greedy::*;
+pub use inverted::*;
+pub use manifold::*;
+pub use mesh::*;
+pub use neighbors::*;
+pub use rle::*;
+pub use shape::*;
+pub use shell::*;
 pub use simple::*;
+pub use source::*;
+pub use surface_nets::*;
+pub use visibility::*;
+pub use writer::*;
+
+#[cfg(feature = "bevy")]
+pub use bevy_mesh::*;
+#[cfg(feature = "gltf")]
+pub use gltf::*;
+#[cfg(feature = "half")]
+pub use mesh_f16::*;
+#[cfg(feature = "ndarray")]
+pub use ndarray_voxels::*;
 
 pub use ilattice;
+#[cfg(feature = "ndarray")]
+pub use ndarray;
 pub use ndshape;
 
 /// Describes how this voxel influences mesh generation.
@@ -113,8 +178,109 @@ pub trait Voxel {
 
 pub trait VoxelContext<T> {
     fn get_visibility(&self, voxel: &T) -> VoxelVisibility;
+
+    /// The visibility of `voxel` when looking through its face with the normal of `faces[face_index]`, where `faces` is
+    /// the array passed to the meshing algorithm.
+    ///
+    /// This allows for one-sided voxels, like a panel that is opaque from above but empty from below. When deciding
+    /// whether the face between a voxel and its neighbor is meshed, the neighbor is queried with the index of the
+    /// opposite face.
+    ///
+    /// Defaults to [`VoxelContext::get_visibility`]. If `get_visibility` returns [`VoxelVisibility::Empty`], this must
+    /// also return `Empty`.
+    fn get_visibility_for_face(&self, voxel: &T, face_index: usize) -> VoxelVisibility {
+        let _ = face_index;
+        self.get_visibility(voxel)
+    }
+
+    /// How far the face of `voxel` with the normal of `faces[face_index]` is moved into the voxel's cell, in units of
+    /// voxels.
+    ///
+    /// This allows blocks that don't fill their whole cell, like panes and fence posts, to be meshed without a separate
+    /// model pipeline. The faces of the voxel are also trimmed by the insets of the adjacent faces, so a bottom slab is a
+    /// voxel with an inset of 0.5 on its +Y face: the top face sits halfway up the cell and the side faces are half as
+    /// tall. A voxel with any nonzero inset never merges with other voxels in
+    /// [`greedy_quads`](crate::greedy_quads), and the insets are applied to its quads by
+    /// [`QuadBuffer::to_mesh_data_with_voxels`] with [`MeshOptions::insets`]. Since such a voxel doesn't cover the
+    /// faces of its neighbors, it should usually be [`VoxelVisibility::Translucent`].
+    ///
+    /// Defaults to 0.
+    fn face_inset(&self, voxel: &T, face_index: usize) -> f32 {
+        let _ = (voxel, face_index);
+        0.0
+    }
+
+    /// The RGBA vertex color of the face of `voxel` with the normal of `faces[face_index]`, e.g. for a biome tint.
+    ///
+    /// Every vertex of a quad gets the color of the voxel at the quad's minimum, so voxels with different colors should
+    /// have different merge values. Colors are applied by [`QuadBuffer::to_mesh_data_with_voxels`] and
+    /// [`UnitQuadBuffer::to_mesh_data_with_voxels`] with [`MeshOptions::colors`].
+    ///
+    /// Defaults to opaque white.
+    fn color(&self, voxel: &T, face_index: usize) -> [u8; 4] {
+        let _ = (voxel, face_index);
+        [u8::MAX; 4]
+    }
+
+    /// The number of quarter turns to rotate the texture of the face of `voxel` with the normal of `faces[face_index]`,
+    /// e.g. to align wood grain or to break up the tiling of a texture. Only the value modulo 4 is used.
+    ///
+    /// Faces with different rotations are never merged by [`greedy_quads`](crate::greedy_quads), so every voxel of a
+    /// quad has the rotation of the voxel at its minimum. Rotations are applied to the UVs by
+    /// [`QuadBuffer::to_mesh_data_with_voxels`] with [`MeshOptions::texture_rotations`]; see [`rotate_tex_coords`].
+    ///
+    /// Defaults to 0.
+    fn texture_rotation(&self, voxel: &T, face_index: usize) -> u8 {
+        let _ = (voxel, face_index);
+        0
+    }
+
+    /// The overlays drawn on top of the face of `voxel` with the normal of `faces[face_index]`, from the bottom up, like
+    /// a snow layer on grass or moss on stone.
+    ///
+    /// Faces with different layers are never merged by [`greedy_quads`](crate::greedy_quads). Each layer gets its own
+    /// quad, slightly in front of the face, from [`QuadBuffer::to_mesh_data_with_voxels`] with
+    /// [`MeshOptions::layer_offset`].
+    ///
+    /// Defaults to no layers.
+    fn face_layers<'a>(&'a self, voxel: &'a T, face_index: usize) -> &'a [LayerId] {
+        let _ = (voxel, face_index);
+        &[]
+    }
+
+    /// The material of the face of `voxel` with the normal of `faces[face_index]`, e.g. an index into the materials that
+    /// are drawn with separate draw calls.
+    ///
+    /// Faces with different materials are never merged by [`greedy_quads`](crate::greedy_quads), so every voxel of a
+    /// quad has the material of the voxel at its minimum. [`greedy_quads_by_material`] groups the quads by material as
+    /// well as by face.
+    ///
+    /// Defaults to 0.
+    fn material_id(&self, voxel: &T, face_index: usize) -> MaterialId {
+        let _ = (voxel, face_index);
+        0
+    }
+
+    /// The geometry to draw in place of the cube of `voxel`, which is at `position` in the voxel array, for blocks that
+    /// aren't cubes at all, like stairs, torches, and flowers. The position can be used to pick between variants.
+    ///
+    /// When this returns `Some`, [`greedy_mesh_with_custom_geometry`] doesn't mesh any faces of the voxel, and instead
+    /// appends the [`CustomMesh`] moved to the voxel's cell. The faces of neighboring voxels are still culled by the
+    /// voxel's visibility, so it should usually be [`VoxelVisibility::Translucent`] or [`VoxelVisibility::Empty`].
+    ///
+    /// Defaults to no custom geometry.
+    fn custom_geometry<'a>(&'a self, voxel: &'a T, position: [u32; 3]) -> Option<&'a CustomMesh> {
+        let _ = (voxel, position);
+        None
+    }
 }
 
+/// Identifies an overlay returned by [`VoxelContext::face_layers`], e.g. an index into a texture array.
+pub type LayerId = u32;
+
+/// Identifies the material of a face, returned by [`VoxelContext::material_id`].
+pub type MaterialId = u32;
+
 pub trait MergeVoxelContext<T>: VoxelContext<T> {
     type MergeValue: Eq;
     type MergeValueFacingNeighbour: Eq;
@@ -122,6 +288,19 @@ pub trait MergeVoxelContext<T>: VoxelContext<T> {
     fn merge_value(&self, voxel: &T) -> Self::MergeValue;
 
     fn merge_value_facing_neighbour(&self, voxel: &T) -> Self::MergeValueFacingNeighbour;
+
+    /// Returns `true` iff the face of `voxel` with the normal of `faces[face_index]` may join a quad whose minimum is the
+    /// same face of `quad_voxel`. This is checked in addition to the merge values, for policies that depend on both
+    /// voxels, like merging grass of similar biomes and blending their colors at the seams.
+    ///
+    /// Every voxel of a quad is compared with the voxel at its minimum, including that voxel itself, so the predicate
+    /// doesn't need to be transitive, but it must return `true` when both voxels are the same.
+    ///
+    /// Defaults to `true`.
+    fn can_merge_pair(&self, quad_voxel: &T, voxel: &T, face_index: usize) -> bool {
+        let _ = (quad_voxel, voxel, face_index);
+        true
+    }
 }
 
 pub struct DefaultVoxelContext;
@@ -0,0 +1,351 @@
+use crate::{
+    bounds::assert_in_bounds, shell::interior_extent, OrientedBlockFace, UnorientedQuad,
+    VoxelContext, VoxelSource, VoxelVisibility,
+};
+
+use ilattice::glam::{UVec3, Vec3};
+use ndshape::Shape;
+use std::collections::HashMap;
+
+/// Marks a corner or side that doesn't have a vertex yet.
+const NULL_VERTEX: u32 = u32::MAX;
+
+/// Contains the output from [`mesh_manifold_shell`], a closed triangle mesh.
+///
+/// This buffer can be reused between multiple calls of [`mesh_manifold_shell`] in order to avoid reallocations.
+#[derive(Clone, Debug, Default)]
+pub struct ManifoldShellBuffer {
+    /// The position of each vertex, in the same coordinates as [`OrientedBlockFace::quad_mesh_positions`].
+    pub positions: Vec<[f32; 3]>,
+    /// Triangle indices into `positions`, wound the same way as [`OrientedBlockFace::quad_mesh_indices`].
+    pub indices: Vec<u32>,
+}
+
+/// A face of a solid voxel that borders a voxel outside of the solid region.
+struct BoundaryFace {
+    /// The linear index of the solid voxel.
+    owner: u32,
+    /// The lattice points of the corners, in winding order.
+    corners: [UVec3; 4],
+}
+
+/// Meshes the boundary of the solid voxels as a watertight, manifold triangle mesh, e.g. for 3D printing.
+///
+/// A voxel is solid if it is on the interior of `[min, max]` and it isn't [`VoxelVisibility::Empty`]. Everything else,
+/// including the padding, is treated as empty, so the shell is always closed: it has an outer surface around every solid
+/// region and an inner surface around every hollow inside of one. Every face between a solid voxel and an empty one is
+/// meshed, regardless of translucency, and faces are never merged, because merged quads would leave T-junctions.
+///
+/// The mesh shares vertices between faces, and every edge is shared by exactly two triangles, with consistent winding.
+/// Solid voxels that only touch along an edge or at a corner are kept apart: their surfaces get separate vertices at the
+/// same positions, as if the empty voxels between them were connected. In the rare case that two such surfaces would
+/// still share both ends of an edge, the faces on one side of the edge are split at its midpoint.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<4, 4, 3>;
+///
+/// // Two voxels that only touch along an edge.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// voxels[ChunkShape::linearize([2, 2, 1]) as usize] = BoolVoxel(true);
+///
+/// let mut buffer = ManifoldShellBuffer::default();
+/// mesh_manifold_shell(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [3, 3, 2],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     1.0,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// // Two separate cubes, even though two of their vertices are at the same positions.
+/// assert_eq!(buffer.positions.len(), 16);
+/// assert_eq!(buffer.indices.len(), 2 * 6 * 6);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn mesh_manifold_shell<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    voxel_size: f32,
+    output: &mut ManifoldShellBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    output.positions.clear();
+    output.indices.clear();
+
+    let interior = interior_extent(min, max);
+    let is_solid = |p: UVec3| {
+        interior.contains(p) && {
+            let voxel =
+                unsafe { voxels.get_unchecked(voxels_shape.linearize(p.to_array()) as usize) };
+            !matches!(ctx.get_visibility(voxel), VoxelVisibility::Empty)
+        }
+    };
+
+    let mut boundary = Vec::new();
+    for p in interior.iter3() {
+        if !is_solid(p) {
+            continue;
+        }
+        let owner = voxels_shape.linearize(p.to_array());
+        for face in faces.iter() {
+            // The neighbor is at most one voxel outside of the interior, so it can't underflow.
+            let neighbor = (p.as_ivec3() + face.signed_normal()).as_uvec3();
+            if is_solid(neighbor) {
+                continue;
+            }
+            let [c0, c1, c2, c3] = face.quad_corners(&UnorientedQuad {
+                minimum: p.to_array(),
+                width: 1,
+                height: 1,
+            });
+            let corners = if face.corners_are_counter_clockwise() {
+                [c0, c1, c3, c2]
+            } else {
+                [c0, c2, c3, c1]
+            };
+            boundary.push(BoundaryFace { owner, corners });
+        }
+    }
+
+    // Side `k` of face `f` is numbered `4 * f + k` and goes from corner `k` to corner `k + 1`, which is also numbered
+    // `4 * f + k`.
+    let mut sides_on_edge: HashMap<([u32; 3], [u32; 3]), Vec<usize>> = HashMap::new();
+    for (f, face) in boundary.iter().enumerate() {
+        for k in 0..4 {
+            let a = face.corners[k].to_array();
+            let b = face.corners[(k + 1) % 4].to_array();
+            sides_on_edge
+                .entry((a.min(b), a.max(b)))
+                .or_default()
+                .push(4 * f + k);
+        }
+    }
+    let next_corner = |side: usize| side - side % 4 + (side + 1) % 4;
+
+    // Glue the sides around each edge in pairs. An edge has 2 sides, or 4 if two solid voxels only touch diagonally
+    // across it, in which case each pair belongs to one voxel. Corners that are glued together share a vertex.
+    let mut parent: Vec<usize> = (0..4 * boundary.len()).collect();
+    let mut diagonal_edges = Vec::new();
+    for sides in sides_on_edge.values() {
+        let (first, second) = match sides[..] {
+            [s, t] => ((s, t), None),
+            [s, t, u, w] => {
+                let owner = |side: usize| boundary[side / 4].owner;
+                let (pair, others) = if owner(t) == owner(s) {
+                    ((s, t), (u, w))
+                } else if owner(u) == owner(s) {
+                    ((s, u), (t, w))
+                } else {
+                    ((s, w), (t, u))
+                };
+                diagonal_edges.push([pair, others]);
+                (pair, Some(others))
+            }
+            _ => unreachable!("a lattice edge borders an even number of boundary faces, at most 4"),
+        };
+        for (s, t) in std::iter::once(first).chain(second) {
+            // Glued sides run in opposite directions.
+            union(&mut parent, s, next_corner(t));
+            union(&mut parent, next_corner(s), t);
+        }
+    }
+
+    // If both pairs of sides on a diagonal edge end up with the same vertices, the edge would have 4 triangles, so split
+    // the first pair at the midpoint of the edge.
+    let mut split_key = vec![usize::MAX; 4 * boundary.len()];
+    for [(s, t), (u, _)] in diagonal_edges {
+        let mut ends = |side: usize| {
+            let (a, b) = (
+                find(&mut parent, side),
+                find(&mut parent, next_corner(side)),
+            );
+            (a.min(b), a.max(b))
+        };
+        if ends(s) == ends(u) {
+            split_key[s] = s;
+            split_key[t] = s;
+        }
+    }
+
+    let mut corner_vertex = vec![NULL_VERTEX; 4 * boundary.len()];
+    let mut midpoint_vertex = vec![NULL_VERTEX; 4 * boundary.len()];
+    let mut polygon = Vec::with_capacity(8);
+    for (f, face) in boundary.iter().enumerate() {
+        polygon.clear();
+        for k in 0..4 {
+            let side = 4 * f + k;
+            let root = find(&mut parent, side);
+            let position = face.corners[k].as_vec3();
+            polygon.push(vertex(
+                &mut corner_vertex[root],
+                position,
+                voxel_size,
+                output,
+            ));
+            if split_key[side] != usize::MAX {
+                let position = (position + face.corners[(k + 1) % 4].as_vec3()) / 2.0;
+                let key = split_key[side];
+                polygon.push(vertex(
+                    &mut midpoint_vertex[key],
+                    position,
+                    voxel_size,
+                    output,
+                ));
+            }
+        }
+
+        if let [a, b, c, d] = polygon[..] {
+            output.indices.extend_from_slice(&[a, b, c, a, c, d]);
+        } else {
+            let center = face
+                .corners
+                .iter()
+                .map(|c| c.as_vec3())
+                .fold(Vec3::ZERO, |sum, c| sum + c)
+                / 4.0;
+            let center_vertex = output.positions.len() as u32;
+            output.positions.push((center * voxel_size).to_array());
+            for (i, &a) in polygon.iter().enumerate() {
+                output.indices.extend_from_slice(&[
+                    center_vertex,
+                    a,
+                    polygon[(i + 1) % polygon.len()],
+                ]);
+            }
+        }
+    }
+}
+
+/// Returns the vertex stored in `slot`, or adds one at `position` if there is none.
+fn vertex(
+    slot: &mut u32,
+    position: Vec3,
+    voxel_size: f32,
+    output: &mut ManifoldShellBuffer,
+) -> u32 {
+    if *slot == NULL_VERTEX {
+        *slot = output.positions.len() as u32;
+        output.positions.push((position * voxel_size).to_array());
+    }
+    *slot
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    parent[a.max(b)] = a.min(b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, SampleShape, EMPTY};
+    use crate::{DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::ConstShape;
+    use std::collections::HashSet;
+
+    /// Asserts that every edge is used once in each direction, i.e. it is shared by exactly two triangles with
+    /// consistent winding.
+    fn assert_manifold(buffer: &ManifoldShellBuffer) {
+        let mut edges = HashSet::new();
+        for triangle in buffer.indices.chunks(3) {
+            for i in 0..3 {
+                let edge = (triangle[i], triangle[(i + 1) % 3]);
+                assert_ne!(edge.0, edge.1);
+                assert!(
+                    edges.insert(edge),
+                    "edge {edge:?} is used twice in the same direction"
+                );
+            }
+        }
+        for &(a, b) in edges.iter() {
+            assert!(
+                edges.contains(&(b, a)),
+                "edge {:?} has only one triangle",
+                (a, b)
+            );
+        }
+    }
+
+    fn mesh(samples: &[BoolVoxel]) -> ManifoldShellBuffer {
+        let mut buffer = ManifoldShellBuffer::default();
+        mesh_manifold_shell(
+            samples,
+            &SampleShape {},
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            1.0,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        buffer
+    }
+
+    #[test]
+    fn hollow_sphere_has_inner_and_outer_surfaces() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0u32..(SampleShape::SIZE) {
+            let p = UVec3::from(shape.delinearize(i)).as_vec3() - 16.0;
+            samples[i as usize] = BoolVoxel((8.0..15.0).contains(&p.length()), false);
+        }
+
+        let buffer = mesh(&samples);
+        assert_manifold(&buffer);
+        // Both surfaces are spheres, so the Euler characteristic is 2 + 2.
+        let num_edges = buffer.indices.len() / 2;
+        let num_triangles = buffer.indices.len() / 3;
+        assert_eq!(buffer.positions.len() + num_triangles - num_edges, 4);
+    }
+
+    #[test]
+    fn noise_is_manifold() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        let mut state = 0x2545_f491_u32;
+        for i in 0u32..(SampleShape::SIZE) {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let [x, y, z] = shape.delinearize(i);
+            // Fill the corners of the chunk densely and the middle sparsely, to hit every configuration.
+            let density = (x + y + z) % 8;
+            samples[i as usize] = BoolVoxel(state % 8 < density, false);
+        }
+
+        let buffer = mesh(&samples);
+        assert!(!buffer.indices.is_empty());
+        assert_manifold(&buffer);
+    }
+}
@@ -0,0 +1,720 @@
+use crate::{
+    oct_encode_normal, rotate_tex_coords, Axis, LayerId, OrientedBlockFace, QuadBuffer,
+    QuadCoordinateConfig, UnitQuadBuffer, UnorientedQuad, VoxelContext, VoxelSource,
+};
+
+use ilattice::glam::Vec3;
+use ndshape::Shape;
+
+/// Vertex attributes and triangle indices for a mesh, ready to be uploaded to a GPU.
+///
+/// Vertices are generated with [`OrientedBlockFace::quad_mesh_positions`],
+/// [`OrientedBlockFace::quad_mesh_normals`], and [`OrientedBlockFace::tex_coords`], along with the world-space size of
+/// each quad in [`MeshData::quad_extents`], and triangles with
+/// [`OrientedBlockFace::quad_mesh_indices`]. Each quad occupies 4 consecutive vertices and 6 consecutive indices.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// let mut quads = QuadBuffer::new();
+/// quads.groups[0].push(UnorientedQuad { minimum: [1; 3], width: 2, height: 3 });
+///
+/// let mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+/// assert_eq!(mesh.positions.len(), quads.vertex_count());
+/// assert_eq!(mesh.indices.len(), quads.index_count());
+/// assert_eq!(mesh.quad_extents, vec![[2.0, 3.0]; 4]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// UV coordinates, assuming that (0, 0) is the top left of the texture as in most graphics APIs.
+    pub tex_coords: Vec<[f32; 2]>,
+    /// The size of the quad containing each vertex along its U and V axes, in the same units as the positions.
+    ///
+    /// This lets a shader tile textures correctly across merged quads without UVs, e.g. for triplanar shading. Insets are
+    /// not taken into account.
+    pub quad_extents: Vec<[f32; 2]>,
+    /// RGBA vertex colors from [`VoxelContext::color`], or opaque white if the mesh was generated without voxels.
+    pub colors: Vec<[u8; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves space for `num_quads` quads.
+    pub fn with_capacity(num_quads: usize) -> Self {
+        Self {
+            positions: Vec::with_capacity(4 * num_quads),
+            normals: Vec::with_capacity(4 * num_quads),
+            tex_coords: Vec::with_capacity(4 * num_quads),
+            quad_extents: Vec::with_capacity(4 * num_quads),
+            colors: Vec::with_capacity(4 * num_quads),
+            indices: Vec::with_capacity(6 * num_quads),
+        }
+    }
+
+    /// Clears the buffer.
+    pub fn reset(&mut self) {
+        self.positions.clear();
+        self.normals.clear();
+        self.tex_coords.clear();
+        self.quad_extents.clear();
+        self.colors.clear();
+        self.indices.clear();
+    }
+
+    /// Appends all of the vertices and triangles in `other`, offsetting its indices so they refer to the appended
+    /// vertices, which come after the vertices already in `self`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let mut quads = QuadBuffer::new();
+    /// quads.groups[0].push(UnorientedQuad { minimum: [1; 3], width: 1, height: 1 });
+    ///
+    /// let mut mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+    /// let single = mesh.clone();
+    /// mesh.append(&single);
+    /// assert_eq!(mesh.positions.len(), 8);
+    /// for (appended, original) in mesh.indices[6..].iter().zip(single.indices.iter()) {
+    ///     assert_eq!(*appended, original + 4);
+    /// }
+    /// ```
+    pub fn append(&mut self, other: &MeshData) {
+        let start = self.positions.len() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.tex_coords.extend_from_slice(&other.tex_coords);
+        self.quad_extents.extend_from_slice(&other.quad_extents);
+        self.colors.extend_from_slice(&other.colors);
+        self.indices
+            .extend(other.indices.iter().map(|&i| start + i));
+    }
+
+    /// Returns [`MeshData::normals`] packed with [`oct_encode_normal`], to upload as an `snorm8x2` vertex attribute
+    /// instead of the `f32` normals.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let mut quads = QuadBuffer::new();
+    /// quads.groups[2].push(UnorientedQuad { minimum: [1; 3], width: 2, height: 3 });
+    ///
+    /// let mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+    /// let packed = mesh.packed_normals();
+    /// assert_eq!(packed, vec![RIGHT_HANDED_Y_UP_CONFIG.faces[2].packed_normal(); 4]);
+    /// assert_eq!(oct_decode_normal(packed[0]), mesh.normals[0]);
+    /// ```
+    pub fn packed_normals(&self) -> Vec<u16> {
+        self.normals.iter().map(|&n| oct_encode_normal(n)).collect()
+    }
+
+    /// Appends the vertices and indices of a single quad.
+    ///
+    /// `u_flip_face` should correspond to the field on [`QuadCoordinateConfig`].
+    pub fn push_quad(
+        &mut self,
+        face: &OrientedBlockFace,
+        quad: &UnorientedQuad,
+        u_flip_face: Axis,
+        voxel_size: f32,
+    ) {
+        self.push_quad_at(face, quad, u_flip_face, voxel_size, Vec3::ZERO);
+    }
+
+    /// Same as [`MeshData::push_quad`], but the positions are offset by `origin` as in
+    /// [`OrientedBlockFace::quad_mesh_positions_at`].
+    pub fn push_quad_at(
+        &mut self,
+        face: &OrientedBlockFace,
+        quad: &UnorientedQuad,
+        u_flip_face: Axis,
+        voxel_size: f32,
+        origin: Vec3,
+    ) {
+        let options = MeshOptions {
+            voxel_size,
+            origin,
+            ..Default::default()
+        };
+        self.push_face(
+            face,
+            quad,
+            u_flip_face,
+            &options,
+            [1.0; 2],
+            &FaceAttributes::default(),
+        );
+    }
+
+    /// Sets the color of every vertex of the most recently pushed quad.
+    pub(crate) fn set_last_quad_color(&mut self, color: [u8; 4]) {
+        let start = self.colors.len() - 4;
+        self.colors[start..].fill(color);
+    }
+
+    /// Same as [`MeshData::push_quad`], but the positions are inset as in
+    /// [`OrientedBlockFace::quad_mesh_positions_inset`].
+    pub fn push_inset_quad(
+        &mut self,
+        face: &OrientedBlockFace,
+        quad: &UnorientedQuad,
+        u_flip_face: Axis,
+        voxel_size: f32,
+        min_inset: Vec3,
+        max_inset: Vec3,
+    ) {
+        let options = MeshOptions {
+            voxel_size,
+            ..Default::default()
+        };
+        let attributes = FaceAttributes {
+            min_inset,
+            max_inset,
+            ..Default::default()
+        };
+        self.push_face(face, quad, u_flip_face, &options, [1.0; 2], &attributes);
+    }
+
+    /// Appends `quad`, followed by a copy of it for each of `attributes.layers`. Every vertex that this crate generates
+    /// goes through here.
+    fn push_face(
+        &mut self,
+        face: &OrientedBlockFace,
+        quad: &UnorientedQuad,
+        u_flip_face: Axis,
+        options: &MeshOptions,
+        [u_scale, v_scale]: [f32; 2],
+        attributes: &FaceAttributes,
+    ) {
+        let voxel_size = options.voxel_size;
+        let positions = face.quad_mesh_positions_inset(
+            quad,
+            voxel_size,
+            attributes.min_inset,
+            attributes.max_inset,
+        );
+        let tex_coords = rotate_tex_coords(
+            face.tex_coords(u_flip_face, true, quad),
+            attributes.quarter_turns,
+        )
+        .map(|[u, v]| [u * u_scale, v * v_scale]);
+        let extent = [
+            quad.width as f32 * voxel_size,
+            quad.height as f32 * voxel_size,
+        ];
+        let layer_step =
+            options.layer_offset.unwrap_or(0.0) * voxel_size * face.signed_normal().as_vec3();
+
+        for i in 0..=attributes.layers.len() {
+            let offset = options.origin + i as f32 * layer_step;
+            let start = self.positions.len() as u32;
+            self.indices
+                .extend_from_slice(&face.quad_mesh_indices(start, false));
+            self.positions
+                .extend(positions.map(|p| (Vec3::from(p) + offset).to_array()));
+            self.normals.extend_from_slice(&face.quad_mesh_normals());
+            self.tex_coords.extend_from_slice(&tex_coords);
+            self.quad_extents.extend_from_slice(&[extent; 4]);
+            self.colors.extend_from_slice(&[attributes.color; 4]);
+        }
+    }
+}
+
+/// How [`QuadBuffer::to_mesh_data_with_options`] and [`QuadBuffer::to_mesh_data_with_voxels`] turn quads into
+/// vertices.
+///
+/// The flags choose which properties of each face are read from the [`VoxelContext`] for the voxel at the minimum of
+/// its quad. They only have an effect when there are voxels to read from, and any combination of them can be used
+/// together.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// # use block_mesh::ilattice::glam::Vec3;
+/// let config = &RIGHT_HANDED_Y_UP_CONFIG;
+/// let top = config.face_index(SignedAxis::PosY);
+/// let mut quads = QuadBuffer::new();
+/// quads.groups[top].push(UnorientedQuad { minimum: [0; 3], width: 3, height: 2 });
+///
+/// // Tops use a tile twice the size of the other faces in the atlas, and the chunk is placed at X = 32.
+/// let mut uv_scale = [[0.25; 2]; 6];
+/// uv_scale[top] = [0.5, 0.5];
+/// let options = MeshOptions { origin: Vec3::new(32.0, 0.0, 0.0), uv_scale, ..Default::default() };
+/// let mesh = quads.to_mesh_data_with_options(config, &options);
+/// let max_uv = mesh.tex_coords.iter().fold([0.0f32; 2], |m, uv| [m[0].max(uv[0]), m[1].max(uv[1])]);
+/// assert_eq!(max_uv, [1.5, 1.0]);
+/// assert!(mesh.positions.iter().all(|p| p[0] >= 32.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshOptions {
+    /// The size of a voxel in world units. Defaults to 1.
+    pub voxel_size: f32,
+    /// Added to every position, as in [`OrientedBlockFace::quad_mesh_positions_at`]. Defaults to zero.
+    pub origin: Vec3,
+    /// The [`MeshData::tex_coords`] of the quads in `groups[i]` are multiplied by `uv_scale[i]`, e.g. when the tiles
+    /// for the tops of blocks have a different size in the atlas than the tiles for the sides.
+    ///
+    /// Each voxel face spans `uv_scale[i]` in UV space, so with a scale of `[0.25, 0.5]`, a 3x2 quad spans
+    /// `[0.75, 1.0]`. Defaults to 1.
+    pub uv_scale: [[f32; 2]; 6],
+    /// Whether the quads of voxels with a nonzero [`VoxelContext::face_inset`] are moved inside of their cells. Since
+    /// voxels with insets are never merged, each of their quads covers a single voxel.
+    pub insets: bool,
+    /// Whether [`MeshData::colors`] are read from [`VoxelContext::color`]. Otherwise every vertex is opaque white.
+    pub colors: bool,
+    /// Whether [`MeshData::tex_coords`] are rotated by [`VoxelContext::texture_rotation`]. Since faces with different
+    /// rotations are never merged, the rotation applies to the whole quad.
+    pub texture_rotations: bool,
+    /// If set, each quad is followed by one quad for each of the [`VoxelContext::face_layers`] of its voxel. The `i`th
+    /// layer is moved `(i + 1) * layer_offset` voxels along the normal, so it is drawn over the face and the layers
+    /// below it.
+    pub layer_offset: Option<f32>,
+}
+
+impl Default for MeshOptions {
+    fn default() -> Self {
+        Self {
+            voxel_size: 1.0,
+            origin: Vec3::ZERO,
+            uv_scale: [[1.0; 2]; 6],
+            insets: false,
+            colors: false,
+            texture_rotations: false,
+            layer_offset: None,
+        }
+    }
+}
+
+impl MeshOptions {
+    /// Reads the properties of the face of `voxel` with the normal of `config.faces[face_index]` that are enabled.
+    fn face_attributes<'a, T, C>(
+        &self,
+        config: &QuadCoordinateConfig,
+        face_index: usize,
+        voxel: &'a T,
+        ctx: &'a C,
+    ) -> FaceAttributes<'a>
+    where
+        C: VoxelContext<T>,
+    {
+        let mut attributes = FaceAttributes::default();
+        if self.insets {
+            for (inset_index, inset_face) in config.faces.iter().enumerate() {
+                let inset = ctx.face_inset(voxel, inset_index);
+                let i = inset_face.axis().index();
+                if inset_face.is_positive() {
+                    attributes.max_inset[i] = inset;
+                } else {
+                    attributes.min_inset[i] = inset;
+                }
+            }
+        }
+        if self.colors {
+            attributes.color = ctx.color(voxel, face_index);
+        }
+        if self.texture_rotations {
+            attributes.quarter_turns = ctx.texture_rotation(voxel, face_index);
+        }
+        if self.layer_offset.is_some() {
+            attributes.layers = ctx.face_layers(voxel, face_index);
+        }
+        attributes
+    }
+}
+
+/// The properties of a face that [`MeshOptions`] can read from a [`VoxelContext`]. The defaults are the defaults of
+/// [`VoxelContext`].
+struct FaceAttributes<'a> {
+    min_inset: Vec3,
+    max_inset: Vec3,
+    color: [u8; 4],
+    quarter_turns: u8,
+    layers: &'a [LayerId],
+}
+
+impl Default for FaceAttributes<'_> {
+    fn default() -> Self {
+        Self {
+            min_inset: Vec3::ZERO,
+            max_inset: Vec3::ZERO,
+            color: [u8::MAX; 4],
+            quarter_turns: 0,
+            layers: &[],
+        }
+    }
+}
+
+/// Meshes `groups[i]` with `config.faces[i]`, reading the properties of each face from `attributes`. Returns the mesh
+/// along with the layer of each of its quads, which is empty unless [`MeshOptions::layer_offset`] is set.
+fn mesh_groups<'a, Q, A>(
+    groups: &[Vec<Q>; 6],
+    config: &QuadCoordinateConfig,
+    options: &MeshOptions,
+    mut attributes: A,
+) -> (MeshData, Vec<Option<LayerId>>)
+where
+    Q: Copy + Into<UnorientedQuad>,
+    A: FnMut(usize, &UnorientedQuad) -> FaceAttributes<'a>,
+{
+    let num_quads = groups.iter().map(Vec::len).sum();
+    let mut mesh = MeshData::with_capacity(num_quads);
+    let mut quad_layers = Vec::new();
+    let groups = groups.iter().zip(config.faces.iter()).zip(options.uv_scale);
+    for (face_index, ((group, face), uv_scale)) in groups.enumerate() {
+        for &quad in group.iter() {
+            let quad = quad.into();
+            let attributes = attributes(face_index, &quad);
+            mesh.push_face(
+                face,
+                &quad,
+                config.u_flip_face,
+                options,
+                uv_scale,
+                &attributes,
+            );
+            if options.layer_offset.is_some() {
+                quad_layers.push(None);
+                quad_layers.extend(attributes.layers.iter().copied().map(Some));
+            }
+        }
+    }
+    (mesh, quad_layers)
+}
+
+impl QuadBuffer {
+    /// Generates a [`MeshData`] containing every quad in this buffer, interpreting `groups[i]` with
+    /// `config.faces[i]`.
+    pub fn to_mesh_data(&self, config: &QuadCoordinateConfig, voxel_size: f32) -> MeshData {
+        let options = MeshOptions {
+            voxel_size,
+            ..Default::default()
+        };
+        self.to_mesh_data_with_options(config, &options)
+    }
+
+    /// Same as [`QuadBuffer::to_mesh_data`], but with the voxel size, origin, and UV scale of `options`.
+    ///
+    /// There are no voxels to read from, so every face gets the defaults of [`VoxelContext`] and the flags of `options`
+    /// have no effect. See [`QuadBuffer::to_mesh_data_with_voxels`].
+    pub fn to_mesh_data_with_options(
+        &self,
+        config: &QuadCoordinateConfig,
+        options: &MeshOptions,
+    ) -> MeshData {
+        let (mesh, _) = mesh_groups(&self.groups, config, options, |_, _| {
+            FaceAttributes::default()
+        });
+        mesh
+    }
+
+    /// Same as [`QuadBuffer::to_mesh_data_with_options`], but the properties enabled by the flags of `options` are read
+    /// from `ctx` for the voxel at the minimum of each quad.
+    ///
+    /// Returns the mesh along with the layer of each of its quads, which is `None` for the faces themselves and empty
+    /// unless [`MeshOptions::layer_offset`] is set. `voxels` and `voxels_shape` must be the same as when the quads were
+    /// generated.
+    pub fn to_mesh_data_with_voxels<V, S, C>(
+        &self,
+        config: &QuadCoordinateConfig,
+        options: &MeshOptions,
+        voxels: &V,
+        voxels_shape: &S,
+        ctx: &C,
+    ) -> (MeshData, Vec<Option<LayerId>>)
+    where
+        V: VoxelSource + ?Sized,
+        S: Shape<3, Coord = u32>,
+        C: VoxelContext<V::Voxel>,
+    {
+        mesh_groups(&self.groups, config, options, |face_index, quad| {
+            let voxel = voxels.voxel(voxels_shape.linearize(quad.minimum) as usize);
+            options.face_attributes(config, face_index, voxel, ctx)
+        })
+    }
+
+    /// Same as [`QuadBuffer::to_mesh_data`], but the positions of the 4 corners of each quad are returned by
+    /// `positions`, in the same order as [`OrientedBlockFace::quad_corners`], e.g. to apply a per-chunk transform or
+    /// wind sway while meshing. The normals, UVs, colors, and indices are generated as usual, and
+    /// [`MeshData::quad_extents`] are measured between the returned corners.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// let mut quads = QuadBuffer::new();
+    /// quads.groups[4].push(UnorientedQuad { minimum: [0; 3], width: 2, height: 3 });
+    ///
+    /// // Lift every vertex by half a voxel.
+    /// let config = &RIGHT_HANDED_Y_UP_CONFIG;
+    /// let mesh = quads.to_mesh_data_with(config, |face, quad| {
+    ///     face.quad_mesh_positions(quad, 1.0).map(|[x, y, z]| [x, y + 0.5, z])
+    /// });
+    /// let options = MeshOptions { origin: [0.0, 0.5, 0.0].into(), ..Default::default() };
+    /// assert_eq!(mesh, quads.to_mesh_data_with_options(config, &options));
+    /// ```
+    pub fn to_mesh_data_with<F>(&self, config: &QuadCoordinateConfig, mut positions: F) -> MeshData
+    where
+        F: FnMut(&OrientedBlockFace, &UnorientedQuad) -> [[f32; 3]; 4],
+    {
+        let mut mesh = self.to_mesh_data(config, 1.0);
+        let quads = self.groups.iter().zip(config.faces.iter());
+        let quads = quads.flat_map(|(group, face)| group.iter().map(move |quad| (face, quad)));
+        for ((face, quad), start) in quads.zip((0..mesh.positions.len()).step_by(4)) {
+            let corners = positions(face, quad);
+            mesh.positions[start..start + 4].copy_from_slice(&corners);
+            let [p0, p1, p2, _] = corners.map(Vec3::from);
+            mesh.quad_extents[start..start + 4].fill([p0.distance(p1), p0.distance(p2)]);
+        }
+        mesh
+    }
+}
+
+impl QuadBuffer {
+    /// Returns two independent triangles for every quad in this buffer, interpreting `groups[i]` with `faces[i]`.
+    ///
+    /// Unlike [`QuadBuffer::to_mesh_data`], no vertices are shared, which is the input that many CSG and mesh boolean
+    /// libraries expect. Every triangle `[a, b, c]` is wound so that `(b - a).cross(c - a)` points out of the voxel along
+    /// the face normal, i.e. counterclockwise when viewed from outside in a right-handed coordinate system, regardless of
+    /// the handedness of `faces`.
+    pub fn to_polygon_soup(
+        &self,
+        faces: &[OrientedBlockFace; 6],
+        voxel_size: f32,
+    ) -> Vec<[Vec3; 3]> {
+        let mut triangles = Vec::with_capacity(2 * self.num_quads());
+        for (group, face) in self.groups.iter().zip(faces.iter()) {
+            let normal = face.signed_normal().as_vec3();
+            let indices = face.quad_mesh_indices(0, false);
+            for quad in group.iter() {
+                let positions = face.quad_mesh_positions(quad, voxel_size).map(Vec3::from);
+                for triangle in indices.chunks(3) {
+                    let [a, mut b, mut c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+                    if (b - a).cross(c - a).dot(normal) < 0.0 {
+                        std::mem::swap(&mut b, &mut c);
+                    }
+                    triangles.push([a, b, c]);
+                }
+            }
+        }
+        triangles
+    }
+}
+
+impl UnitQuadBuffer {
+    /// Generates a [`MeshData`] containing every quad in this buffer, interpreting `groups[i]` with
+    /// `config.faces[i]`.
+    pub fn to_mesh_data(&self, config: &QuadCoordinateConfig, voxel_size: f32) -> MeshData {
+        let options = MeshOptions {
+            voxel_size,
+            ..Default::default()
+        };
+        self.to_mesh_data_with_options(config, &options)
+    }
+
+    /// Same as [`QuadBuffer::to_mesh_data_with_options`], for unit quads.
+    pub fn to_mesh_data_with_options(
+        &self,
+        config: &QuadCoordinateConfig,
+        options: &MeshOptions,
+    ) -> MeshData {
+        let (mesh, _) = mesh_groups(&self.groups, config, options, |_, _| {
+            FaceAttributes::default()
+        });
+        mesh
+    }
+
+    /// Same as [`QuadBuffer::to_mesh_data_with_voxels`], for unit quads.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+    /// # use block_mesh::*;
+    /// struct TintContext;
+    ///
+    /// impl VoxelContext<u8> for TintContext {
+    ///     fn get_visibility(&self, voxel: &u8) -> VoxelVisibility {
+    ///         if *voxel == 0 { VoxelVisibility::Empty } else { VoxelVisibility::Opaque }
+    ///     }
+    ///
+    ///     fn color(&self, voxel: &u8, _face_index: usize) -> [u8; 4] {
+    ///         [*voxel, 255, 0, 255]
+    ///     }
+    /// }
+    ///
+    /// type ChunkShape = ConstShape3u32<3, 3, 3>;
+    /// let mut voxels = [0; ChunkShape::SIZE as usize];
+    /// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = 7;
+    ///
+    /// let mut buffer = UnitQuadBuffer::new();
+    /// visible_block_faces(&voxels, &ChunkShape {}, [0; 3], [2; 3], &RIGHT_HANDED_Y_UP_CONFIG.faces, &mut buffer, &TintContext);
+    /// let options = MeshOptions { colors: true, ..Default::default() };
+    /// let (mesh, _) = buffer.to_mesh_data_with_voxels(&RIGHT_HANDED_Y_UP_CONFIG, &options, &voxels, &ChunkShape {}, &TintContext);
+    /// assert_eq!(mesh.colors, vec![[7, 255, 0, 255]; 24]);
+    /// ```
+    pub fn to_mesh_data_with_voxels<V, S, C>(
+        &self,
+        config: &QuadCoordinateConfig,
+        options: &MeshOptions,
+        voxels: &V,
+        voxels_shape: &S,
+        ctx: &C,
+    ) -> (MeshData, Vec<Option<LayerId>>)
+    where
+        V: VoxelSource + ?Sized,
+        S: Shape<3, Coord = u32>,
+        C: VoxelContext<V::Voxel>,
+    {
+        mesh_groups(&self.groups, config, options, |face_index, quad| {
+            let voxel = voxels.voxel(voxels_shape.linearize(quad.minimum) as usize);
+            options.face_attributes(config, face_index, voxel, ctx)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, EMPTY, FULL};
+    use crate::{
+        greedy_quads, DefaultVoxelContext, GreedyQuadsBuffer, Voxel, VoxelVisibility,
+        RIGHT_HANDED_Y_UP_CONFIG,
+    };
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    #[test]
+    fn polygon_soup_is_closed_and_wound_outward() {
+        // An L shape, so some faces are merged and some are not.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for p in [[1, 1, 1], [2, 1, 1], [3, 1, 1], [1, 2, 1]] {
+            samples[shape.linearize(p) as usize] = FULL;
+        }
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [5; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let soup = buffer
+            .quads
+            .to_polygon_soup(&RIGHT_HANDED_Y_UP_CONFIG.faces, 0.5);
+        assert_eq!(soup.len(), 2 * buffer.quads.num_quads());
+
+        let mut area_sum = Vec3::ZERO;
+        let mut volume = 0.0;
+        for [a, b, c] in soup {
+            let area = (b - a).cross(c - a);
+            // Each triangle faces away from the solid voxels.
+            let center = (a + b + c) / 3.0;
+            let outside = (2.0 * (center + 0.01 * area.normalize()))
+                .floor()
+                .as_uvec3();
+            assert_eq!(samples[shape.linearize(outside.to_array()) as usize], EMPTY);
+            area_sum += area;
+            volume += a.dot(b.cross(c)) / 6.0;
+        }
+        // A closed surface with outward winding encloses a positive volume.
+        assert!(area_sum.length() < 1e-4);
+        assert!((volume - 4.0 * 0.125).abs() < 1e-4);
+    }
+
+    #[test]
+    fn mesh_options_combine() {
+        struct DecoratedContext;
+
+        impl VoxelContext<BoolVoxel> for DecoratedContext {
+            fn get_visibility(&self, voxel: &BoolVoxel) -> VoxelVisibility {
+                voxel.get_visibility()
+            }
+
+            fn face_inset(&self, _voxel: &BoolVoxel, _face_index: usize) -> f32 {
+                0.25
+            }
+
+            fn color(&self, _voxel: &BoolVoxel, _face_index: usize) -> [u8; 4] {
+                [1, 2, 3, 4]
+            }
+
+            fn texture_rotation(&self, _voxel: &BoolVoxel, _face_index: usize) -> u8 {
+                1
+            }
+
+            fn face_layers<'a>(
+                &'a self,
+                _voxel: &'a BoolVoxel,
+                _face_index: usize,
+            ) -> &'a [LayerId] {
+                &[9]
+            }
+        }
+
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1; 3]) as usize] = FULL;
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [5; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        let options = MeshOptions {
+            origin: Vec3::new(10.0, 0.0, 0.0),
+            insets: true,
+            colors: true,
+            texture_rotations: true,
+            layer_offset: Some(0.1),
+            ..Default::default()
+        };
+        let (mesh, layers) = buffer.quads.to_mesh_data_with_voxels(
+            &RIGHT_HANDED_Y_UP_CONFIG,
+            &options,
+            &samples,
+            &shape,
+            &DecoratedContext,
+        );
+        assert_eq!(layers, [None, Some(9)].repeat(6));
+        assert_eq!(mesh.colors, vec![[1, 2, 3, 4]; 4 * layers.len()]);
+
+        let plain = buffer.quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+        for (i, face) in RIGHT_HANDED_Y_UP_CONFIG.faces.iter().enumerate() {
+            let (face_quad, layer_quad) = (8 * i..8 * i + 4, 8 * i + 4..8 * i + 8);
+            let offset = 0.1 * face.signed_normal().as_vec3();
+            for (p, layer_p) in mesh.positions[face_quad.clone()]
+                .iter()
+                .zip(&mesh.positions[layer_quad.clone()])
+            {
+                let p = Vec3::from(*p) - options.origin;
+                assert!(p.cmpge(Vec3::splat(1.25)).all() && p.cmple(Vec3::splat(1.75)).all());
+                assert!((Vec3::from(*layer_p) - options.origin - p - offset).length() < 1e-6);
+            }
+
+            let mut unrotated = [[0.0; 2]; 4];
+            unrotated.copy_from_slice(&plain.tex_coords[4 * i..4 * i + 4]);
+            let rotated = rotate_tex_coords(unrotated, 1);
+            assert_eq!(mesh.tex_coords[face_quad], rotated);
+            assert_eq!(mesh.tex_coords[layer_quad], rotated);
+        }
+    }
+
+    type SampleShape = ConstShape3u32<6, 6, 6>;
+}
@@ -0,0 +1,105 @@
+use crate::{MeshData, QuadBuffer, QuadCoordinateConfig};
+
+use half::f16;
+
+/// Same as [`MeshData`], but every floating point attribute is stored as a half-precision [`f16`], which halves the
+/// memory and bandwidth used by large static meshes.
+///
+/// [`f16`] represents every integer up to 2048 exactly, and multiples of 1/2 up to 1024, so the positions of a chunk are
+/// exact as long as they are small multiples of the voxel size, e.g. when each chunk is meshed in its own coordinates
+/// and moved into place with a transform. Positions far from the origin lose precision quickly, so don't bake large
+/// world offsets into them.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// let mut quads = QuadBuffer::new();
+/// quads.groups[0].push(UnorientedQuad { minimum: [1, 2, 3], width: 2, height: 3 });
+///
+/// let mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+/// let mesh_f16 = quads.to_mesh_data_f16(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+/// assert_eq!(mesh_f16.indices, mesh.indices);
+/// for (p, p_f16) in mesh.positions.iter().zip(mesh_f16.positions.iter()) {
+///     assert_eq!(*p, p_f16.map(f32::from));
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshDataF16 {
+    pub positions: Vec<[f16; 3]>,
+    pub normals: Vec<[f16; 3]>,
+    /// See [`MeshData::tex_coords`].
+    pub tex_coords: Vec<[f16; 2]>,
+    /// See [`MeshData::quad_extents`].
+    pub quad_extents: Vec<[f16; 2]>,
+    /// See [`MeshData::colors`].
+    pub colors: Vec<[u8; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    /// Converts every floating point attribute to [`f16`], rounding to the nearest representable value.
+    pub fn to_f16(&self) -> MeshDataF16 {
+        MeshDataF16 {
+            positions: self
+                .positions
+                .iter()
+                .map(|p| p.map(f16::from_f32))
+                .collect(),
+            normals: self.normals.iter().map(|n| n.map(f16::from_f32)).collect(),
+            tex_coords: self
+                .tex_coords
+                .iter()
+                .map(|uv| uv.map(f16::from_f32))
+                .collect(),
+            quad_extents: self
+                .quad_extents
+                .iter()
+                .map(|e| e.map(f16::from_f32))
+                .collect(),
+            colors: self.colors.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+
+impl QuadBuffer {
+    /// Same as [`QuadBuffer::to_mesh_data`], but with half-precision attributes. See [`MeshDataF16`].
+    pub fn to_mesh_data_f16(&self, config: &QuadCoordinateConfig, voxel_size: f32) -> MeshDataF16 {
+        self.to_mesh_data(config, voxel_size).to_f16()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{UnorientedQuad, RIGHT_HANDED_Y_UP_CONFIG};
+
+    #[test]
+    fn chunk_positions_are_exact_in_f16() {
+        let mut quads = QuadBuffer::new();
+        for (i, group) in quads.groups.iter_mut().enumerate() {
+            let i = i as u32;
+            group.push(UnorientedQuad {
+                minimum: [33 - i, i, 17 + i],
+                width: 1 + i,
+                height: 32 - i,
+            });
+        }
+
+        // Every coordinate in a 64^3 chunk with half-voxel steps is exact.
+        let mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 0.5);
+        let mesh_f16 = mesh.to_f16();
+        for (p, p_f16) in mesh.positions.iter().zip(mesh_f16.positions.iter()) {
+            assert_eq!(*p, p_f16.map(f32::from));
+        }
+        for (n, n_f16) in mesh.normals.iter().zip(mesh_f16.normals.iter()) {
+            assert_eq!(*n, n_f16.map(f32::from));
+        }
+        for (uv, uv_f16) in mesh.tex_coords.iter().zip(mesh_f16.tex_coords.iter()) {
+            assert_eq!(*uv, uv_f16.map(f32::from));
+        }
+        assert_eq!(mesh_f16.colors, mesh.colors);
+        assert_eq!(mesh_f16.indices, mesh.indices);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    checked_shape_size, greedy_quads, GreedyQuadsBuffer, MergeVoxelContext, OrientedBlockFace,
+};
+
+use ndarray::{ArrayBase, CowArray, Data, Ix3};
+use ndshape::Shape;
+
+/// Same as [`greedy_quads`], but the voxels and their shape come from a 3D [`ndarray`] array, e.g. an
+/// `Array3<T>` or an `ArrayView3<T>`.
+///
+/// The voxel at position `[x, y, z]` is `array[[x, y, z]]`, so `min` and `max` are indices into the array, and the
+/// quads in `output` use the same coordinates. The array's memory is meshed in place when it is contiguous with
+/// non-negative strides, which includes both the default row-major layout and column-major (`.f()`) layout. Any other
+/// array, e.g. a sliced or reversed view, is first copied into the default layout.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// # use block_mesh::ndarray::Array3;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// let mut voxels = Array3::from_elem((8, 6, 4), BoolVoxel(false));
+/// voxels[[1, 1, 1]] = BoolVoxel(true);
+/// voxels[[2, 1, 1]] = BoolVoxel(true);
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_ndarray(
+///     &voxels,
+///     [0; 3],
+///     [7, 5, 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// assert!(buffer.quads.groups[0].iter().all(|quad| quad.minimum == [1, 1, 1]));
+/// ```
+pub fn greedy_quads_ndarray<A, T, C>(
+    array: &ArrayBase<A, Ix3>,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    A: Data<Elem = T>,
+    T: Clone,
+    C: MergeVoxelContext<T>,
+{
+    let in_place =
+        array.as_slice_memory_order().is_some() && array.strides().iter().all(|&s| s >= 0);
+    let array: CowArray<T, Ix3> = if in_place {
+        array.view().into()
+    } else {
+        array.as_standard_layout()
+    };
+    let shape = StridedShape::new(&array);
+    let voxels = array
+        .as_slice_memory_order()
+        .expect("the array is contiguous");
+    greedy_quads(voxels, &shape, min, max, faces, output, ctx);
+}
+
+/// Linearizes `[x, y, z]` to the offset of `array[[x, y, z]]` in the memory of a contiguous array with non-negative
+/// strides.
+struct StridedShape {
+    dims: [u32; 3],
+    strides: [u32; 3],
+}
+
+impl StridedShape {
+    fn new<T>(array: &CowArray<T, Ix3>) -> Self {
+        let (x, y, z) = array.dim();
+        let dims = [x, y, z].map(|d| u32::try_from(d).expect("the array is too large"));
+        checked_shape_size(dims);
+        let strides = [0, 1, 2].map(|axis| {
+            // The stride of an axis with a single index is never used, and may be anything.
+            if dims[axis] > 1 {
+                array.strides()[axis] as u32
+            } else {
+                0
+            }
+        });
+        Self { dims, strides }
+    }
+}
+
+impl Shape<3> for StridedShape {
+    type Coord = u32;
+
+    #[inline]
+    fn size(&self) -> u32 {
+        self.dims[0] * self.dims[1] * self.dims[2]
+    }
+
+    #[inline]
+    fn usize(&self) -> usize {
+        self.size() as usize
+    }
+
+    #[inline]
+    fn as_array(&self) -> [u32; 3] {
+        self.dims
+    }
+
+    #[inline]
+    fn linearize(&self, [x, y, z]: [u32; 3]) -> u32 {
+        // Wrapping so that negative offsets (as unsigned vectors) produce strides that can be added to indices.
+        x.wrapping_mul(self.strides[0])
+            .wrapping_add(y.wrapping_mul(self.strides[1]))
+            .wrapping_add(z.wrapping_mul(self.strides[2]))
+    }
+
+    #[inline]
+    fn delinearize(&self, mut i: u32) -> [u32; 3] {
+        let mut axes = [0, 1, 2];
+        axes.sort_unstable_by_key(|&axis| std::cmp::Reverse(self.strides[axis]));
+        let mut p = [0; 3];
+        for axis in axes {
+            if let Some(c) = i.checked_div(self.strides[axis]) {
+                p[axis] = c;
+                i %= self.strides[axis];
+            }
+        }
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sphere, SampleShape, EMPTY};
+    use crate::{DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndarray::{s, Array3, Axis, ShapeBuilder};
+
+    #[test]
+    fn ndarray_layouts_match_flat_slice() {
+        let shape = SampleShape {};
+        let samples = sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+
+        let row_major = Array3::from_shape_fn((34, 34, 34), |(x, y, z)| {
+            samples[shape.linearize([x, y, z].map(|c| c as u32)) as usize]
+        });
+        let mut column_major = Array3::from_elem((34, 34, 34).f(), EMPTY);
+        column_major.assign(&row_major);
+        // The same voxels with a negative stride along Y.
+        let mut reversed = row_major.slice(s![.., ..;-1, ..]).to_owned();
+        reversed.invert_axis(Axis(1));
+
+        for array in [row_major.view(), column_major.view(), reversed.view()] {
+            let mut buffer = GreedyQuadsBuffer::new(array.len());
+            greedy_quads_ndarray(
+                &array,
+                [0; 3],
+                [33; 3],
+                faces,
+                &mut buffer,
+                &DefaultVoxelContext,
+            );
+            assert_eq!(buffer.quads.groups, expected.quads.groups);
+        }
+    }
+
+    #[test]
+    fn strided_shape_delinearizes_linear_indices() {
+        let array = Array3::from_elem((3, 4, 5).f(), 0u8);
+        let shape = StridedShape::new(&array.view().into());
+        for (i, _) in array.as_slice_memory_order().unwrap().iter().enumerate() {
+            assert_eq!(shape.linearize(shape.delinearize(i as u32)), i as u32);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    geometry::opposite_face_indices, simple::face_is_visible_for_face, OrientedBlockFace,
+    UnitQuadBuffer, UnorientedUnitQuad, VoxelContext, VoxelVisibility,
+};
+
+use ilattice::glam::{IVec3, UVec3};
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// A chunk without padding, along with borrowed views of its 26 neighbors, all of the same shape.
+///
+/// `chunks[x][y][z]` is the chunk at offset `[x - 1, y - 1, z - 1]` from the core chunk, so `chunks[1][1][1]` is the
+/// core chunk itself and must be present. Voxels on the boundary of the core chunk are compared against the voxels of
+/// the neighboring chunks as they are needed, so there is no need to copy each chunk into a padded array before
+/// meshing. A missing neighbor is treated as if all of its voxels were [`VoxelVisibility::Empty`]; pass a chunk of
+/// opaque voxels instead if faces on that side should be culled.
+///
+/// Currently only [`visible_block_faces_with_neighbors`] can mesh a neighborhood; [`greedy_quads`](crate::greedy_quads)
+/// still requires a padded array.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<16, 16, 16>;
+///
+/// let full = [BoolVoxel(true); ChunkShape::SIZE as usize];
+/// let mut chunks = [[[None; 3]; 3]; 3];
+/// chunks[1][1][1] = Some(&full[..]);
+/// // The neighbor above is also full, so the top of the core chunk is hidden.
+/// chunks[1][2][1] = Some(&full[..]);
+///
+/// let neighborhood = ChunkNeighborhood::new(ChunkShape {}, chunks);
+/// let mut buffer = UnitQuadBuffer::new();
+/// visible_block_faces_with_neighbors(
+///     &neighborhood,
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(buffer.num_quads(), 5 * 16 * 16);
+/// ```
+pub struct ChunkNeighborhood<'a, T, S> {
+    chunks: [[[Option<&'a [T]>; 3]; 3]; 3],
+    chunk_shape: S,
+}
+
+impl<'a, T, S> ChunkNeighborhood<'a, T, S>
+where
+    S: Shape<3, Coord = u32>,
+{
+    /// Panics if the core chunk is missing or any chunk is smaller than `chunk_shape`.
+    pub fn new(chunk_shape: S, chunks: [[[Option<&'a [T]>; 3]; 3]; 3]) -> Self {
+        assert!(chunks[1][1][1].is_some(), "The core chunk must be present");
+        for chunk in chunks.iter().flatten().flatten().flatten() {
+            assert!(
+                chunk_shape.size() as usize <= chunk.len(),
+                "chunk size {:?} is less than the shape size {:?}; would cause access out of bounds",
+                chunk.len(),
+                chunk_shape.size()
+            );
+        }
+        Self {
+            chunks,
+            chunk_shape,
+        }
+    }
+
+    /// The voxels of the core chunk.
+    #[inline]
+    pub fn core(&self) -> &'a [T] {
+        self.chunks[1][1][1].unwrap()
+    }
+
+    #[inline]
+    pub fn chunk_shape(&self) -> &S {
+        &self.chunk_shape
+    }
+
+    /// Returns the voxel at `p`, relative to the minimum of the core chunk, or `None` if it belongs to a missing
+    /// neighbor.
+    ///
+    /// Panics if `p` is not in the core chunk or one of its neighbors.
+    #[inline]
+    pub fn get(&self, p: IVec3) -> Option<&'a T> {
+        let dims = self.chunk_shape.as_array().map(|d| d as i32);
+        let p = p.to_array();
+        let mut chunk_index = [1; 3];
+        let mut local = [0; 3];
+        for i in 0..3 {
+            let offset = p[i].div_euclid(dims[i]);
+            assert!(offset.abs() <= 1, "p={p:?} is outside of the neighborhood");
+            chunk_index[i] = (offset + 1) as usize;
+            local[i] = p[i].rem_euclid(dims[i]) as u32;
+        }
+        let [x, y, z] = chunk_index;
+        self.chunks[x][y][z].map(|chunk| &chunk[self.chunk_shape.linearize(local) as usize])
+    }
+}
+
+/// Same as [`visible_block_faces`](crate::visible_block_faces), but meshes every voxel of the core chunk in
+/// `neighborhood`, which doesn't need any padding. Faces on the boundary of the core chunk are culled using the
+/// neighboring chunks.
+///
+/// The quads are in the coordinates of the core chunk, so the minimum of each quad is in `[0, chunk_shape)`.
+pub fn visible_block_faces_with_neighbors<T, S, C>(
+    neighborhood: &ChunkNeighborhood<T, S>,
+    faces: &[OrientedBlockFace; 6],
+    output: &mut UnitQuadBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<T>,
+{
+    let voxels = neighborhood.core();
+    let chunk_shape = neighborhood.chunk_shape();
+    let extent = Extent::from_min_and_shape(UVec3::ZERO, UVec3::from(chunk_shape.as_array()));
+    // Voxels on the interior only have neighbors in the core chunk.
+    let interior = Extent::from_min_and_shape(UVec3::ONE, extent.shape.max(UVec3::splat(2)) - 2);
+
+    let kernel_strides =
+        faces.map(|face| chunk_shape.linearize(face.signed_normal().as_uvec3().to_array()));
+    let opposite_faces = opposite_face_indices(faces);
+
+    for p in extent.iter3() {
+        let p_array = p.to_array();
+        let p_index = chunk_shape.linearize(p_array);
+        let p_voxel = &voxels[p_index as usize];
+
+        if let VoxelVisibility::Empty = ctx.get_visibility(p_voxel) {
+            continue;
+        }
+
+        let p_is_interior = interior.contains(p);
+        for (face_index, face) in faces.iter().enumerate() {
+            let neighbor_voxel = if p_is_interior {
+                let neighbor_index = p_index.wrapping_add(kernel_strides[face_index]);
+                Some(unsafe { voxels.get_unchecked(neighbor_index as usize) })
+            } else {
+                neighborhood.get(p.as_ivec3() + face.signed_normal())
+            };
+
+            let is_visible = match neighbor_voxel {
+                Some(neighbor_voxel) => face_is_visible_for_face(
+                    p_voxel,
+                    neighbor_voxel,
+                    face_index,
+                    opposite_faces[face_index],
+                    ctx,
+                ),
+                None => ctx.get_visibility_for_face(p_voxel, face_index) != VoxelVisibility::Empty,
+            };
+            if is_visible {
+                output.groups[face_index].push(UnorientedUnitQuad { minimum: p_array });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, EMPTY};
+    use crate::{visible_block_faces, DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::{ConstShape, ConstShape3u32};
+
+    #[test]
+    fn matches_meshing_a_padded_copy() {
+        // A sphere centered on the corner of the core chunk, so it crosses into every neighbor.
+        let is_solid = |p: IVec3| (p - IVec3::splat(8)).as_vec3().length() < 6.0;
+
+        let chunk_shape = ChunkShape {};
+        let neighbors_shape = NeighborsShape {};
+        let mut chunks = vec![[EMPTY; ChunkShape::SIZE as usize]; 27];
+        for (chunk_index, chunk) in chunks.iter_mut().enumerate() {
+            let offset = IVec3::from(
+                neighbors_shape
+                    .delinearize(chunk_index as u32)
+                    .map(|i| i as i32),
+            ) - 1;
+            for i in 0..ChunkShape::SIZE {
+                let p = IVec3::from(chunk_shape.delinearize(i).map(|c| c as i32)) + 8 * offset;
+                chunk[i as usize] = BoolVoxel(is_solid(p), false);
+            }
+        }
+        let mut chunk_refs = [[[None; 3]; 3]; 3];
+        for (chunk_index, chunk) in chunks.iter().enumerate() {
+            let [x, y, z] = neighbors_shape
+                .delinearize(chunk_index as u32)
+                .map(|i| i as usize);
+            chunk_refs[x][y][z] = Some(&chunk[..]);
+        }
+
+        let neighborhood = ChunkNeighborhood::new(chunk_shape, chunk_refs);
+        let mut buffer = UnitQuadBuffer::new();
+        visible_block_faces_with_neighbors(
+            &neighborhood,
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        let padded_shape = PaddedShape {};
+        let mut padded = [EMPTY; PaddedShape::SIZE as usize];
+        for i in 0..PaddedShape::SIZE {
+            let p = IVec3::from(padded_shape.delinearize(i).map(|c| c as i32)) - 1;
+            padded[i as usize] = BoolVoxel(is_solid(p), false);
+        }
+        let mut padded_buffer = UnitQuadBuffer::new();
+        visible_block_faces(
+            &padded,
+            &padded_shape,
+            [0; 3],
+            [9; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut padded_buffer,
+            &DefaultVoxelContext,
+        );
+
+        assert!(buffer.num_quads() > 0);
+        for (group, padded_group) in buffer.groups.iter().zip(padded_buffer.groups.iter()) {
+            let padded_minimums: Vec<_> = padded_group
+                .iter()
+                .map(|quad| quad.minimum.map(|c| c - 1))
+                .collect();
+            let minimums: Vec<_> = group.iter().map(|quad| quad.minimum).collect();
+            assert_eq!(minimums, padded_minimums);
+        }
+    }
+
+    type ChunkShape = ConstShape3u32<8, 8, 8>;
+    type PaddedShape = ConstShape3u32<10, 10, 10>;
+    type NeighborsShape = ConstShape3u32<3, 3, 3>;
+}
@@ -0,0 +1,196 @@
+use crate::{
+    greedy_quads, ContextAdapter, ForwardingContext, GreedyQuadsBuffer, MergeContextAdapter,
+    MergeVoxelContext, OrientedBlockFace, VoxelContext,
+};
+
+use ndshape::Shape;
+
+/// Same as [`greedy_quads`], but the voxels are given as run-length encoded `(voxel, run_length)` pairs in linearized
+/// order, so the full voxel array never needs to be materialized.
+///
+/// The run lengths must add up to exactly the size of `voxels_shape`.
+///
+/// This currently expands the runs into an array with the index of the run containing each voxel, which takes 4 bytes
+/// per voxel and is allocated on every call. That is usually much less than a decompressed voxel array, and voxels are
+/// never cloned.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // A slab of solid voxels from z = 1 to z = 8.
+/// let layer = 18 * 18;
+/// let runs = [
+///     (BoolVoxel(false), layer),
+///     (BoolVoxel(true), 8 * layer),
+///     (BoolVoxel(false), 9 * layer),
+/// ];
+///
+/// let mut buffer = GreedyQuadsBuffer::new(ChunkShape::USIZE);
+/// greedy_quads_rle(
+///     &runs,
+///     &ChunkShape {},
+///     [0; 3],
+///     [17; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// // The slab fills the padding on the sides, so only the top and bottom are visible.
+/// assert_eq!(buffer.quads.num_quads(), 2);
+/// ```
+pub fn greedy_quads_rle<T, S, C>(
+    runs: &[(T, u32)],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    let total_length: u64 = runs.iter().map(|&(_, length)| length as u64).sum();
+    assert_eq!(
+        total_length,
+        voxels_shape.size() as u64,
+        "The run lengths must add up to the size of the shape"
+    );
+
+    let mut run_indices = Vec::with_capacity(voxels_shape.usize());
+    for (run_index, &(_, length)) in runs.iter().enumerate() {
+        run_indices.extend(std::iter::repeat_n(run_index as u32, length as usize));
+    }
+
+    let rle_ctx = ForwardingContext(RleContext { runs, ctx });
+    greedy_quads(
+        &run_indices,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        output,
+        &rle_ctx,
+    );
+}
+
+/// Reads the voxel of each run index from `runs`.
+struct RleContext<'a, T, C> {
+    runs: &'a [(T, u32)],
+    ctx: &'a C,
+}
+
+impl<'a, T, C> ContextAdapter<u32> for RleContext<'a, T, C>
+where
+    C: VoxelContext<T>,
+{
+    type Voxel = T;
+    type Inner = C;
+
+    #[inline]
+    fn inner(&self) -> &C {
+        self.ctx
+    }
+
+    #[inline]
+    fn voxel<'b>(&'b self, run_index: &'b u32) -> Option<&'b T> {
+        Some(&self.runs[*run_index as usize].0)
+    }
+}
+
+impl<'a, T, C> MergeContextAdapter<u32> for RleContext<'a, T, C>
+where
+    C: MergeVoxelContext<T>,
+{
+    type MergeValue = C::MergeValue;
+    type MergeValueFacingNeighbour = C::MergeValueFacingNeighbour;
+
+    #[inline]
+    fn merge_value(&self, run_index: &u32) -> Self::MergeValue {
+        self.ctx.merge_value(&self.runs[*run_index as usize].0)
+    }
+
+    #[inline]
+    fn merge_value_facing_neighbour(&self, run_index: &u32) -> Self::MergeValueFacingNeighbour {
+        self.ctx
+            .merge_value_facing_neighbour(&self.runs[*run_index as usize].0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{split_sphere, BoolVoxel, SampleShape, EMPTY};
+    use crate::{DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::ConstShape;
+
+    #[test]
+    fn rle_matches_flat_array() {
+        let shape = SampleShape {};
+        let samples = split_sphere();
+
+        let mut runs: Vec<(BoolVoxel, u32)> = Vec::new();
+        for &voxel in samples.iter() {
+            match runs.last_mut() {
+                Some((last, length)) if *last == voxel => *length += 1,
+                _ => runs.push((voxel, 1)),
+            }
+        }
+        assert!(runs.len() < samples.len() / 10);
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_rle(
+            &runs,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(buffer.quads.groups, expected.quads.groups);
+    }
+
+    #[test]
+    #[should_panic]
+    fn panics_with_short_runs() {
+        let runs = [(EMPTY, SampleShape::SIZE - 1)];
+        let mut buffer = GreedyQuadsBuffer::new(SampleShape::USIZE);
+        greedy_quads_rle(
+            &runs,
+            &SampleShape {},
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use ndshape::Shape;
+
+/// A runtime-sized chunk shape that includes the 1-voxel boundary padding required by the meshing algorithms.
+///
+/// This is the runtime equivalent of using something like `ConstShape3u32<18, 18, 18>` for a 16^3 chunk, and it
+/// linearizes points in the same order (X fastest, then Y, then Z). The interior voxels that get meshed are
+/// `[1; 3]..=dims`, and the whole padded array is `[0; 3]..=dims + 1`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// # use block_mesh::ndshape::Shape;
+/// let shape = PaddedChunkShape::new([16, 32, 16]);
+/// assert_eq!(shape.as_array(), [18, 34, 18]);
+/// assert_eq!(shape.interior_min(), [1; 3]);
+/// assert_eq!(shape.interior_max(), [16, 32, 16]);
+///
+/// // These are the `min` and `max` arguments for the meshing functions.
+/// assert_eq!(shape.min(), [0; 3]);
+/// assert_eq!(shape.max(), [17, 33, 17]);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PaddedChunkShape {
+    dims: [u32; 3],
+    padded: [u32; 3],
+}
+
+impl PaddedChunkShape {
+    /// Creates a shape for a chunk with `dims` interior voxels along each axis, plus 1 voxel of padding on every side.
+    pub fn new(dims: [u32; 3]) -> Self {
+        assert!(
+            dims.iter().all(|&d| d > 0),
+            "Invalid chunk dims={dims:?}; must be positive"
+        );
+        let padded = dims.map(|d| d.checked_add(2).expect("chunk dims are too large"));
+        checked_shape_size(padded);
+        Self { dims, padded }
+    }
+
+    /// The number of interior (unpadded) voxels along each axis.
+    #[inline]
+    pub fn dims(&self) -> [u32; 3] {
+        self.dims
+    }
+
+    /// The minimum interior voxel.
+    #[inline]
+    pub fn interior_min(&self) -> [u32; 3] {
+        [1; 3]
+    }
+
+    /// The maximum interior voxel.
+    #[inline]
+    pub fn interior_max(&self) -> [u32; 3] {
+        self.dims
+    }
+
+    /// The minimum voxel of the padded array, to be passed as the `min` argument of the meshing functions.
+    #[inline]
+    pub fn min(&self) -> [u32; 3] {
+        [0; 3]
+    }
+
+    /// The maximum voxel of the padded array, to be passed as the `max` argument of the meshing functions.
+    #[inline]
+    pub fn max(&self) -> [u32; 3] {
+        self.dims.map(|d| d + 1)
+    }
+}
+
+impl Shape<3> for PaddedChunkShape {
+    type Coord = u32;
+
+    #[inline]
+    fn size(&self) -> u32 {
+        self.padded[0] * self.padded[1] * self.padded[2]
+    }
+
+    #[inline]
+    fn usize(&self) -> usize {
+        self.size() as usize
+    }
+
+    #[inline]
+    fn as_array(&self) -> [u32; 3] {
+        self.padded
+    }
+
+    #[inline]
+    fn linearize(&self, [x, y, z]: [u32; 3]) -> u32 {
+        // Wrapping so that negative offsets (as unsigned vectors) produce strides that can be added to indices.
+        x.wrapping_add(self.padded[0].wrapping_mul(y.wrapping_add(self.padded[1].wrapping_mul(z))))
+    }
+
+    #[inline]
+    fn delinearize(&self, i: u32) -> [u32; 3] {
+        let xy = self.padded[0] * self.padded[1];
+        let z = i / xy;
+        let rem = i % xy;
+        [rem % self.padded[0], rem / self.padded[0], z]
+    }
+}
+
+/// Returns the number of voxels in an array with dimensions `dims`, or panics if the array is too large for its linear
+/// indices and strides to fit in a `u32`, which would silently wrap while meshing.
+///
+/// This is a `const fn`, so it can check the dimensions of a constant shape at compile time.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// type ChunkShape = ConstShape3u32<34, 34, 34>;
+/// const _: u32 = checked_shape_size(ChunkShape::ARRAY);
+/// ```
+///
+/// Shapes that are too large fail to compile:
+///
+/// ```compile_fail
+/// # use block_mesh::*;
+/// const _: u32 = checked_shape_size([2048, 2048, 1024]);
+/// ```
+pub const fn checked_shape_size(dims: [u32; 3]) -> u32 {
+    let size = dims[0] as u64 * dims[1] as u64 * dims[2] as u64;
+    assert!(
+        size <= u32::MAX as u64,
+        "The shape has too many voxels to be indexed with u32 strides"
+    );
+    size as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_shape_size_accepts_u32_max_voxels() {
+        // 65537 * 65535 == u32::MAX
+        assert_eq!(checked_shape_size([65537, 65535, 1]), u32::MAX);
+        assert_eq!(checked_shape_size([1, 65535, 65537]), u32::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn checked_shape_size_panics_past_u32_max_voxels() {
+        // One more than u32::MAX.
+        checked_shape_size([65536, 65536, 1]);
+    }
+
+    #[test]
+    fn padded_chunk_shape_round_trips_non_cubic_dims() {
+        for dims in [[1, 2, 3], [5, 3, 2], [16, 1, 7]] {
+            let shape = PaddedChunkShape::new(dims);
+            let [px, py, pz] = shape.as_array();
+            assert_eq!(shape.size(), px * py * pz);
+
+            let mut i = 0;
+            for z in 0..pz {
+                for y in 0..py {
+                    for x in 0..px {
+                        assert_eq!(shape.linearize([x, y, z]), i);
+                        assert_eq!(shape.delinearize(i), [x, y, z]);
+                        i += 1;
+                    }
+                }
+            }
+        }
+    }
+}
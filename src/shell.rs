@@ -0,0 +1,276 @@
+use crate::{
+    bounds::assert_in_bounds,
+    geometry::opposite_face_indices,
+    simple::{face_is_visible_for_face, for_each_visible_face_where},
+    GreedyMeshPlan, GreedyQuadsBuffer, GreedyQuadsConfig, MergeVoxelContext, OrientedBlockFace,
+    UnitQuadBuffer, UnorientedUnitQuad, VoxelContext, VoxelMerger,
+};
+
+use ilattice::glam::UVec3;
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// A mask of the voxels that can't have any visible faces, computed by [`surface_shell_view`].
+///
+/// The remaining voxels form the surface shell of the solid regions. Meshing with
+/// [`visible_block_faces_with_surface_shell`] or [`greedy_quads_with_surface_shell`] skips the buried voxels without
+/// checking their neighbors, which saves work on very dense chunks. The output is the same as without the mask.
+///
+/// The mask is only valid for the voxels it was computed from.
+pub struct SurfaceShell {
+    buried: Vec<bool>,
+}
+
+impl SurfaceShell {
+    /// Returns `true` iff the voxel at linear index `index` has no visible faces.
+    #[inline]
+    pub fn is_buried(&self, index: u32) -> bool {
+        self.buried[index as usize]
+    }
+
+    /// The number of voxels that are skipped when meshing.
+    pub fn num_buried(&self) -> usize {
+        self.buried.iter().filter(|&&b| b).count()
+    }
+}
+
+/// Finds every voxel on the interior of `[min, max]` whose faces are all hidden, e.g. an opaque voxel whose six
+/// neighbors are all opaque. Visibility is determined exactly as in [`visible_block_faces`](crate::visible_block_faces),
+/// and `faces` must be the same faces that are used for meshing.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // A solid cube of 16^3 voxels.
+/// let mut voxels = [BoolVoxel(true); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     let p = ChunkShape::delinearize(i);
+///     voxels[i as usize] = BoolVoxel(p.iter().all(|&c| (1..=16).contains(&c)));
+/// }
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let shell = surface_shell_view(&voxels, &ChunkShape {}, [0; 3], [17; 3], faces, &DefaultVoxelContext);
+/// assert_eq!(shell.num_buried(), 14 * 14 * 14);
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_with_surface_shell(
+///     &shell,
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [17; 3],
+///     faces,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// ```
+pub fn surface_shell_view<T, S, C>(
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    ctx: &C,
+) -> SurfaceShell
+where
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<T>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    let interior = interior_extent(min, max);
+    let kernel_strides =
+        faces.map(|face| voxels_shape.linearize(face.signed_normal().as_uvec3().to_array()));
+    let opposite_faces = opposite_face_indices(faces);
+
+    let mut buried = vec![false; voxels.len()];
+    for p in interior.iter3() {
+        let p_index = voxels_shape.linearize(p.to_array());
+        let p_voxel = &voxels[p_index as usize];
+        buried[p_index as usize] =
+            kernel_strides
+                .iter()
+                .enumerate()
+                .all(|(face_index, &face_stride)| {
+                    let neighbor_voxel = &voxels[p_index.wrapping_add(face_stride) as usize];
+                    !face_is_visible_for_face(
+                        p_voxel,
+                        neighbor_voxel,
+                        face_index,
+                        opposite_faces[face_index],
+                        ctx,
+                    )
+                });
+    }
+
+    SurfaceShell { buried }
+}
+
+/// Same as [`visible_block_faces`](crate::visible_block_faces), but skips the voxels that are buried in `shell`.
+#[allow(clippy::too_many_arguments)]
+pub fn visible_block_faces_with_surface_shell<T, S, C>(
+    shell: &SurfaceShell,
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut UnitQuadBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<T>,
+{
+    assert_shell_fits(shell, voxels);
+
+    for_each_visible_face_where(
+        voxels,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        |_, p_index| !shell.is_buried(p_index),
+        ctx,
+        |p, _, face_index| {
+            output.groups[face_index].push(UnorientedUnitQuad { minimum: p });
+        },
+    );
+}
+
+/// Same as [`greedy_quads`](crate::greedy_quads), but skips the voxels that are buried in `shell`.
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_surface_shell<T, S, C>(
+    shell: &SurfaceShell,
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    assert_shell_fits(shell, voxels);
+
+    GreedyMeshPlan::new(voxels_shape, faces, min, max).mesh_skipping::<_, VoxelMerger<T>, _>(
+        voxels,
+        &GreedyQuadsConfig::default(),
+        [Some(&shell.buried); 6],
+        output,
+        None,
+        ctx,
+    );
+}
+
+pub(crate) fn interior_extent(min: [u32; 3], max: [u32; 3]) -> Extent<UVec3> {
+    let min = UVec3::from(min).as_ivec3();
+    let max = UVec3::from(max).as_ivec3();
+    let interior = Extent::from_min_and_max(min, max).padded(-1); // Avoid accessing out of bounds with a 3x3x3 kernel.
+    Extent::from_min_and_shape(interior.minimum.as_uvec3(), interior.shape.as_uvec3())
+}
+
+fn assert_shell_fits<T>(shell: &SurfaceShell, voxels: &[T]) {
+    assert_eq!(
+        shell.buried.len(),
+        voxels.len(),
+        "The surface shell was computed for a different voxel array"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{BoolVoxel, SampleShape, EMPTY};
+    use crate::{greedy_quads, visible_block_faces, DefaultVoxelContext, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::ConstShape;
+
+    #[test]
+    fn surface_shell_matches_full_meshing() {
+        // Dense noise-like terrain with a few holes.
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let [x, y, z] = shape.delinearize(i);
+            let height = 20 + (x * 7 + z * 3) % 9;
+            let hole = (x * 5 + y * 11 + z * 13) % 37 == 0;
+            samples[i as usize] = BoolVoxel(y < height && !hole, (x + z) % 3 == 0);
+        }
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let shell = surface_shell_view(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &DefaultVoxelContext,
+        );
+        assert!(shell.num_buried() > 0);
+
+        let mut expected_unit = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected_unit,
+            &DefaultVoxelContext,
+        );
+        let mut unit = UnitQuadBuffer::new();
+        visible_block_faces_with_surface_shell(
+            &shell,
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut unit,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(unit.groups, expected_unit.groups);
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_surface_shell(
+            &shell,
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(buffer.quads.groups, expected.quads.groups);
+    }
+}
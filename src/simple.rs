@@ -1,72 +1,156 @@
 use crate::{
-    bounds::assert_in_bounds, OrientedBlockFace, UnitQuadBuffer, UnorientedUnitQuad, VoxelVisibility,
+    bounds::assert_in_bounds, geometry::opposite_face_indices, shell::interior_extent, MeshData,
+    OrientedBlockFace, QuadCoordinateConfig, UnitQuadBuffer, UnorientedQuad, UnorientedUnitQuad,
+    VoxelVisibility,
 };
-use crate::VoxelContext;
+use crate::{VoxelContext, VoxelSource};
 
-use ilattice::glam::UVec3;
+use ilattice::glam::{UVec3, Vec3};
 use ilattice::prelude::Extent;
 use ndshape::Shape;
 
 /// A fast and simple meshing algorithm that produces a single quad for every visible face of a block.
 ///
 /// This is faster than [`greedy_quads`](crate::greedy_quads) but it produces many more quads.
-pub fn visible_block_faces<T, S, C>(
-    voxels: &[T],
+pub fn visible_block_faces<V, S, C>(
+    voxels: &V,
     voxels_shape: &S,
     min: [u32; 3],
     max: [u32; 3],
     faces: &[OrientedBlockFace; 6],
     output: &mut UnitQuadBuffer,
-    ctx: &C
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    visible_block_faces_with_voxel_view(voxels, voxels_shape, min, max, faces, output, ctx)
+}
+
+/// Same as [`visible_block_faces`](visible_block_faces),
+/// with the additional ability to interpret the array as some other type.
+/// Use this if you want to mesh the same array multiple times
+/// with different sets of voxels being visible.
+pub fn visible_block_faces_with_voxel_view<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut UnitQuadBuffer,
+    ctx: &C,
 ) where
+    V: VoxelSource + ?Sized,
+    C: VoxelContext<V::Voxel>,
     S: Shape<3, Coord = u32>,
-    C: VoxelContext<T>
 {
-    visible_block_faces_with_voxel_view(
+    for_each_visible_face(
         voxels,
         voxels_shape,
         min,
         max,
         faces,
-        output,
         ctx,
-    )
+        |p, _, face_index| {
+            output.groups[face_index].push(UnorientedUnitQuad { minimum: p });
+        },
+    );
 }
 
-/// Same as [`visible_block_faces`](visible_block_faces),
-/// with the additional ability to interpret the array as some other type.
-/// Use this if you want to mesh the same array multiple times
-/// with different sets of voxels being visible.
-pub fn visible_block_faces_with_voxel_view<'a, T, S, C>(
-    voxels: &'a [T],
+/// Calls `f(p, p_index, face_index)` for every face that [`visible_block_faces`] would generate a quad for, where `p` is
+/// the voxel, `p_index` is its linear index in `voxels_shape`, and `face_index` is the index of the face in `faces`.
+///
+/// Faces are visited voxel by voxel in the order of [`Extent::iter3`] over the interior of `[min, max]`, and in the
+/// order of `faces` for each voxel. The other meshing functions in this module are built on this loop, so it's the
+/// place to start for outputs that they don't cover.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<4, 3, 3>;
+///
+/// // Two blocks side by side along X.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// voxels[ChunkShape::linearize([2, 1, 1]) as usize] = BoolVoxel(true);
+///
+/// let mut visible = Vec::new();
+/// for_each_visible_face(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [3, 2, 2],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &DefaultVoxelContext,
+///     |p, _, face_index| visible.push((p, face_index)),
+/// );
+/// let pos_x = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosX);
+/// assert_eq!(visible.len(), 10);
+/// assert!(!visible.contains(&([1, 1, 1], pos_x)));
+/// assert!(visible.contains(&([2, 1, 1], pos_x)));
+/// ```
+pub fn for_each_visible_face<V, S, C, F>(
+    voxels: &V,
     voxels_shape: &S,
     min: [u32; 3],
     max: [u32; 3],
     faces: &[OrientedBlockFace; 6],
-    output: &mut UnitQuadBuffer,
     ctx: &C,
+    f: F,
 ) where
-    C: VoxelContext<T>,
+    V: VoxelSource + ?Sized,
     S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+    F: FnMut([u32; 3], u32, usize),
 {
-    assert_in_bounds(voxels, voxels_shape, min, max);
+    for_each_visible_face_where(voxels, voxels_shape, min, max, faces, |_, _| true, ctx, f);
+}
 
-    let min = UVec3::from(min).as_ivec3();
-    let max = UVec3::from(max).as_ivec3();
-    let extent = Extent::from_min_and_max(min, max);
-    let interior = extent.padded(-1); // Avoid accessing out of bounds with a 3x3x3 kernel.
-    let interior =
-        Extent::from_min_and_shape(interior.minimum.as_uvec3(), interior.shape.as_uvec3());
+/// Same as [`for_each_visible_face`], but the voxels where `include(p, p_index)` returns `false` are skipped without
+/// being read.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn for_each_visible_face_where<V, S, C, I, F>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    mut include: I,
+    ctx: &C,
+    mut f: F,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+    I: FnMut([u32; 3], u32) -> bool,
+    F: FnMut([u32; 3], u32, usize),
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
 
+    let interior = interior_extent(min, max);
     let kernel_strides =
         faces.map(|face| voxels_shape.linearize(face.signed_normal().as_uvec3().to_array()));
+    let opposite_faces = opposite_face_indices(faces);
 
     for p in interior.iter3() {
         let p_array = p.to_array();
         let p_index = voxels_shape.linearize(p_array);
+        if !include(p_array, p_index) {
+            continue;
+        }
         let p_voxel = unsafe { voxels.get_unchecked(p_index as usize) };
 
-        if let VoxelVisibility::Empty = ctx.get_visibility(&p_voxel) {
+        if let VoxelVisibility::Empty = ctx.get_visibility(p_voxel) {
             continue;
         }
 
@@ -74,28 +158,652 @@ pub fn visible_block_faces_with_voxel_view<'a, T, S, C>(
             let neighbor_index = p_index.wrapping_add(face_stride);
             let neighbor_voxel = unsafe { voxels.get_unchecked(neighbor_index as usize) };
 
-            // TODO: If the face lies between two transparent voxels, we choose not to mesh it. We might need to extend the
-            // IsOpaque trait with different levels of transparency to support this.
-            let face_needs_mesh = match ctx.get_visibility(&neighbor_voxel) {
-                VoxelVisibility::Empty => true,
-                VoxelVisibility::Translucent => {
-                    ctx.get_visibility(&p_voxel) == VoxelVisibility::Opaque
-                }
-                VoxelVisibility::Opaque => false,
-            };
+            if face_is_visible_for_face(
+                p_voxel,
+                neighbor_voxel,
+                face_index,
+                opposite_faces[face_index],
+                ctx,
+            ) {
+                f(p_array, p_index, face_index);
+            }
+        }
+    }
+}
+
+/// The visible faces of every voxel on the interior of `[min, max]`, as found by [`for_each_visible_face`], for
+/// algorithms that also need the visibility of the faces around each face.
+pub(crate) struct VisibleFaceMask {
+    interior: Extent<UVec3>,
+    /// Bit `face_index` is set for each visible face, with the voxels in the order of [`Extent::iter3`].
+    masks: Vec<u8>,
+}
+
+impl VisibleFaceMask {
+    pub(crate) fn new<V, S, C>(
+        voxels: &V,
+        voxels_shape: &S,
+        min: [u32; 3],
+        max: [u32; 3],
+        faces: &[OrientedBlockFace; 6],
+        ctx: &C,
+    ) -> Self
+    where
+        V: VoxelSource + ?Sized,
+        S: Shape<3, Coord = u32>,
+        C: VoxelContext<V::Voxel>,
+    {
+        let interior = interior_extent(min, max);
+        let mut mask = Self {
+            interior,
+            masks: vec![0; interior.num_points() as usize],
+        };
+        for_each_visible_face(
+            voxels,
+            voxels_shape,
+            min,
+            max,
+            faces,
+            ctx,
+            |p, _, face_index| {
+                let i = mask.index(p.into());
+                mask.masks[i] |= 1 << face_index;
+            },
+        );
+        mask
+    }
+
+    /// The interior of `[min, max]`.
+    #[inline]
+    pub(crate) fn interior(&self) -> Extent<UVec3> {
+        self.interior
+    }
+
+    /// Returns `true` iff the face of the voxel at `p` with the normal of `faces[face_index]` is visible. Faces outside
+    /// of the interior are never visible.
+    #[inline]
+    pub(crate) fn is_visible(&self, p: UVec3, face_index: usize) -> bool {
+        self.interior.contains(p) && self.masks[self.index(p)] & (1 << face_index) != 0
+    }
+
+    #[inline]
+    fn index(&self, p: UVec3) -> usize {
+        let [x, y, z] = (p - self.interior.minimum).to_array();
+        let [width, height, _] = self.interior.shape.to_array();
+        (x + width * (y + height * z)) as usize
+    }
+}
 
-            if face_needs_mesh {
-                output.groups[face_index].push(UnorientedUnitQuad { minimum: p_array });
+/// Counts the faces that [`visible_block_faces`] would generate for each of the `faces`, without building any quads.
+///
+/// This is cheaper than meshing when only surface metrics are needed, e.g. for simulation. The exposed area in each
+/// direction is the count times the area of one voxel face.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<4, 4, 4>;
+///
+/// // Two blocks side by side along X.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// voxels[ChunkShape::linearize([2, 1, 1]) as usize] = BoolVoxel(true);
+///
+/// let counts = count_exposed_faces(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [3; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &DefaultVoxelContext,
+/// );
+/// let pos_x = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosX);
+/// let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+/// assert_eq!(counts[pos_x], 1);
+/// assert_eq!(counts[pos_y], 2);
+/// assert_eq!(counts.iter().sum::<u32>(), 10);
+/// ```
+pub fn count_exposed_faces<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    ctx: &C,
+) -> [u32; 6]
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    let mut counts = [0; 6];
+    for_each_visible_face(
+        voxels,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        ctx,
+        |_, _, face_index| counts[face_index] += 1,
+    );
+    counts
+}
+
+/// [`estimate_mesh_cost`] checks the voxels where `(x + 2y + 3z) % COST_SAMPLE_PERIOD == 0`. The period is prime and no
+/// sum of the coefficients is a multiple of it, so this is a seventh of the voxels in every slice along each axis and
+/// diagonal, and flat or sloped surfaces aren't sampled unevenly.
+const COST_SAMPLE_PERIOD: u32 = 7;
+
+/// Estimates the number of visible faces on the interior of `[min, max]`, as a cheap measure of how expensive the
+/// chunk is to mesh, e.g. for balancing chunks between meshing threads.
+///
+/// Only a sparse lattice of voxels is checked, and their faces are scaled up to the whole interior, so this reads about
+/// a seventh of the voxels that [`count_exposed_faces`] does. Visibility is determined exactly as in
+/// [`count_exposed_faces`]. The estimate is approximate and can be off by quite a bit for small features that the
+/// lattice skips over, but an empty or fully buried interior always gives 0.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // The bottom half of the chunk is solid.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     voxels[i as usize] = BoolVoxel(ChunkShape::delinearize(i)[1] <= 8);
+/// }
+///
+/// // The exact count is the 16x16 faces on top.
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let cost = estimate_mesh_cost(&voxels, &ChunkShape {}, [0; 3], [17; 3], faces, &DefaultVoxelContext);
+/// assert!(cost.abs_diff(16 * 16) < 10);
+///
+/// let empty = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// assert_eq!(estimate_mesh_cost(&empty, &ChunkShape {}, [0; 3], [17; 3], faces, &DefaultVoxelContext), 0);
+/// ```
+pub fn estimate_mesh_cost<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    ctx: &C,
+) -> u32
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+
+    let interior_min = UVec3::from(min) + 1;
+    let interior_ub = UVec3::from(max);
+    if interior_min.cmpge(interior_ub).any() {
+        return 0;
+    }
+
+    let mut num_samples = 0u64;
+    let mut num_faces = 0u64;
+    let is_sample = |[x, y, z]: [u32; 3], _| {
+        let is_sample = (x + 2 * y + 3 * z) % COST_SAMPLE_PERIOD == 0;
+        num_samples += is_sample as u64;
+        is_sample
+    };
+    for_each_visible_face_where(
+        voxels,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        is_sample,
+        ctx,
+        |_, _, _| num_faces += 1,
+    );
+
+    if num_samples == 0 {
+        return 0;
+    }
+    let volume = (interior_ub - interior_min)
+        .to_array()
+        .iter()
+        .map(|&d| d as u64)
+        .product::<u64>();
+    (num_faces * volume / num_samples) as u32
+}
+
+/// Returns the coordinates of every voxel on the interior of `[min, max]` whose face with the normal of
+/// `faces[face_index]` is visible, in the same order that [`visible_block_faces`] would generate their quads.
+///
+/// This is the same visibility test as meshing, for gameplay systems that need positions instead of geometry, like
+/// placing grass tufts or torches on every block with an exposed top.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<4, 5, 4>;
+///
+/// // A column of two blocks.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// voxels[ChunkShape::linearize([1, 2, 1]) as usize] = BoolVoxel(true);
+///
+/// let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+/// let tops = surface_voxels_facing(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [3, 4, 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     pos_y,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(tops, vec![[1, 2, 1]]);
+/// ```
+pub fn surface_voxels_facing<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    face_index: usize,
+    ctx: &C,
+) -> Vec<[u32; 3]>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    let mut surface = Vec::new();
+    for_each_visible_face(
+        voxels,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        ctx,
+        |p, _, visible_face_index| {
+            if visible_face_index == face_index {
+                surface.push(p);
+            }
+        },
+    );
+    surface
+}
+
+/// Lists every visible face of a voxel on the interior of `[min, max]` whose neighbor is [`VoxelVisibility::Empty`], as
+/// the coordinates of the voxel and the index of the face in `faces`.
+///
+/// These are the faces where light from open space enters a solid region, so they can seed a flood-fill lighting
+/// engine. Faces next to translucent voxels are visible but not listed. Visibility is determined exactly as in
+/// [`visible_block_faces`], and faces are listed voxel by voxel in the order of `faces`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// #[derive(Clone, Copy, Eq, PartialEq)]
+/// enum Block {
+///     Air,
+///     Stone,
+///     Water,
+/// }
+///
+/// impl Voxel for Block {
+///     fn get_visibility(&self) -> VoxelVisibility {
+///         match self {
+///             Block::Air => VoxelVisibility::Empty,
+///             Block::Stone => VoxelVisibility::Opaque,
+///             Block::Water => VoxelVisibility::Translucent,
+///         }
+///     }
+/// }
+///
+/// type ChunkShape = ConstShape3u32<3, 4, 3>;
+///
+/// // A stone block under a water block.
+/// let mut voxels = [Block::Air; ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = Block::Stone;
+/// voxels[ChunkShape::linearize([1, 2, 1]) as usize] = Block::Water;
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let exposed = exposed_air_faces(&voxels, &ChunkShape {}, [0; 3], [2, 3, 2], faces, &DefaultVoxelContext);
+/// // Every face of both blocks except the top of the stone.
+/// let stone_top = ([1, 1, 1], RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY));
+/// assert_eq!(exposed.len(), 5 + 5);
+/// assert!(!exposed.contains(&stone_top));
+/// ```
+pub fn exposed_air_faces<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    ctx: &C,
+) -> Vec<([u32; 3], usize)>
+where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    let kernel_strides =
+        faces.map(|face| voxels_shape.linearize(face.signed_normal().as_uvec3().to_array()));
+
+    let mut exposed = Vec::new();
+    for_each_visible_face(
+        voxels,
+        voxels_shape,
+        min,
+        max,
+        faces,
+        ctx,
+        |p, p_index, face_index| {
+            let neighbor_index = p_index.wrapping_add(kernel_strides[face_index]);
+            let neighbor_voxel = unsafe { voxels.get_unchecked(neighbor_index as usize) };
+            if ctx.get_visibility(neighbor_voxel) == VoxelVisibility::Empty {
+                exposed.push((p, face_index));
+            }
+        },
+    );
+    exposed
+}
+
+/// Generates a mesh with the same vertices and triangles in the same order for any voxels of the same shape, so
+/// meshes of different states of a chunk can be used as morph targets of each other.
+///
+/// Every voxel on the interior of `[min, max]` gets one quad for each face in `config.faces`, whether or not it is
+/// visible. Visibility is determined exactly as in [`visible_block_faces`], and the 4 vertices of each hidden face are
+/// collapsed to its center, so it has no area. Quads are emitted voxel by voxel in the order of
+/// [`Extent::iter3`] over the interior, with the 6 faces of each voxel in the order of `config.faces`, so the `k`th
+/// voxel of the interior always uses vertices `24 * k..24 * (k + 1)`.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<4, 3, 3>;
+///
+/// let mut before = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// before[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// let mut after = before;
+/// after[ChunkShape::linearize([2, 1, 1]) as usize] = BoolVoxel(true);
+///
+/// let mesh = |voxels: &[BoolVoxel]| {
+///     let mut mesh = MeshData::new();
+///     visible_block_faces_stable(
+///         voxels,
+///         &ChunkShape {},
+///         [0; 3],
+///         [3, 2, 2],
+///         &RIGHT_HANDED_Y_UP_CONFIG,
+///         1.0,
+///         &mut mesh,
+///         &DefaultVoxelContext,
+///     );
+///     mesh
+/// };
+/// let (before, after) = (mesh(&before), mesh(&after));
+/// assert_eq!(before.positions.len(), 2 * 6 * 4);
+/// assert_eq!(before.indices, after.indices);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn visible_block_faces_stable<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    config: &QuadCoordinateConfig,
+    voxel_size: f32,
+    output: &mut MeshData,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    let visible = VisibleFaceMask::new(voxels, voxels_shape, min, max, &config.faces, ctx);
+
+    for p in visible.interior().iter3() {
+        let quad = UnorientedQuad {
+            minimum: p.to_array(),
+            width: 1,
+            height: 1,
+        };
+
+        for (face_index, face) in config.faces.iter().enumerate() {
+            output.push_quad(face, &quad, config.u_flip_face, voxel_size);
+            if !visible.is_visible(p, face_index) {
+                let center = (voxel_size * face.quad_center(&quad)).to_array();
+                let start = output.positions.len() - 4;
+                output.positions[start..].fill(center);
             }
         }
     }
 }
 
+/// Generates line segments tracing the outline of every visible region of block faces, e.g. for a selection overlay
+/// or a debug wireframe.
+///
+/// Visibility is determined exactly as in [`visible_block_faces`]. For every visible face, each of its four edges is
+/// emitted unless the face on the other side of that edge (in the same plane, with the same orientation) is also
+/// visible. So a flat 3x3 patch of visible faces produces only its 12 perimeter edges. Where two regions with
+/// different orientations meet at a convex corner, the shared edge is emitted once for each region.
+///
+/// Segment endpoints are lattice coordinates, in the same space as [`OrientedBlockFace::quad_corners`].
+pub fn visible_block_edges<V, S, C>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut Vec<[Vec3; 2]>,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<V::Voxel>,
+{
+    let visible = VisibleFaceMask::new(voxels, voxels_shape, min, max, faces, ctx);
+
+    for p in visible.interior().iter3() {
+        for (face_index, face) in faces.iter().enumerate() {
+            if !visible.is_visible(p, face_index) {
+                continue;
+            }
+
+            let unit_quad = UnorientedUnitQuad {
+                minimum: p.to_array(),
+            };
+            let [c0, c1, c2, c3] = face.quad_corners(&unit_quad.into());
+            let edges = [
+                (p - face.u, [c0, c2]),
+                (p + face.u, [c1, c3]),
+                (p - face.v, [c0, c1]),
+                (p + face.v, [c2, c3]),
+            ];
+            for (across, [a, b]) in edges {
+                if !visible.is_visible(across, face_index) {
+                    output.push([a.as_vec3(), b.as_vec3()]);
+                }
+            }
+        }
+    }
+}
+
+/// Returns `true` iff the face between `voxel` and `neighbor` is meshed as a face of `voxel`.
+///
+/// This is the rule used by every meshing algorithm in this crate:
+///
+/// | `voxel` \ `neighbor` | Empty | Translucent | Opaque |
+/// |-----------------------|-------|-------------|--------|
+/// | Empty                 | no    | no          | no     |
+/// | Translucent           | yes   | no          | no     |
+/// | Opaque                | yes   | yes         | no     |
+///
+/// This only uses [`VoxelContext::get_visibility`]; see [`face_is_visible_for_face`] for one-sided voxels.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// # struct V(VoxelVisibility);
+/// # impl Voxel for V {
+/// #     fn get_visibility(&self) -> VoxelVisibility { self.0 }
+/// # }
+/// let opaque = V(VoxelVisibility::Opaque);
+/// let glass = V(VoxelVisibility::Translucent);
+/// assert!(face_is_visible(&opaque, &glass, &DefaultVoxelContext));
+/// assert!(!face_is_visible(&glass, &opaque, &DefaultVoxelContext));
+/// ```
+#[inline]
+pub fn face_is_visible<T, C>(voxel: &T, neighbor: &T, ctx: &C) -> bool
+where
+    C: VoxelContext<T>,
+{
+    visibility_rule(ctx.get_visibility(voxel), ctx.get_visibility(neighbor))
+}
+
+/// Same as [`face_is_visible`], but for the face `face_index` of `voxel`, which touches face `opposite_face_index` of
+/// `neighbor`, using [`VoxelContext::get_visibility_for_face`]. The face indices refer to the `faces` array passed to
+/// the meshing algorithm.
+#[inline]
+pub fn face_is_visible_for_face<T, C>(
+    voxel: &T,
+    neighbor: &T,
+    face_index: usize,
+    opposite_face_index: usize,
+    ctx: &C,
+) -> bool
+where
+    C: VoxelContext<T>,
+{
+    visibility_rule(
+        ctx.get_visibility_for_face(voxel, face_index),
+        ctx.get_visibility_for_face(neighbor, opposite_face_index),
+    )
+}
+
+/// Returns the [`VoxelContext::get_visibility`] of the 6 neighbors of the voxel at linear index `p_index`, where
+/// neighbor `i` is at `p_index + kernel_strides[i]` with wrapping arithmetic.
+///
+/// The strides are usually the linearized normals of the `faces` passed to the meshing algorithm, so the neighbors are
+/// in the same order as the face groups. This is meant for custom meshing code, like a [`MergeStrategy`] or a
+/// [`VoxelContext`], that inspects every neighbor of a voxel.
+///
+/// Panics if any neighbor is out of bounds.
+///
+/// [`MergeStrategy`]: crate::MergeStrategy
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// type ChunkShape = ConstShape3u32<3, 3, 3>;
+///
+/// // A block with another block above it.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// voxels[ChunkShape::linearize([1, 2, 1]) as usize] = BoolVoxel(true);
+///
+/// let config = &RIGHT_HANDED_Y_UP_CONFIG;
+/// let kernel_strides = config
+///     .faces
+///     .map(|face| ChunkShape::linearize(face.signed_normal().as_uvec3().to_array()));
+/// let visibilities = neighbor_visibilities(
+///     ChunkShape::linearize([1, 1, 1]),
+///     &kernel_strides,
+///     &voxels,
+///     &DefaultVoxelContext,
+/// );
+/// for (face_index, visibility) in visibilities.into_iter().enumerate() {
+///     if face_index == config.face_index(SignedAxis::PosY) {
+///         assert_eq!(visibility, VoxelVisibility::Opaque);
+///     } else {
+///         assert_eq!(visibility, VoxelVisibility::Empty);
+///     }
+/// }
+/// ```
+#[inline]
+pub fn neighbor_visibilities<V, C>(
+    p_index: u32,
+    kernel_strides: &[u32; 6],
+    voxels: &V,
+    ctx: &C,
+) -> [VoxelVisibility; 6]
+where
+    V: VoxelSource + ?Sized,
+    C: VoxelContext<V::Voxel>,
+{
+    kernel_strides.map(|stride| {
+        let neighbor_index = p_index.wrapping_add(stride);
+        ctx.get_visibility(voxels.voxel(neighbor_index as usize))
+    })
+}
+
+#[inline]
+fn visibility_rule(visibility: VoxelVisibility, neighbor_visibility: VoxelVisibility) -> bool {
+    if visibility == VoxelVisibility::Empty {
+        return false;
+    }
+
+    // TODO: If the face lies between two transparent voxels, we choose not to mesh it. We might need to extend the
+    // IsOpaque trait with different levels of transparency to support this.
+    match neighbor_visibility {
+        VoxelVisibility::Empty => true,
+        VoxelVisibility::Translucent => visibility == VoxelVisibility::Opaque,
+        VoxelVisibility::Opaque => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{DefaultVoxelContext, Voxel, RIGHT_HANDED_Y_UP_CONFIG};
-    use ndshape::{ConstShape, ConstShape3u32};
+    use crate::test_util::{sphere, BoolVoxel, SampleShape, EMPTY, FULL};
+    use crate::{DefaultVoxelContext, SignedAxis, Voxel, RIGHT_HANDED_Y_UP_CONFIG};
+    use ndshape::ConstShape;
 
     #[test]
     #[should_panic]
@@ -109,7 +817,7 @@ mod tests {
             [34, 33, 33],
             &RIGHT_HANDED_Y_UP_CONFIG.faces,
             &mut buffer,
-            &DefaultVoxelContext
+            &DefaultVoxelContext,
         );
     }
 
@@ -125,25 +833,344 @@ mod tests {
             [33; 3],
             &RIGHT_HANDED_Y_UP_CONFIG.faces,
             &mut buffer,
-            &DefaultVoxelContext
+            &DefaultVoxelContext,
+        );
+    }
+
+    #[test]
+    fn exposed_face_counts_match_visible_block_faces() {
+        let shape = SampleShape {};
+        let samples = sphere();
+
+        let mut buffer = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let counts = count_exposed_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(counts, buffer.groups.map(|group| group.len() as u32));
+    }
+
+    #[test]
+    fn mesh_cost_estimate_is_close_to_exposed_face_count() {
+        let shape = SampleShape {};
+        let samples = sphere();
+
+        let counts = count_exposed_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &DefaultVoxelContext,
+        );
+        let exact = counts.iter().sum::<u32>() as f32;
+        let estimate = estimate_mesh_cost(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &DefaultVoxelContext,
+        ) as f32;
+        assert!((estimate - exact).abs() < 0.1 * exact);
+    }
+
+    #[test]
+    fn surface_voxels_match_visible_block_faces() {
+        let shape = SampleShape {};
+        let samples = sphere();
+
+        let mut buffer = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        for (face_index, group) in buffer.groups.iter().enumerate() {
+            let surface = surface_voxels_facing(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                &RIGHT_HANDED_Y_UP_CONFIG.faces,
+                face_index,
+                &DefaultVoxelContext,
+            );
+            let minimums: Vec<_> = group.iter().map(|quad| quad.minimum).collect();
+            assert_eq!(surface, minimums);
+        }
+    }
+
+    #[test]
+    fn exposed_air_faces_match_visible_block_faces_without_translucency() {
+        let shape = SampleShape {};
+        let samples = sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut buffer = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        let mut expected: Vec<_> = buffer
+            .groups
+            .iter()
+            .enumerate()
+            .flat_map(|(face_index, group)| {
+                group.iter().map(move |quad| (quad.minimum, face_index))
+            })
+            .collect();
+        let mut exposed = exposed_air_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &DefaultVoxelContext,
+        );
+        expected.sort();
+        exposed.sort();
+        assert_eq!(exposed, expected);
+    }
+
+    #[test]
+    fn stable_faces_keep_vertex_order_and_match_visible_faces() {
+        let shape = SampleShape {};
+        let config = &RIGHT_HANDED_Y_UP_CONFIG;
+        let mut meshes = Vec::new();
+        for radius in [6.0, 12.0] {
+            let mut samples = [EMPTY; SampleShape::SIZE as usize];
+            for i in 0..SampleShape::SIZE {
+                let p = UVec3::from(shape.delinearize(i)).as_vec3() - 16.0;
+                if p.length() < radius {
+                    samples[i as usize] = FULL;
+                }
+            }
+            let mut mesh = MeshData::new();
+            visible_block_faces_stable(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                config,
+                1.0,
+                &mut mesh,
+                &DefaultVoxelContext,
+            );
+
+            // The faces with area are exactly the visible faces.
+            let mut unit_quads = UnitQuadBuffer::new();
+            visible_block_faces(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                &config.faces,
+                &mut unit_quads,
+                &DefaultVoxelContext,
+            );
+            let num_visible = mesh
+                .positions
+                .chunks(4)
+                .filter(|corners| corners[0] != corners[3])
+                .count();
+            assert_eq!(num_visible, unit_quads.num_quads());
+            meshes.push(mesh);
+        }
+
+        assert_eq!(meshes[0].positions.len(), 32 * 32 * 32 * 6 * 4);
+        assert_eq!(meshes[0].indices, meshes[1].indices);
+        assert_eq!(meshes[0].normals, meshes[1].normals);
+        assert_eq!(meshes[0].tex_coords, meshes[1].tex_coords);
+    }
+
+    #[test]
+    fn edges_outline_a_single_block() {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1; 3]) as usize] = FULL;
+
+        let mut edges = Vec::new();
+        visible_block_edges(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut edges,
+            &DefaultVoxelContext,
+        );
+
+        // Each of the 6 faces is its own region with 4 edges.
+        assert_eq!(edges.len(), 24);
+    }
+
+    #[test]
+    fn edges_outline_a_coplanar_patch() {
+        // A 3x3 slab, 1 voxel thick.
+        let edges = slab_edges(&[]);
+
+        // The top and bottom are 3x3 regions with 12 edges each, and each side is a 3x1 region with 8 edges.
+        assert_eq!(edges.len(), 2 * 12 + 4 * 8);
+
+        // The top region contributes only its perimeter, none of the edges between its faces.
+        let mut top: Vec<_> = edges
+            .iter()
+            .filter(|[a, b]| a.y == 2.0 && b.y == 2.0)
+            .map(|&[a, b]| sorted_segment(a, b))
+            .collect();
+        top.sort_by(|s, t| s.partial_cmp(t).unwrap());
+        top.dedup();
+        assert_eq!(top.len(), 12);
+        for [a, b] in top {
+            let on_perimeter = |c: f32| c == 1.0 || c == 4.0;
+            assert!((on_perimeter(a[0]) && a[0] == b[0]) || (on_perimeter(a[2]) && a[2] == b[2]));
+        }
+    }
+
+    #[test]
+    fn convex_edges_are_emitted_for_each_region() {
+        let edges = slab_edges(&[]);
+
+        // Every edge around the top of the slab is shared by the top region and one of the side regions.
+        for x in 1..4 {
+            for z in [1.0, 4.0] {
+                let a = Vec3::new(x as f32, 2.0, z);
+                assert_eq!(count_segment(&edges, a, a + Vec3::X), 2);
+            }
+        }
+        for z in 1..4 {
+            for x in [1.0, 4.0] {
+                let a = Vec3::new(x, 2.0, z as f32);
+                assert_eq!(count_segment(&edges, a, a + Vec3::Z), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn concave_edges_are_emitted_for_each_region() {
+        // A wall along the back of the slab, which hides the top faces of the slab's back row.
+        let edges = slab_edges(&[[1, 2, 1], [2, 2, 1], [3, 2, 1]]);
+
+        // The crease between the top of the slab and the front of the wall is an edge of both regions.
+        for x in 1..4 {
+            let a = Vec3::new(x as f32, 2.0, 2.0);
+            assert_eq!(count_segment(&edges, a, a + Vec3::X), 2);
+        }
+        // Neither the hidden faces under the wall nor the coplanar sides of the wall and the slab produce any edges.
+        for x in 1..5 {
+            let a = Vec3::new(x as f32, 2.0, 1.0);
+            assert_eq!(count_segment(&edges, a, a + Vec3::Z), 0);
+        }
+    }
+
+    /// The edges of a 3x3 slab of voxels at `y = 1`, plus the voxels at `extra`.
+    fn slab_edges(extra: &[[u32; 3]]) -> Vec<[Vec3; 2]> {
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for x in 1..=3 {
+            for z in 1..=3 {
+                samples[shape.linearize([x, 1, z]) as usize] = FULL;
+            }
+        }
+        for &p in extra {
+            samples[shape.linearize(p) as usize] = FULL;
+        }
+
+        let mut edges = Vec::new();
+        visible_block_edges(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut edges,
+            &DefaultVoxelContext,
         );
+        edges
     }
 
-    type SampleShape = ConstShape3u32<34, 34, 34>;
+    fn sorted_segment(a: Vec3, b: Vec3) -> [[f32; 3]; 2] {
+        let (a, b) = (a.to_array(), b.to_array());
+        if a <= b {
+            [a, b]
+        } else {
+            [b, a]
+        }
+    }
 
-    /// Basic voxel type with one byte of texture layers
-    #[derive(Default, Clone, Copy, Eq, PartialEq)]
-    struct BoolVoxel(bool);
+    /// The number of times the segment from `a` to `b`, in either direction, is in `edges`.
+    fn count_segment(edges: &[[Vec3; 2]], a: Vec3, b: Vec3) -> usize {
+        edges
+            .iter()
+            .filter(|&&[c, d]| sorted_segment(c, d) == sorted_segment(a, b))
+            .count()
+    }
 
-    const EMPTY: BoolVoxel = BoolVoxel(false);
+    #[test]
+    fn one_sided_voxels_use_face_visibility() {
+        /// Voxels can't be seen through their bottom (-Y) face.
+        struct NoBottomContext;
 
-    impl Voxel for BoolVoxel {
-        fn get_visibility(&self) -> VoxelVisibility {
-            if *self == EMPTY {
-                VoxelVisibility::Empty
-            } else {
-                VoxelVisibility::Opaque
+        impl VoxelContext<BoolVoxel> for NoBottomContext {
+            fn get_visibility(&self, voxel: &BoolVoxel) -> VoxelVisibility {
+                voxel.get_visibility()
+            }
+
+            fn get_visibility_for_face(
+                &self,
+                voxel: &BoolVoxel,
+                face_index: usize,
+            ) -> VoxelVisibility {
+                if RIGHT_HANDED_Y_UP_CONFIG.face_direction(face_index) == SignedAxis::NegY {
+                    VoxelVisibility::Empty
+                } else {
+                    voxel.get_visibility()
+                }
             }
         }
+
+        let shape = SampleShape {};
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        samples[shape.linearize([1; 3]) as usize] = FULL;
+        samples[shape.linearize([1, 2, 1]) as usize] = FULL;
+
+        let mut buffer = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+            &NoBottomContext,
+        );
+
+        // 8 side faces, plus the top faces of both voxels, since the upper voxel is empty when seen from below.
+        assert_eq!(buffer.num_quads(), 10);
+        let top = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+        assert_eq!(buffer.groups[top].len(), 2);
     }
 }
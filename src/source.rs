@@ -0,0 +1,138 @@
+/// A linearly indexed store of voxels that can be meshed, e.g. a slice, a `Vec`, or a custom paged or compressed
+/// structure.
+///
+/// The meshing functions read voxels through this trait with the same linear indices as `voxels_shape.linearize`
+/// produces, so any `voxels` argument that is a slice today can also be a custom source.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// /// Voxels stored in fixed-size pages, so no contiguous array is ever allocated.
+/// struct PagedVoxels {
+///     pages: Vec<Vec<BoolVoxel>>,
+/// }
+///
+/// const PAGE_SIZE: usize = 64;
+///
+/// impl VoxelSource for PagedVoxels {
+///     type Voxel = BoolVoxel;
+///
+///     fn len(&self) -> usize {
+///         self.pages.iter().map(Vec::len).sum()
+///     }
+///
+///     unsafe fn get_unchecked(&self, index: usize) -> &BoolVoxel {
+///         self.pages
+///             .get_unchecked(index / PAGE_SIZE)
+///             .get_unchecked(index % PAGE_SIZE)
+///     }
+/// }
+///
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+/// let paged = PagedVoxels {
+///     pages: voxels.chunks(PAGE_SIZE).map(<[BoolVoxel]>::to_vec).collect(),
+/// };
+///
+/// let mut buffer = GreedyQuadsBuffer::new(ChunkShape::USIZE);
+/// greedy_quads(
+///     &paged,
+///     &ChunkShape {},
+///     [0; 3],
+///     [17; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// ```
+pub trait VoxelSource {
+    type Voxel;
+
+    /// The number of voxels in this source. Every index less than this must be readable.
+    fn len(&self) -> usize;
+
+    /// Returns `true` iff this source has no voxels.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the voxel at linear index `index` without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be less than [`VoxelSource::len`].
+    unsafe fn get_unchecked(&self, index: usize) -> &Self::Voxel;
+
+    /// Returns the voxel at linear index `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    fn voxel(&self, index: usize) -> &Self::Voxel {
+        assert!(
+            index < self.len(),
+            "voxel index {index} is out of bounds for {} voxels",
+            self.len()
+        );
+        unsafe { self.get_unchecked(index) }
+    }
+}
+
+impl<T> VoxelSource for [T] {
+    type Voxel = T;
+
+    #[inline]
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    #[inline]
+    unsafe fn get_unchecked(&self, index: usize) -> &T {
+        <[T]>::get_unchecked(self, index)
+    }
+}
+
+impl<T, const N: usize> VoxelSource for [T; N] {
+    type Voxel = T;
+
+    #[inline]
+    fn len(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    unsafe fn get_unchecked(&self, index: usize) -> &T {
+        <[T]>::get_unchecked(self, index)
+    }
+}
+
+impl<T> VoxelSource for Vec<T> {
+    type Voxel = T;
+
+    #[inline]
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    #[inline]
+    unsafe fn get_unchecked(&self, index: usize) -> &T {
+        <[T]>::get_unchecked(self, index)
+    }
+}
@@ -0,0 +1,315 @@
+use crate::bounds::assert_in_bounds;
+
+use ilattice::glam::{UVec3, Vec3};
+use ilattice::prelude::Extent;
+use ndshape::Shape;
+
+/// Marks a cell in [`SurfaceNetsBuffer::stride_to_index`] that doesn't contain a vertex.
+const NULL_VERTEX: u32 = u32::MAX;
+
+/// Contains the output from the [`surface_nets`] algorithm, a smooth triangle mesh.
+///
+/// This buffer can be reused between multiple calls of [`surface_nets`] in order to avoid reallocations.
+#[derive(Default)]
+pub struct SurfaceNetsBuffer {
+    /// The position of each vertex, in the same coordinates as the samples.
+    pub positions: Vec<[f32; 3]>,
+    /// The normal of each vertex, computed from the gradient of the samples. These are not normalized, since the
+    /// gradient is often useful as it is, e.g. for blending.
+    pub normals: Vec<[f32; 3]>,
+    /// Triangle indices into `positions` and `normals`, wound counterclockwise when viewed from outside of the surface
+    /// in a right-handed coordinate system.
+    pub indices: Vec<u32>,
+
+    /// The minimum corner of the cell containing each vertex.
+    pub surface_points: Vec<[u32; 3]>,
+    /// The linear index of the minimum corner of the cell containing each vertex.
+    pub surface_strides: Vec<u32>,
+
+    /// For each cell, indexed by the linear index of its minimum corner, the index of its vertex, or [`u32::MAX`] if it
+    /// has none.
+    stride_to_index: Vec<u32>,
+}
+
+impl SurfaceNetsBuffer {
+    /// Clears the buffer for sample arrays with `size` elements.
+    pub fn reset(&mut self, size: usize) {
+        self.positions.clear();
+        self.normals.clear();
+        self.indices.clear();
+        self.surface_points.clear();
+        self.surface_strides.clear();
+        self.stride_to_index.clear();
+        self.stride_to_index.resize(size, NULL_VERTEX);
+    }
+}
+
+/// The "Naive Surface Nets" algorithm, for meshing a signed distance field with a smooth surface instead of blocks.
+///
+/// `sdf` holds one signed distance sample per point of `sdf_shape`, where negative values are inside of the surface.
+/// Every cubic cell with corners in `[min, max]` whose corners don't all have the same sign gets one vertex, placed at
+/// the average of the points where the surface crosses the edges of the cell. Neighboring vertices are connected with
+/// quads, which are split into two triangles along their shorter diagonal.
+///
+/// Faces are only generated between cells with corners in `[min, max]`, so to mesh chunks without gaps between them,
+/// each chunk needs to overlap the next one by 1 sample on the maximum side, and the previous one by 1 sample on the
+/// minimum side. Unlike the block meshing algorithms, no part of `[min, max]` is treated as padding.
+///
+/// See the [0fps article](https://0fps.net/2012/07/12/smooth-voxel-terrain-part-2/) for an explanation.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // A sphere with a radius of 6 samples.
+/// let mut sdf = [1.0; ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     let [x, y, z] = ChunkShape::delinearize(i).map(|c| c as f32 - 8.5);
+///     sdf[i as usize] = (x * x + y * y + z * z).sqrt() - 6.0;
+/// }
+///
+/// let mut buffer = SurfaceNetsBuffer::default();
+/// surface_nets(&sdf, &ChunkShape {}, [0; 3], [17; 3], &mut buffer);
+/// assert!(!buffer.indices.is_empty());
+/// ```
+pub fn surface_nets<S>(
+    sdf: &[f32],
+    sdf_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    output: &mut SurfaceNetsBuffer,
+) where
+    S: Shape<3, Coord = u32>,
+{
+    assert_in_bounds(sdf, sdf_shape, min, max);
+
+    output.reset(sdf.len());
+
+    estimate_surface(sdf, sdf_shape, min, max, output);
+    make_all_quads(sdf, sdf_shape, min, max, output);
+}
+
+/// Finds the vertex of every cell that the surface passes through.
+fn estimate_surface<S>(
+    sdf: &[f32],
+    sdf_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    output: &mut SurfaceNetsBuffer,
+) where
+    S: Shape<3, Coord = u32>,
+{
+    // Cells are identified by their minimum corner, so the last sample on each axis has no cell.
+    let cells = Extent::from_min_and_max(UVec3::from(min), UVec3::from(max) - UVec3::ONE);
+    let corner_strides = CUBE_CORNERS.map(|corner| sdf_shape.linearize(corner));
+
+    for p in cells.iter3() {
+        let p_stride = sdf_shape.linearize(p.to_array());
+        let corner_dists = corner_strides.map(|stride| sdf[(p_stride + stride) as usize]);
+        if let Some((centroid, gradient)) = estimate_cell(&corner_dists) {
+            output.stride_to_index[p_stride as usize] = output.positions.len() as u32;
+            output.positions.push((p.as_vec3() + centroid).to_array());
+            output.normals.push(gradient.to_array());
+            output.surface_points.push(p.to_array());
+            output.surface_strides.push(p_stride);
+        }
+    }
+}
+
+/// Returns the average of the edge crossings of the cell, relative to its minimum corner, and the gradient of the
+/// samples, or `None` if the surface doesn't cross the cell.
+fn estimate_cell(corner_dists: &[f32; 8]) -> Option<(Vec3, Vec3)> {
+    let num_negative = corner_dists.iter().filter(|&&d| d < 0.0).count();
+    if num_negative == 0 || num_negative == 8 {
+        return None;
+    }
+
+    let mut sum = Vec3::ZERO;
+    let mut count = 0;
+    for [a, b] in CUBE_EDGES {
+        let (d1, d2) = (corner_dists[a], corner_dists[b]);
+        if (d1 < 0.0) != (d2 < 0.0) {
+            let t = d1 / (d1 - d2);
+            let (p1, p2) = (corner_position(a), corner_position(b));
+            sum += p1 + t * (p2 - p1);
+            count += 1;
+        }
+    }
+
+    // Each component is the sum of the differences along the 4 edges parallel to that axis.
+    let d = corner_dists;
+    let gradient = Vec3::new(
+        (d[1] - d[0]) + (d[3] - d[2]) + (d[5] - d[4]) + (d[7] - d[6]),
+        (d[2] - d[0]) + (d[3] - d[1]) + (d[6] - d[4]) + (d[7] - d[5]),
+        (d[4] - d[0]) + (d[5] - d[1]) + (d[6] - d[2]) + (d[7] - d[3]),
+    );
+
+    Some((sum / count as f32, gradient))
+}
+
+/// Connects the vertices around every edge that the surface crosses.
+fn make_all_quads<S>(
+    sdf: &[f32],
+    sdf_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    output: &mut SurfaceNetsBuffer,
+) where
+    S: Shape<3, Coord = u32>,
+{
+    let axis_strides = [[1, 0, 0], [0, 1, 0], [0, 0, 1]].map(|axis| sdf_shape.linearize(axis));
+
+    for (&p_stride, &p) in output
+        .surface_strides
+        .iter()
+        .zip(output.surface_points.iter())
+    {
+        // The edge along axis `i` starting at `p` is surrounded by the cells at `p`, `p - b`, `p - c`, and `p - b - c`,
+        // where `b` and `c` are the other axes, so those cells must exist. Edges starting on the last cell are left to
+        // the next chunk, which sees them from its minimum side, so overlapping chunks don't both emit them.
+        for i in 0..3 {
+            let (b, c) = ((i + 1) % 3, (i + 2) % 3);
+            if p[b] == min[b] || p[c] == min[c] || p[i] == max[i] - 1 {
+                continue;
+            }
+            maybe_make_quad(
+                sdf,
+                &output.stride_to_index,
+                &output.positions,
+                p_stride,
+                p_stride + axis_strides[i],
+                axis_strides[b],
+                axis_strides[c],
+                &mut output.indices,
+            );
+        }
+    }
+}
+
+/// Emits a quad around the edge from `p1` to `p2` if the surface crosses it.
+#[allow(clippy::too_many_arguments)]
+fn maybe_make_quad(
+    sdf: &[f32],
+    stride_to_index: &[u32],
+    positions: &[[f32; 3]],
+    p1: u32,
+    p2: u32,
+    axis_b_stride: u32,
+    axis_c_stride: u32,
+    indices: &mut Vec<u32>,
+) {
+    let (d1, d2) = (sdf[p1 as usize], sdf[p2 as usize]);
+    let negative_face = match (d1 < 0.0, d2 < 0.0) {
+        (true, false) => false,
+        (false, true) => true,
+        _ => return,
+    };
+
+    // The vertices, viewed with the edge pointing toward the viewer, look like this:
+    //
+    // v1 v3
+    // v2 v4
+    let v1 = stride_to_index[p1 as usize];
+    let v2 = stride_to_index[(p1 - axis_b_stride) as usize];
+    let v3 = stride_to_index[(p1 - axis_c_stride) as usize];
+    let v4 = stride_to_index[(p1 - axis_b_stride - axis_c_stride) as usize];
+    let position = |v: u32| Vec3::from(positions[v as usize]);
+
+    // Split the quad along its shorter diagonal.
+    let quad = if position(v1).distance_squared(position(v4))
+        < position(v2).distance_squared(position(v3))
+    {
+        if negative_face {
+            [v1, v4, v2, v1, v3, v4]
+        } else {
+            [v1, v2, v4, v1, v4, v3]
+        }
+    } else if negative_face {
+        [v2, v3, v4, v2, v1, v3]
+    } else {
+        [v2, v4, v3, v2, v3, v1]
+    };
+    indices.extend_from_slice(&quad);
+}
+
+/// The corners of a unit cube, where bit `i` of the index is the coordinate on axis `i`.
+const CUBE_CORNERS: [[u32; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+
+/// The edges of a unit cube, as pairs of indices into [`CUBE_CORNERS`].
+const CUBE_EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [2, 3],
+    [4, 5],
+    [6, 7],
+    [0, 2],
+    [1, 3],
+    [4, 6],
+    [5, 7],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+#[inline]
+fn corner_position(corner: usize) -> Vec3 {
+    UVec3::from(CUBE_CORNERS[corner]).as_vec3()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SampleShape;
+    use ndshape::ConstShape;
+
+    #[test]
+    fn sphere_is_closed_and_faces_outward() {
+        let shape = SampleShape {};
+        let center = Vec3::splat(16.5);
+        let radius = 10.0;
+        let mut sdf = [0.0; SampleShape::SIZE as usize];
+        for i in 0..SampleShape::SIZE {
+            let p = UVec3::from(shape.delinearize(i)).as_vec3();
+            sdf[i as usize] = p.distance(center) - radius;
+        }
+
+        let mut buffer = SurfaceNetsBuffer::default();
+        surface_nets(&sdf, &shape, [0; 3], [33; 3], &mut buffer);
+
+        assert!(!buffer.indices.is_empty());
+        assert_eq!(buffer.indices.len() % 3, 0);
+        for p in buffer.positions.iter() {
+            assert!((Vec3::from(*p).distance(center) - radius).abs() < 0.5);
+        }
+        for (p, n) in buffer.positions.iter().zip(buffer.normals.iter()) {
+            assert!(Vec3::from(*n).dot(Vec3::from(*p) - center) > 0.0);
+        }
+        for triangle in buffer.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(buffer.positions[triangle[i] as usize]));
+            let normal = (b - a).cross(c - a);
+            assert!(normal.dot((a + b + c) / 3.0 - center) > 0.0);
+        }
+
+        // A closed surface has every edge shared by exactly two triangles.
+        let mut edges = std::collections::HashMap::new();
+        for triangle in buffer.indices.chunks(3) {
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+        assert!(edges.values().all(|&count| count == 2));
+    }
+}
@@ -0,0 +1,61 @@
+//! Voxels and samples shared by the unit tests.
+
+use crate::{MergeVoxel, Voxel, VoxelVisibility};
+
+use ilattice::glam::{UVec3, Vec3};
+use ndshape::{ConstShape, ConstShape3u32};
+
+/// A 32^3 chunk with 1 voxel of padding on every side.
+pub type SampleShape = ConstShape3u32<34, 34, 34>;
+
+/// Occupancy and a merge value, so faces only merge between solid voxels with the same `.1`.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BoolVoxel(pub bool, pub bool);
+
+pub const EMPTY: BoolVoxel = BoolVoxel(false, false);
+pub const FULL: BoolVoxel = BoolVoxel(true, false);
+
+impl Voxel for BoolVoxel {
+    fn get_visibility(&self) -> VoxelVisibility {
+        if self.0 {
+            VoxelVisibility::Opaque
+        } else {
+            VoxelVisibility::Empty
+        }
+    }
+}
+
+impl MergeVoxel for BoolVoxel {
+    type MergeValue = Self;
+    type MergeValueFacingNeighbour = bool;
+
+    fn merge_value(&self) -> Self::MergeValue {
+        *self
+    }
+
+    fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+        true
+    }
+}
+
+/// Returns `f(p)` for every voxel of a [`SampleShape`], where `p` is the voxel's offset from the center of the shape.
+pub fn sample<T>(mut f: impl FnMut(Vec3) -> T) -> [T; SampleShape::USIZE] {
+    std::array::from_fn(|i| f(UVec3::from(SampleShape::delinearize(i as u32)).as_vec3() - 16.0))
+}
+
+/// A solid ball of radius 15 in the center of a [`SampleShape`].
+pub fn sphere() -> [BoolVoxel; SampleShape::USIZE] {
+    sample(|p| BoolVoxel(p.length() < 15.0, false))
+}
+
+/// Same as [`sphere`], but the halves above and below the center have different merge values, so no quad crosses
+/// between them.
+pub fn split_sphere() -> [BoolVoxel; SampleShape::USIZE] {
+    sample(|p| {
+        if p.length() < 15.0 {
+            BoolVoxel(true, p.y > 0.0)
+        } else {
+            EMPTY
+        }
+    })
+}
@@ -0,0 +1,235 @@
+//! Utilities for checking the correctness of generated meshes, e.g. in the tests of crates that use a custom
+//! [`QuadCoordinateConfig`](crate::QuadCoordinateConfig).
+
+use crate::{
+    visible_block_faces, GreedyQuadsBuffer, MeshData, OrientedBlockFace, UnitQuadBuffer,
+    VoxelContext, VoxelSource,
+};
+
+use ilattice::glam::{UVec3, Vec3};
+use ndshape::Shape;
+
+/// Checks that the winding of every triangle in `mesh` agrees with its vertex normals, i.e. that counterclockwise
+/// winding produces a geometric normal in the same hemisphere as the stored normal of the triangle's first vertex.
+///
+/// `mesh` is expected to have the layout produced by [`MeshData::push_quad`]: each quad is 4 consecutive vertices and 6
+/// consecutive indices. On failure, returns the index of every quad that has at least one inside-out triangle.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::*;
+/// # use block_mesh::testing::validate_outward_normals;
+/// let mut quads = QuadBuffer::new();
+/// for group in quads.groups.iter_mut() {
+///     group.push(UnorientedQuad { minimum: [1; 3], width: 1, height: 1 });
+/// }
+/// let mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+/// assert_eq!(validate_outward_normals(&mesh), Ok(()));
+/// ```
+pub fn validate_outward_normals(mesh: &MeshData) -> Result<(), Vec<usize>> {
+    let mut mismatched = Vec::new();
+    for (quad_index, quad_indices) in mesh.indices.chunks(6).enumerate() {
+        let inside_out = quad_indices.chunks(3).any(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.positions[triangle[i] as usize]));
+            let geometric_normal = (b - a).cross(c - a);
+            let normal = Vec3::from(mesh.normals[triangle[0] as usize]);
+            geometric_normal.dot(normal) <= 0.0
+        });
+        if inside_out {
+            mismatched.push(quad_index);
+        }
+    }
+
+    if mismatched.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatched)
+    }
+}
+
+impl GreedyQuadsBuffer {
+    /// Returns `true` iff the quads cover every face generated by [`visible_block_faces`] with the same arguments exactly
+    /// once, and nothing else.
+    ///
+    /// This runs the simple algorithm and checks every voxel face of every quad against its output, so it catches merge
+    /// strategies that drop faces, emit them twice, or grow quads over hidden faces. It's meant for tests; the quads are
+    /// expected to come from meshing `voxels` in `[min, max]` with the same `faces` and `ctx`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+    /// # use block_mesh::*;
+    /// # #[derive(Clone, Copy, Eq, PartialEq)]
+    /// # struct BoolVoxel(bool);
+    /// # impl Voxel for BoolVoxel {
+    /// #     fn get_visibility(&self) -> VoxelVisibility {
+    /// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+    /// #     }
+    /// # }
+    /// # impl MergeVoxel for BoolVoxel {
+    /// #     type MergeValue = bool;
+    /// #     type MergeValueFacingNeighbour = bool;
+    /// #     fn merge_value(&self) -> bool { self.0 }
+    /// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+    /// # }
+    /// type ChunkShape = ConstShape3u32<6, 6, 6>;
+    ///
+    /// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+    /// voxels[ChunkShape::linearize([1, 1, 1]) as usize] = BoolVoxel(true);
+    /// voxels[ChunkShape::linearize([2, 1, 1]) as usize] = BoolVoxel(true);
+    ///
+    /// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+    /// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+    /// greedy_quads(&voxels, &ChunkShape {}, [0; 3], [5; 3], faces, &mut buffer, &DefaultVoxelContext);
+    /// assert!(buffer.verify_full_coverage(&voxels, &ChunkShape {}, [0; 3], [5; 3], faces, &DefaultVoxelContext));
+    ///
+    /// // Dropping a quad leaves some faces uncovered.
+    /// buffer.quads.groups[0].pop();
+    /// assert!(!buffer.verify_full_coverage(&voxels, &ChunkShape {}, [0; 3], [5; 3], faces, &DefaultVoxelContext));
+    /// ```
+    pub fn verify_full_coverage<V, S, C>(
+        &self,
+        voxels: &V,
+        voxels_shape: &S,
+        min: [u32; 3],
+        max: [u32; 3],
+        faces: &[OrientedBlockFace; 6],
+        ctx: &C,
+    ) -> bool
+    where
+        V: VoxelSource + ?Sized,
+        S: Shape<3, Coord = u32>,
+        C: VoxelContext<V::Voxel>,
+    {
+        let mut expected = UnitQuadBuffer::new();
+        visible_block_faces(voxels, voxels_shape, min, max, faces, &mut expected, ctx);
+
+        let (min, max) = (UVec3::from(min), UVec3::from(max));
+        // For each voxel, whether its face is visible and whether a quad covers it.
+        let mut visible = vec![false; voxels.len()];
+        let mut covered = vec![false; voxels.len()];
+        for ((group, expected_group), face) in self
+            .quads
+            .groups
+            .iter()
+            .zip(expected.groups.iter())
+            .zip(faces.iter())
+        {
+            visible.fill(false);
+            covered.fill(false);
+            for unit_quad in expected_group.iter() {
+                visible[voxels_shape.linearize(unit_quad.minimum) as usize] = true;
+            }
+            for quad in group.iter() {
+                let minimum = UVec3::from(quad.minimum);
+                for v in 0..quad.height {
+                    for u in 0..quad.width {
+                        let p = minimum + face.u * u + face.v * v;
+                        if p.cmplt(min).any() || p.cmpgt(max).any() {
+                            return false;
+                        }
+                        let index = voxels_shape.linearize(p.to_array()) as usize;
+                        if !visible[index] || covered[index] {
+                            return false;
+                        }
+                        covered[index] = true;
+                    }
+                }
+            }
+            if visible != covered {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{split_sphere, SampleShape};
+    use crate::{greedy_quads, DefaultVoxelContext, UnorientedQuad, RIGHT_HANDED_Y_UP_CONFIG};
+
+    #[test]
+    fn both_diagonals_are_wound_outward() {
+        let quad = UnorientedQuad {
+            minimum: [1; 3],
+            width: 2,
+            height: 3,
+        };
+        for flip_diagonal in [false, true] {
+            let mut mesh = MeshData::new();
+            for face in RIGHT_HANDED_Y_UP_CONFIG.faces.iter() {
+                let start = mesh.positions.len() as u32;
+                mesh.indices
+                    .extend_from_slice(&face.quad_mesh_indices(start, flip_diagonal));
+                mesh.positions
+                    .extend_from_slice(&face.quad_mesh_positions(&quad, 1.0));
+                mesh.normals.extend_from_slice(&face.quad_mesh_normals());
+            }
+            assert_eq!(validate_outward_normals(&mesh), Ok(()));
+        }
+    }
+
+    #[test]
+    fn detects_inside_out_quads() {
+        let quad = UnorientedQuad {
+            minimum: [1; 3],
+            width: 1,
+            height: 1,
+        };
+        let mut mesh = MeshData::new();
+        for face in RIGHT_HANDED_Y_UP_CONFIG.faces.iter() {
+            mesh.push_quad(face, &quad, RIGHT_HANDED_Y_UP_CONFIG.u_flip_face, 1.0);
+        }
+        // Flip the normal of the second quad.
+        for normal in &mut mesh.normals[4..8] {
+            *normal = (-Vec3::from(*normal)).to_array();
+        }
+        assert_eq!(validate_outward_normals(&mesh), Err(vec![1]));
+    }
+
+    #[test]
+    fn greedy_quads_cover_visible_faces_exactly_once() {
+        let shape = SampleShape {};
+        let samples = split_sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let verify = |buffer: &GreedyQuadsBuffer| {
+            buffer.verify_full_coverage(
+                &samples,
+                &shape,
+                [0; 3],
+                [33; 3],
+                faces,
+                &DefaultVoxelContext,
+            )
+        };
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert!(verify(&buffer));
+
+        // A duplicated quad overlaps itself.
+        let mut duplicated = GreedyQuadsBuffer::new(samples.len());
+        duplicated.append(&buffer);
+        let quad = duplicated.quads.groups[2][0];
+        duplicated.quads.groups[2].push(quad);
+        assert!(!verify(&duplicated));
+
+        // A grown quad covers hidden faces.
+        let mut grown = GreedyQuadsBuffer::new(samples.len());
+        grown.append(&buffer);
+        grown.quads.groups[3][0].width += 1;
+        assert!(!verify(&grown));
+    }
+}
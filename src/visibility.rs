@@ -0,0 +1,458 @@
+use crate::{
+    bounds::assert_in_bounds, geometry::opposite_face_indices, is_occupied, shell::interior_extent,
+    simple::face_is_visible_for_face, GreedyMeshPlan, GreedyQuadsBuffer, GreedyQuadsConfig,
+    MergeVoxelContext, OrientedBlockFace, UnitQuadBuffer, UnorientedUnitQuad, VoxelContext,
+    VoxelMerger, VoxelSource,
+};
+
+use ndshape::Shape;
+
+/// The visible faces found by [`visible_block_faces_with_visibility_mask`], kept so [`greedy_quads_with_visibility_mask`]
+/// doesn't have to find them again.
+///
+/// Pipelines that need both the unit quads, e.g. for a coarse face count, and the greedy mesh otherwise check the
+/// neighbors of every voxel twice. With the mask, greedy meshing skips every voxel without a visible face in the
+/// current direction without reading its neighbor, so only the voxels on the surface are checked again while merging.
+/// The output is the same as without the mask.
+///
+/// The mask is only valid for the voxels, extent, and faces it was computed from. It can be reused for other arrays of
+/// the same size to avoid reallocating.
+#[derive(Default)]
+pub struct VisibilityMask {
+    /// `hidden[i][index]` is `true` iff face `i` of the voxel at `index` is not visible.
+    hidden: [Vec<bool>; 6],
+}
+
+impl VisibilityMask {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` iff face `face_index` of the voxel at linear index `index` is visible.
+    #[inline]
+    pub fn is_visible(&self, face_index: usize, index: u32) -> bool {
+        !self.hidden[face_index][index as usize]
+    }
+
+    /// The number of visible faces in each face group.
+    pub fn num_visible_faces(&self) -> [usize; 6] {
+        [0, 1, 2, 3, 4, 5].map(|i| self.hidden[i].iter().filter(|&&h| !h).count())
+    }
+
+    /// Marks every face of `size` voxels as hidden.
+    fn reset(&mut self, size: usize) {
+        for hidden in self.hidden.iter_mut() {
+            hidden.clear();
+            hidden.resize(size, true);
+        }
+    }
+}
+
+/// Same as [`visible_block_faces`](crate::visible_block_faces), but also records every visible face in `mask`, which
+/// can then be passed to [`greedy_quads_with_visibility_mask`].
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // A 2x2x2 cube.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for i in 0..ChunkShape::SIZE {
+///     let p = ChunkShape::delinearize(i);
+///     voxels[i as usize] = BoolVoxel(p.iter().all(|&c| (1..=2).contains(&c)));
+/// }
+///
+/// let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+/// let mut mask = VisibilityMask::new();
+/// let mut unit_quads = UnitQuadBuffer::new();
+/// visible_block_faces_with_visibility_mask(
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [17; 3],
+///     faces,
+///     &mut unit_quads,
+///     &mut mask,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(unit_quads.num_quads(), 6 * 4);
+/// assert_eq!(mask.num_visible_faces(), [4; 6]);
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_with_visibility_mask(
+///     &mask,
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [17; 3],
+///     faces,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(buffer.quads.num_quads(), 6);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn visible_block_faces_with_visibility_mask<T, S, C>(
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut UnitQuadBuffer,
+    mask: &mut VisibilityMask,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: VoxelContext<T>,
+{
+    assert_in_bounds(voxels, voxels_shape, min, max);
+    mask.reset(voxels.len());
+
+    let interior = interior_extent(min, max);
+    let kernel_strides =
+        faces.map(|face| voxels_shape.linearize(face.signed_normal().as_uvec3().to_array()));
+    let opposite_faces = opposite_face_indices(faces);
+
+    for p in interior.iter3() {
+        let p_array = p.to_array();
+        let p_index = voxels_shape.linearize(p_array);
+        let p_voxel = &voxels[p_index as usize];
+
+        for (face_index, face_stride) in kernel_strides.into_iter().enumerate() {
+            let neighbor_voxel = &voxels[p_index.wrapping_add(face_stride) as usize];
+            if face_is_visible_for_face(
+                p_voxel,
+                neighbor_voxel,
+                face_index,
+                opposite_faces[face_index],
+                ctx,
+            ) {
+                output.groups[face_index].push(UnorientedUnitQuad { minimum: p_array });
+                mask.hidden[face_index][p_index as usize] = false;
+            }
+        }
+    }
+}
+
+/// Same as [`greedy_quads`](crate::greedy_quads), but skips the voxel faces that are hidden in `mask`.
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_visibility_mask<T, S, C>(
+    mask: &VisibilityMask,
+    voxels: &[T],
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<T>,
+{
+    assert!(
+        mask.hidden
+            .iter()
+            .all(|hidden| hidden.len() == voxels.len()),
+        "The visibility mask was computed for a different voxel array"
+    );
+
+    let skip = [0, 1, 2, 3, 4, 5].map(|i| Some(mask.hidden[i].as_slice()));
+    GreedyMeshPlan::new(voxels_shape, faces, min, max).mesh_skipping::<_, VoxelMerger<T>, _>(
+        voxels,
+        &GreedyQuadsConfig::default(),
+        skip,
+        output,
+        None,
+        ctx,
+    );
+}
+
+/// Faces that are known to be hidden behind other geometry, e.g. from GPU occlusion queries on a previous frame, for
+/// [`greedy_quads_with_occlusion_mask`].
+///
+/// Unlike a [`VisibilityMask`], this can hide faces that are visible within the chunk, so the mesh only contains the
+/// faces that weren't occluded.
+#[derive(Clone, Debug, Default)]
+pub struct OcclusionMask {
+    /// `occluded[i][index]` is `true` iff face `i` of the voxel at `index` should not be meshed.
+    occluded: [Vec<bool>; 6],
+}
+
+impl OcclusionMask {
+    /// A mask for `size` voxels where no face is occluded.
+    pub fn new(size: usize) -> Self {
+        Self {
+            occluded: [(); 6].map(|_| vec![false; size]),
+        }
+    }
+
+    /// A mask for `size` voxels where face `i` of the voxel at linear index `index` is occluded iff that voxel is set
+    /// in the packed bits `occluded[i]`, using the same layout as [`is_occupied`](crate::is_occupied).
+    pub fn from_packed_bits(occluded: [&[u64]; 6], size: usize) -> Self {
+        Self {
+            occluded: occluded.map(|bits| {
+                assert!(
+                    bits.len() * 64 >= size,
+                    "{} words of bits can't cover {} voxels",
+                    bits.len(),
+                    size
+                );
+                (0..size as u32).map(|i| is_occupied(bits, i)).collect()
+            }),
+        }
+    }
+
+    /// Returns `true` iff face `face_index` of the voxel at linear index `index` is occluded.
+    #[inline]
+    pub fn is_occluded(&self, face_index: usize, index: u32) -> bool {
+        self.occluded[face_index][index as usize]
+    }
+
+    /// Sets whether face `face_index` of the voxel at linear index `index` is occluded.
+    #[inline]
+    pub fn set_occluded(&mut self, face_index: usize, index: u32, occluded: bool) {
+        self.occluded[face_index][index as usize] = occluded;
+    }
+}
+
+/// Same as [`greedy_quads`](crate::greedy_quads), but leaves out the voxel faces that are occluded in `mask`.
+///
+/// Occluded faces are treated as if they were already meshed, so no quad covers them and the remaining faces are
+/// merged around them.
+///
+/// # Example
+///
+/// ```
+/// # use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+/// # use block_mesh::*;
+/// # #[derive(Clone, Copy, Eq, PartialEq)]
+/// # struct BoolVoxel(bool);
+/// # impl Voxel for BoolVoxel {
+/// #     fn get_visibility(&self) -> VoxelVisibility {
+/// #         if self.0 { VoxelVisibility::Opaque } else { VoxelVisibility::Empty }
+/// #     }
+/// # }
+/// # impl MergeVoxel for BoolVoxel {
+/// #     type MergeValue = bool;
+/// #     type MergeValueFacingNeighbour = bool;
+/// #     fn merge_value(&self) -> bool { self.0 }
+/// #     fn merge_value_facing_neighbour(&self) -> bool { self.0 }
+/// # }
+/// type ChunkShape = ConstShape3u32<18, 18, 18>;
+///
+/// // A row of 3 blocks along X.
+/// let mut voxels = [BoolVoxel(false); ChunkShape::SIZE as usize];
+/// for x in 1..=3 {
+///     voxels[ChunkShape::linearize([x, 1, 1]) as usize] = BoolVoxel(true);
+/// }
+///
+/// // The top of the middle block is hidden.
+/// let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+/// let mut mask = OcclusionMask::new(voxels.len());
+/// mask.set_occluded(pos_y, ChunkShape::linearize([2, 1, 1]), true);
+///
+/// let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+/// greedy_quads_with_occlusion_mask(
+///     &mask,
+///     &voxels,
+///     &ChunkShape {},
+///     [0; 3],
+///     [17; 3],
+///     &RIGHT_HANDED_Y_UP_CONFIG.faces,
+///     &mut buffer,
+///     &DefaultVoxelContext,
+/// );
+/// assert_eq!(buffer.quads.groups[pos_y].len(), 2);
+/// assert_eq!(buffer.quads.num_quads(), 7);
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_with_occlusion_mask<V, S, C>(
+    mask: &OcclusionMask,
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    faces: &[OrientedBlockFace; 6],
+    output: &mut GreedyQuadsBuffer,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+{
+    assert!(
+        mask.occluded
+            .iter()
+            .all(|occluded| occluded.len() == voxels.len()),
+        "The occlusion mask was created for a different voxel array"
+    );
+
+    let skip = [0, 1, 2, 3, 4, 5].map(|i| Some(mask.occluded[i].as_slice()));
+    GreedyMeshPlan::new(voxels_shape, faces, min, max)
+        .mesh_skipping::<_, VoxelMerger<V::Voxel>, _>(
+            voxels,
+            &GreedyQuadsConfig::default(),
+            skip,
+            output,
+            None,
+            ctx,
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{split_sphere, SampleShape};
+    use crate::{
+        greedy_quads, visible_block_faces, DefaultVoxelContext, SignedAxis,
+        RIGHT_HANDED_Y_UP_CONFIG,
+    };
+    use ilattice::glam::UVec3;
+    use ndshape::ConstShape;
+
+    #[test]
+    fn visibility_mask_matches_full_meshing() {
+        let shape = SampleShape {};
+        let samples = split_sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        let mut mask = VisibilityMask::new();
+        let mut unit = UnitQuadBuffer::new();
+        visible_block_faces_with_visibility_mask(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut unit,
+            &mut mask,
+            &DefaultVoxelContext,
+        );
+        let mut expected_unit = UnitQuadBuffer::new();
+        visible_block_faces(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected_unit,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(unit.groups, expected_unit.groups);
+        assert_eq!(
+            mask.num_visible_faces(),
+            [0, 1, 2, 3, 4, 5].map(|i| unit.groups[i].len())
+        );
+        for (face_index, group) in unit.groups.iter().enumerate() {
+            for quad in group.iter() {
+                assert!(mask.is_visible(face_index, shape.linearize(quad.minimum)));
+            }
+        }
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_visibility_mask(
+            &mask,
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(buffer.quads.groups, expected.quads.groups);
+    }
+
+    #[test]
+    fn occluded_faces_are_left_out() {
+        let shape = SampleShape {};
+        let samples = split_sphere();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let pos_y = RIGHT_HANDED_Y_UP_CONFIG.face_index(SignedAxis::PosY);
+
+        // Occlude the top faces of every voxel with x < 16.
+        let mut mask = OcclusionMask::new(samples.len());
+        for i in 0..SampleShape::SIZE {
+            if shape.delinearize(i)[0] < 16 {
+                mask.set_occluded(pos_y, i, true);
+            }
+        }
+
+        let mut expected = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut expected,
+            &DefaultVoxelContext,
+        );
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        greedy_quads_with_occlusion_mask(
+            &mask,
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            faces,
+            &mut buffer,
+            &DefaultVoxelContext,
+        );
+
+        for face_index in (0..6).filter(|&i| i != pos_y) {
+            assert_eq!(
+                buffer.quads.groups[face_index],
+                expected.quads.groups[face_index]
+            );
+        }
+        let face = &faces[pos_y];
+        let mut num_faces = 0;
+        for quad in buffer.quads.groups[pos_y].iter() {
+            for u in 0..quad.width {
+                for v in 0..quad.height {
+                    let p = UVec3::from(quad.minimum) + u * face.u + v * face.v;
+                    assert!(!mask.is_occluded(pos_y, shape.linearize(p.to_array())));
+                    num_faces += 1;
+                }
+            }
+        }
+        let num_unoccluded = expected.quads.groups[pos_y]
+            .iter()
+            .flat_map(|quad| {
+                (0..quad.width).flat_map(move |u| {
+                    (0..quad.height)
+                        .map(move |v| UVec3::from(quad.minimum) + u * face.u + v * face.v)
+                })
+            })
+            .filter(|p| p.x >= 16)
+            .count();
+        assert_eq!(num_faces, num_unoccluded);
+    }
+}
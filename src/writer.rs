@@ -0,0 +1,159 @@
+use crate::{
+    greedy_quads, GreedyQuadsBuffer, MergeVoxelContext, QuadBuffer, QuadCoordinateConfig,
+    VoxelSource,
+};
+
+use ilattice::glam::Vec3;
+use ndshape::Shape;
+
+/// A destination for mesh vertices and triangle indices, e.g. a memory-mapped GPU buffer, so that a mesh can be written
+/// without first being collected into a [`MeshData`](crate::MeshData).
+///
+/// Vertices are written in the same order as they are in [`MeshData`](crate::MeshData), and indices refer to the
+/// vertices written by the same call, starting from 0 for its first vertex.
+pub trait QuadWriter {
+    /// Writes the next vertex.
+    fn write_vertex(&mut self, position: [f32; 3], normal: [f32; 3], uv: [f32; 2]);
+
+    /// Writes the next triangle index.
+    fn write_index(&mut self, index: u32);
+}
+
+impl QuadBuffer {
+    /// Writes the same vertices and indices as [`QuadBuffer::to_mesh_data_with_options`] with the given `voxel_size`
+    /// and `origin` into `writer`, 4 vertices and then 6 indices for each quad.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use block_mesh::*;
+    /// # use block_mesh::ilattice::glam::Vec3;
+    /// /// Interleaves the vertex attributes like a typical vertex buffer.
+    /// #[derive(Default)]
+    /// struct Interleaved {
+    ///     vertices: Vec<[f32; 8]>,
+    ///     indices: Vec<u32>,
+    /// }
+    ///
+    /// impl QuadWriter for Interleaved {
+    ///     fn write_vertex(&mut self, [x, y, z]: [f32; 3], [nx, ny, nz]: [f32; 3], [u, v]: [f32; 2]) {
+    ///         self.vertices.push([x, y, z, nx, ny, nz, u, v]);
+    ///     }
+    ///
+    ///     fn write_index(&mut self, index: u32) {
+    ///         self.indices.push(index);
+    ///     }
+    /// }
+    ///
+    /// let mut quads = QuadBuffer::new();
+    /// quads.groups[0].push(UnorientedQuad { minimum: [1; 3], width: 2, height: 3 });
+    ///
+    /// let mut writer = Interleaved::default();
+    /// quads.write_mesh(&RIGHT_HANDED_Y_UP_CONFIG, 1.0, Vec3::ZERO, &mut writer);
+    /// let mesh = quads.to_mesh_data(&RIGHT_HANDED_Y_UP_CONFIG, 1.0);
+    /// assert_eq!(writer.indices, mesh.indices);
+    /// assert_eq!(writer.vertices[0][..3], mesh.positions[0]);
+    /// ```
+    pub fn write_mesh<W>(
+        &self,
+        config: &QuadCoordinateConfig,
+        voxel_size: f32,
+        origin: Vec3,
+        writer: &mut W,
+    ) where
+        W: QuadWriter + ?Sized,
+    {
+        let mut start = 0;
+        for (group, face) in self.groups.iter().zip(config.faces.iter()) {
+            let normals = face.quad_mesh_normals();
+            for quad in group.iter() {
+                let positions = face.quad_mesh_positions_at(quad, voxel_size, origin);
+                let tex_coords = face.tex_coords(config.u_flip_face, true, quad);
+                for ((position, normal), uv) in positions.into_iter().zip(normals).zip(tex_coords) {
+                    writer.write_vertex(position, normal, uv);
+                }
+                for index in face.quad_mesh_indices(start, false) {
+                    writer.write_index(index);
+                }
+                start += 4;
+            }
+        }
+    }
+}
+
+/// Runs [`greedy_quads`] and writes the resulting mesh straight into `writer` with [`QuadBuffer::write_mesh`], so the
+/// vertices are never collected on the CPU.
+///
+/// Only the compact quads are kept in `buffer`, which can be reused between calls to avoid allocating. Afterwards,
+/// [`QuadBuffer::vertex_count`] and [`QuadBuffer::index_count`] of `buffer.quads` tell how much was written.
+#[allow(clippy::too_many_arguments)]
+pub fn greedy_quads_to_writer<V, S, C, W>(
+    voxels: &V,
+    voxels_shape: &S,
+    min: [u32; 3],
+    max: [u32; 3],
+    config: &QuadCoordinateConfig,
+    voxel_size: f32,
+    buffer: &mut GreedyQuadsBuffer,
+    writer: &mut W,
+    ctx: &C,
+) where
+    V: VoxelSource + ?Sized,
+    S: Shape<3, Coord = u32>,
+    C: MergeVoxelContext<V::Voxel>,
+    W: QuadWriter + ?Sized,
+{
+    greedy_quads(voxels, voxels_shape, min, max, &config.faces, buffer, ctx);
+    buffer
+        .quads
+        .write_mesh(config, voxel_size, Vec3::ZERO, writer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{sphere, SampleShape};
+    use crate::{DefaultVoxelContext, MeshData, RIGHT_HANDED_Y_UP_CONFIG};
+
+    /// Writes into the attributes of a [`MeshData`], ignoring the extents and colors.
+    impl QuadWriter for MeshData {
+        fn write_vertex(&mut self, position: [f32; 3], normal: [f32; 3], uv: [f32; 2]) {
+            self.positions.push(position);
+            self.normals.push(normal);
+            self.tex_coords.push(uv);
+        }
+
+        fn write_index(&mut self, index: u32) {
+            self.indices.push(index);
+        }
+    }
+
+    #[test]
+    fn writer_matches_mesh_data() {
+        let shape = SampleShape {};
+        let samples = sphere();
+        let config = &RIGHT_HANDED_Y_UP_CONFIG;
+
+        let mut buffer = GreedyQuadsBuffer::new(samples.len());
+        let mut written = MeshData::new();
+        greedy_quads_to_writer(
+            &samples,
+            &shape,
+            [0; 3],
+            [33; 3],
+            config,
+            0.5,
+            &mut buffer,
+            &mut written,
+            &DefaultVoxelContext,
+        );
+        assert_eq!(written.positions.len(), buffer.quads.vertex_count());
+        assert_eq!(written.indices.len(), buffer.quads.index_count());
+
+        let expected = buffer.quads.to_mesh_data(config, 0.5);
+        assert_eq!(written.positions, expected.positions);
+        assert_eq!(written.normals, expected.normals);
+        assert_eq!(written.tex_coords, expected.tex_coords);
+        assert_eq!(written.indices, expected.indices);
+    }
+}